@@ -70,8 +70,8 @@ impl SignableTransaction {
           previous_output: OutPoint::default(),
           // This is empty for a Taproot spend
           script_sig: ScriptBuf::new(),
-          // This is fixed size, yet we do use Sequence::MAX
-          sequence: Sequence::MAX,
+          // This is fixed size, yet we do use Sequence::ENABLE_RBF_NO_LOCKTIME
+          sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
           // Our witnesses contains a single 64-byte signature
           witness: Witness::from_slice(&[vec![0; 64]])
         };
@@ -139,7 +139,8 @@ impl SignableTransaction {
       .map(|input| TxIn {
         previous_output: input.outpoint,
         script_sig: ScriptBuf::new(),
-        sequence: Sequence::MAX,
+        // Signal replaceability so the transaction can be re-signed with a higher fee if it lingers
+        sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
         witness: Witness::new(),
       })
       .collect::<Vec<_>>();
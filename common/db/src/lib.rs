@@ -1,12 +1,14 @@
 use core::fmt::Debug;
 use std::{
   sync::{Arc, RwLock},
-  collections::{HashSet, HashMap},
+  collections::{BTreeSet, BTreeMap},
 };
 
 /// An object implementing get.
 pub trait Get: Send + Sync + Debug {
   fn get(&self, key: impl AsRef<[u8]>) -> Option<Vec<u8>>;
+  /// Get every key-value pair whose key starts with the specified prefix, ordered by key.
+  fn get_prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<(Vec<u8>, Vec<u8>)>;
 }
 
 /// An atomic database operation.
@@ -14,6 +16,12 @@ pub trait Get: Send + Sync + Debug {
 pub trait DbTxn: Send + Sync + Debug + Get {
   fn put(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>);
   fn del(&mut self, key: impl AsRef<[u8]>);
+  /// Delete every key which starts with the specified prefix.
+  fn del_prefix(&mut self, prefix: impl AsRef<[u8]>) {
+    for (key, _) in self.get_prefix(prefix) {
+      self.del(key);
+    }
+  }
   fn commit(self);
 }
 
@@ -31,7 +39,7 @@ pub trait Db: 'static + Send + Sync + Clone + Debug + Get {
 /// An atomic operation for the in-memory databae.
 #[must_use]
 #[derive(PartialEq, Eq, Debug)]
-pub struct MemDbTxn<'a>(&'a MemDb, HashMap<Vec<u8>, Vec<u8>>, HashSet<Vec<u8>>);
+pub struct MemDbTxn<'a>(&'a MemDb, BTreeMap<Vec<u8>, Vec<u8>>, BTreeSet<Vec<u8>>);
 
 impl<'a> Get for MemDbTxn<'a> {
   fn get(&self, key: impl AsRef<[u8]>) -> Option<Vec<u8>> {
@@ -40,6 +48,17 @@ impl<'a> Get for MemDbTxn<'a> {
     }
     self.1.get(key.as_ref()).cloned().or(self.0 .0.read().unwrap().get(key.as_ref()).cloned())
   }
+  fn get_prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let prefix = prefix.as_ref();
+    let mut res = self
+      .0
+      .get_prefix(prefix)
+      .into_iter()
+      .filter(|(key, _)| !self.2.contains(key))
+      .collect::<BTreeMap<_, _>>();
+    res.extend(prefix_range(&self.1, prefix).map(|(key, value)| (key.clone(), value.clone())));
+    res.into_iter().collect()
+  }
 }
 impl<'a> DbTxn for MemDbTxn<'a> {
   fn put(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
@@ -50,9 +69,9 @@ impl<'a> DbTxn for MemDbTxn<'a> {
     self.1.remove(key.as_ref());
     self.2.insert(key.as_ref().to_vec());
   }
-  fn commit(mut self) {
+  fn commit(self) {
     let mut db = self.0 .0.write().unwrap();
-    for (key, value) in self.1.drain() {
+    for (key, value) in self.1 {
       db.insert(key, value);
     }
     for key in self.2 {
@@ -61,9 +80,17 @@ impl<'a> DbTxn for MemDbTxn<'a> {
   }
 }
 
+// Iterate over every entry in the map whose key starts with the prefix
+fn prefix_range<'a>(
+  map: &'a BTreeMap<Vec<u8>, Vec<u8>>,
+  prefix: &'a [u8],
+) -> impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)> {
+  map.range(prefix.to_vec() ..).take_while(move |(key, _)| key.starts_with(prefix))
+}
+
 /// An in-memory database.
 #[derive(Clone, Debug)]
-pub struct MemDb(Arc<RwLock<HashMap<Vec<u8>, Vec<u8>>>>);
+pub struct MemDb(Arc<RwLock<BTreeMap<Vec<u8>, Vec<u8>>>>);
 
 impl PartialEq for MemDb {
   fn eq(&self, other: &MemDb) -> bool {
@@ -74,7 +101,7 @@ impl Eq for MemDb {}
 
 impl Default for MemDb {
   fn default() -> MemDb {
-    MemDb(Arc::new(RwLock::new(HashMap::new())))
+    MemDb(Arc::new(RwLock::new(BTreeMap::new())))
   }
 }
 
@@ -89,12 +116,22 @@ impl Get for MemDb {
   fn get(&self, key: impl AsRef<[u8]>) -> Option<Vec<u8>> {
     self.0.read().unwrap().get(key.as_ref()).cloned()
   }
+  fn get_prefix(&self, prefix: impl AsRef<[u8]>) -> Vec<(Vec<u8>, Vec<u8>)> {
+    prefix_range(&self.0.read().unwrap(), prefix.as_ref())
+      .map(|(key, value)| (key.clone(), value.clone()))
+      .collect()
+  }
 }
 impl Db for MemDb {
   type Transaction<'a> = MemDbTxn<'a>;
   fn txn(&mut self) -> MemDbTxn<'_> {
-    MemDbTxn(self, HashMap::new(), HashSet::new())
+    MemDbTxn(self, BTreeMap::new(), BTreeSet::new())
   }
 }
 
-// TODO: Also bind RocksDB
+// TODO: Also bind RocksDB. Its binding will need to implement get_prefix, which should be done
+// with a prefix iterator over the committed state, overlaid with the transaction's pending writes
+// and deletions as MemDbTxn does
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,78 @@
+use crate::*;
+
+fn kv(key: &[u8], value: &[u8]) -> (Vec<u8>, Vec<u8>) {
+  (key.to_vec(), value.to_vec())
+}
+
+#[test]
+fn get_prefix() {
+  let mut db = MemDb::new();
+  let mut txn = db.txn();
+  txn.put(b"ab", b"1");
+  txn.put(b"abc", b"2");
+  txn.put(b"abd", b"3");
+  txn.put(b"ac", b"4");
+  txn.put(b"b", b"5");
+  txn.commit();
+
+  // Only keys with the prefix should be returned, ordered by key
+  assert_eq!(db.get_prefix(b"ab"), vec![kv(b"ab", b"1"), kv(b"abc", b"2"), kv(b"abd", b"3")]);
+  assert_eq!(db.get_prefix(b"abc"), vec![kv(b"abc", b"2")]);
+  assert_eq!(db.get_prefix(b"a").len(), 4);
+  assert_eq!(db.get_prefix(b"").len(), 5);
+  assert!(db.get_prefix(b"c").is_empty());
+}
+
+#[test]
+fn txn_get_prefix() {
+  let mut db = MemDb::new();
+  let mut txn = db.txn();
+  txn.put(b"ab", b"1");
+  txn.put(b"abc", b"2");
+  txn.commit();
+
+  // MemDb is a handle to a shared database, so this clone can read while the transaction is open
+  let reader = db.clone();
+  let mut txn = db.txn();
+  // Uncommitted puts should be visible through the transaction, including overwrites
+  txn.put(b"abd", b"3");
+  txn.put(b"ab", b"4");
+  // As should uncommitted deletions
+  txn.del(b"abc");
+  assert_eq!(txn.get_prefix(b"ab"), vec![kv(b"ab", b"4"), kv(b"abd", b"3")]);
+
+  // A deletion followed by a put should leave the put value
+  txn.del(b"abd");
+  assert_eq!(txn.get_prefix(b"ab"), vec![kv(b"ab", b"4")]);
+  txn.put(b"abc", b"5");
+  assert_eq!(txn.get_prefix(b"ab"), vec![kv(b"ab", b"4"), kv(b"abc", b"5")]);
+  assert_eq!(txn.get(b"abc"), Some(b"5".to_vec()));
+
+  // None of this should be visible until committed
+  assert_eq!(reader.get_prefix(b"ab"), vec![kv(b"ab", b"1"), kv(b"abc", b"2")]);
+  txn.commit();
+  assert_eq!(db.get_prefix(b"ab"), vec![kv(b"ab", b"4"), kv(b"abc", b"5")]);
+}
+
+#[test]
+fn del_prefix() {
+  let mut db = MemDb::new();
+  let mut txn = db.txn();
+  txn.put(b"ab", b"1");
+  txn.put(b"abc", b"2");
+  txn.put(b"ac", b"3");
+  txn.commit();
+
+  let mut txn = db.txn();
+  // Uncommitted puts should also be deleted
+  txn.put(b"abd", b"4");
+  txn.del_prefix(b"ab");
+  assert!(txn.get_prefix(b"ab").is_empty());
+  assert_eq!(txn.get(b"abd"), None);
+  assert_eq!(txn.get_prefix(b"a"), vec![kv(b"ac", b"3")]);
+
+  // A put after the deletion should be kept
+  txn.put(b"abc", b"5");
+  txn.commit();
+  assert_eq!(db.get_prefix(b"a"), vec![kv(b"abc", b"5"), kv(b"ac", b"3")]);
+}
@@ -1,5 +1,12 @@
+use scale::{Encode, Decode};
+
 pub use serai_db::*;
 
+use serai_client::{
+  primitives::BlockHash,
+  validator_sets::primitives::{ValidatorSet, KeyPair},
+};
+
 use crate::tributary::TributarySpec;
 
 #[derive(Debug)]
@@ -39,6 +46,51 @@ impl<'a, D: Db> MainDb<'a, D> {
     spec.write(&mut existing_bytes).unwrap();
     let mut txn = self.0.txn();
     txn.put(key, existing_bytes);
+    txn.put(Self::set_tributary_key(spec.set()), spec.genesis());
     txn.commit();
   }
+
+  fn set_tributary_key(set: ValidatorSet) -> Vec<u8> {
+    Self::main_key(b"set_tributary", set.encode())
+  }
+  pub fn set_tributary<G: Get>(getter: &G, set: ValidatorSet) -> Option<[u8; 32]> {
+    getter.get(Self::set_tributary_key(set)).map(|genesis| genesis.try_into().unwrap())
+  }
+
+  fn set_keys_key(set: ValidatorSet) -> Vec<u8> {
+    Self::main_key(b"set_keys", set.encode())
+  }
+  fn key_set_key(key: &[u8]) -> Vec<u8> {
+    Self::main_key(b"key_set", key)
+  }
+  // Save the key pair Serai confirmed for a validator set
+  // Both the Substrate key and the network key are mapped back to the set, as the processor
+  // identifies batch signing by the former and transaction signing by the latter
+  pub fn save_set_keys(txn: &mut D::Transaction<'_>, set: ValidatorSet, key_pair: &KeyPair) {
+    txn.put(Self::set_keys_key(set), key_pair.encode());
+    txn.put(Self::key_set_key(&key_pair.0 .0), set.encode());
+    txn.put(Self::key_set_key(&key_pair.1), set.encode());
+  }
+  pub fn set_keys<G: Get>(getter: &G, set: ValidatorSet) -> Option<KeyPair> {
+    getter
+      .get(Self::set_keys_key(set))
+      .map(|key_pair| KeyPair::decode(&mut key_pair.as_ref()).unwrap())
+  }
+  pub fn key_set<G: Get>(getter: &G, key: &[u8]) -> Option<ValidatorSet> {
+    getter.get(Self::key_set_key(key)).map(|set| ValidatorSet::decode(&mut set.as_ref()).unwrap())
+  }
+  pub fn key_tributary<G: Get>(getter: &G, key: &[u8]) -> Option<[u8; 32]> {
+    Self::set_tributary(getter, Self::key_set(getter, key)?)
+  }
+
+  fn scanner_cursor_key(key: &[u8]) -> Vec<u8> {
+    Self::main_key(b"scanner_cursor", key)
+  }
+  // Save the latest block the processor's scanner has handled for this key
+  pub fn save_scanner_cursor(txn: &mut D::Transaction<'_>, key: &[u8], block: BlockHash) {
+    txn.put(Self::scanner_cursor_key(key), block.0);
+  }
+  pub fn scanner_cursor<G: Get>(getter: &G, key: &[u8]) -> Option<BlockHash> {
+    getter.get(Self::scanner_cursor_key(key)).map(|block| BlockHash(block.try_into().unwrap()))
+  }
 }
@@ -5,7 +5,7 @@
 use core::ops::Deref;
 use std::{
   sync::Arc,
-  time::{SystemTime, Duration},
+  time::{SystemTime, Duration, Instant},
   collections::{VecDeque, HashMap},
 };
 
@@ -17,12 +17,16 @@ use ciphersuite::{group::ff::Field, Ciphersuite, Ristretto};
 use serai_db::{Db, MemDb};
 use serai_client::Serai;
 
-use tokio::{sync::RwLock, time::sleep};
+use tokio::{
+  sync::RwLock,
+  time::sleep,
+  net::{TcpStream, TcpListener},
+};
 
-use ::tributary::{ReadWrite, Block, Tributary, TributaryReader};
+use ::tributary::{ReadWrite, ProvidedError, Block, Tributary, TributaryReader};
 
 mod tributary;
-use crate::tributary::{TributarySpec, SignData, Transaction};
+use crate::tributary::{TributarySpec, SignData, Transaction, TributaryDb};
 
 mod db;
 use db::MainDb;
@@ -30,10 +34,10 @@ use db::MainDb;
 mod p2p;
 pub use p2p::*;
 
-use processor_messages::{key_gen, sign, coordinator, ProcessorMessage};
+use processor_messages::{key_gen, sign, coordinator, CoordinatorMessage, ProcessorMessage};
 
 pub mod processor;
-use processor::Processor;
+use processor::{Processor, ProcessorRouter, StreamProcessor};
 
 mod substrate;
 
@@ -165,6 +169,7 @@ pub async fn scan_tributaries<D: Db, Pro: Processor, P: P2p>(
         &processor,
         spec,
         reader,
+        true,
       )
       .await;
     }
@@ -208,6 +213,13 @@ pub async fn handle_p2p<D: Db, P: P2p>(
   p2p: P,
   tributaries: Arc<RwLock<HashMap<[u8; 32], ActiveTributary<D, P>>>>,
 ) {
+  // When we last responded to a heartbeat from a specific peer for a specific Tributary
+  let mut last_heartbeat_response = HashMap::new();
+  // Honest peers only send a heartbeat every ten blocks of time, so responding to a peer more
+  // often than every five blocks of time is solely of benefit to a peer spamming heartbeats
+  let heartbeat_response_interval =
+    Duration::from_secs((5 * Tributary::<D, Transaction, P>::block_time()).into());
+
   loop {
     let mut msg = p2p.receive().await;
     match msg.kind {
@@ -223,7 +235,6 @@ pub async fn handle_p2p<D: Db, P: P2p>(
         }
       }
 
-      // TODO2: Rate limit this
       P2pMessageKind::Heartbeat(genesis) => {
         let tributaries = tributaries.read().await;
         let Some(tributary) = tributaries.get(&genesis) else {
@@ -274,6 +285,20 @@ pub async fn handle_p2p<D: Db, P: P2p>(
 
         log::debug!("received heartbeat and selected to respond");
 
+        // Rate limit responses, as each one may cause us to send every block we have
+        let now = Instant::now();
+        // Evict responses outside the rate limit window, so this doesn't grow with every peer and
+        // Tributary we've ever responded to
+        last_heartbeat_response
+          .retain(|_, last: &mut Instant| now.duration_since(*last) < heartbeat_response_interval);
+        if let Some(last) = last_heartbeat_response.get(&(genesis, msg.sender)) {
+          if now.duration_since(*last) < heartbeat_response_interval {
+            log::debug!("received heartbeat too soon after the last one from this peer");
+            continue;
+          }
+        }
+        last_heartbeat_response.insert((genesis, msg.sender), now);
+
         let reader = tributary_read.reader();
         drop(tributary_read);
 
@@ -312,8 +337,52 @@ pub async fn handle_p2p<D: Db, P: P2p>(
   }
 }
 
+pub async fn accept_processors(
+  router: ProcessorRouter<StreamProcessor<TcpStream>>,
+  key: Zeroizing<<Ristretto as Ciphersuite>::F>,
+  listener: TcpListener,
+) {
+  loop {
+    let (stream, addr) = match listener.accept().await {
+      Ok(res) => res,
+      Err(e) => {
+        log::warn!("couldn't accept processor connection: {e}");
+        continue;
+      }
+    };
+
+    let router = router.clone();
+    let key = key.clone();
+    tokio::spawn(async move {
+      if let Err(e) = processor::accept(&router, key, stream).await {
+        log::warn!("connection with processor at {addr} failed: {e}");
+      }
+    });
+  }
+}
+
+pub async fn monitor_processors<Pro: Processor>(router: ProcessorRouter<Pro>) {
+  // Processors are expected to be heard from at least this often
+  const PROCESSOR_TIMEOUT: Duration = Duration::from_secs(120);
+
+  loop {
+    sleep(PROCESSOR_TIMEOUT).await;
+
+    for network in router.unresponsive(PROCESSOR_TIMEOUT).await {
+      log::warn!(
+        "processor for {network:?} hasn't been heard from in over {}s",
+        PROCESSOR_TIMEOUT.as_secs()
+      );
+    }
+    for (network, queued) in router.queued().await {
+      log::warn!("processor for {network:?} isn't connected yet has {queued} messages queued");
+    }
+  }
+}
+
 #[allow(clippy::type_complexity)]
 pub async fn handle_processors<D: Db, Pro: Processor, P: P2p>(
+  mut db: D,
   key: Zeroizing<<Ristretto as Ciphersuite>::F>,
   mut processor: Pro,
   tributaries: Arc<RwLock<HashMap<[u8; 32], ActiveTributary<D, P>>>>,
@@ -323,57 +392,169 @@ pub async fn handle_processors<D: Db, Pro: Processor, P: P2p>(
   loop {
     let msg = processor.recv().await;
 
-    // TODO: We need (ValidatorSet or key) to genesis hash
-    let genesis = [0; 32];
-
-    let tx = match msg.msg {
-      ProcessorMessage::KeyGen(msg) => match msg {
-        key_gen::ProcessorMessage::Commitments { id, commitments } => {
-          Some(Transaction::DkgCommitments(id.attempt, commitments, Transaction::empty_signed()))
+    // Acknowledge the processor's completions, so it stops resending them on reboot
+    if let ProcessorMessage::Coordinator(msg) = &msg.msg {
+      if let Some(ack) = msg.ack() {
+        let mut txn = db.txn();
+        match msg {
+          // These signing protocols completed, so their IDs no longer have to accept data
+          coordinator::ProcessorMessage::BatchSigned { key, id } => {
+            let set = MainDb::<D>::key_set(&txn, key)
+              .expect("processor signed a batch with an unknown key");
+            let genesis = MainDb::<D>::key_tributary(&txn, key)
+              .expect("processor signed a batch with an unknown key");
+            TributaryDb::<D>::complete_id(
+              &mut txn,
+              "batch",
+              genesis,
+              coordinator::batch_sign_id(set.network, *id),
+            );
+          }
+          coordinator::ProcessorMessage::TransactionSigned { key, id } => {
+            let genesis = MainDb::<D>::key_tributary(&txn, key)
+              .expect("processor signed a transaction with an unknown key");
+            TributaryDb::<D>::complete_id(&mut txn, "sign", genesis, *id);
+          }
+          coordinator::ProcessorMessage::ScannerCursor { key, block } => {
+            if MainDb::<D>::scanner_cursor(&txn, key) == Some(*block) {
+              log::warn!("processor resent its scanner cursor. this should only appear on reboot");
+            }
+            MainDb::<D>::save_scanner_cursor(&mut txn, key, *block);
+          }
+          _ => {}
         }
-        key_gen::ProcessorMessage::Shares { id, shares } => {
-          Some(Transaction::DkgShares(id.attempt, shares, Transaction::empty_signed()))
+        txn.commit();
+        processor.send(CoordinatorMessage::Coordinator(ack)).await;
+        continue;
+      }
+    }
+
+    // Author the provided transaction this message implies, if any, saving the data the scanner
+    // will need when it's included on-chain
+    let provided = match &msg.msg {
+      // The first preprocess for a batch means the processor has scanned the external block it's
+      // for, so we can provide the batch, authorizing it
+      ProcessorMessage::Coordinator(coordinator::ProcessorMessage::BatchPreprocess {
+        id,
+        block,
+        ..
+      }) if id.attempt == 0 => {
+        let genesis = MainDb::<D>::key_tributary(&db, &id.key)
+          .expect("processor sent message for an unknown key");
+        let mut txn = db.txn();
+        TributaryDb::<D>::set_batch(&mut txn, genesis, id.id, block.0);
+        txn.commit();
+        Some((genesis, Transaction::Batch(block.0, id.id)))
+      }
+      ProcessorMessage::Coordinator(coordinator::ProcessorMessage::SubstrateBlockAck {
+        network,
+        block,
+        plans,
+      }) => {
+        // TODO: This assumes a single active set per network
+        let genesis = tributaries
+          .read()
+          .await
+          .values()
+          .find(|tributary| tributary.spec.set().network == *network)
+          .map(|tributary| tributary.spec.genesis())
+          .expect("processor acknowledged a substrate block for a network without a tributary");
+        let mut txn = db.txn();
+        TributaryDb::<D>::set_plan_ids(&mut txn, genesis, *block, plans);
+        txn.commit();
+        Some((genesis, Transaction::SubstrateBlock(*block)))
+      }
+      _ => None,
+    };
+    if let Some((genesis, tx)) = provided {
+      let tributaries = tributaries.read().await;
+      let tributary = tributaries
+        .get(&genesis)
+        .expect("providing a transaction to a tributary we don't have")
+        .tributary
+        .read()
+        .await;
+      match tributary.provide_transaction(tx).await {
+        Ok(()) => {}
+        // This should only happen on reboot
+        Err(ProvidedError::AlreadyProvided) => {
+          log::warn!("already provided transaction. this should only appear on reboot")
         }
+        Err(e) => panic!("created an invalid provided transaction: {e:?}"),
+      }
+    }
+
+    // Find the Tributary a message is for, either by its validator set or by the key it's using
+    let set_genesis = |set| {
+      MainDb::<D>::set_tributary(&db, set).expect("processor sent message for an unknown set")
+    };
+    let key_genesis = |key: &[u8]| {
+      MainDb::<D>::key_tributary(&db, key).expect("processor sent message for an unknown key")
+    };
+
+    let (genesis, tx) = match msg.msg {
+      ProcessorMessage::KeyGen(msg) => match msg {
+        key_gen::ProcessorMessage::Commitments { id, commitments } => (
+          set_genesis(id.set),
+          Some(Transaction::DkgCommitments(id.attempt, commitments, Transaction::empty_signed())),
+        ),
+        key_gen::ProcessorMessage::Shares { id, shares } => (
+          set_genesis(id.set),
+          Some(Transaction::DkgShares(id.attempt, shares, Transaction::empty_signed())),
+        ),
         // TODO
         key_gen::ProcessorMessage::GeneratedKeyPair { .. } => todo!(),
       },
       ProcessorMessage::Sign(msg) => match msg {
-        sign::ProcessorMessage::Preprocess { id, preprocess } => {
+        sign::ProcessorMessage::Preprocess { id, preprocess } => (
+          key_genesis(&id.key),
           Some(Transaction::SignPreprocess(SignData {
             plan: id.id,
             attempt: id.attempt,
             data: preprocess,
             signed: Transaction::empty_signed(),
-          }))
-        }
-        sign::ProcessorMessage::Share { id, share } => Some(Transaction::SignShare(SignData {
-          plan: id.id,
-          attempt: id.attempt,
-          data: share,
-          signed: Transaction::empty_signed(),
-        })),
+          })),
+        ),
+        sign::ProcessorMessage::Share { id, share } => (
+          key_genesis(&id.key),
+          Some(Transaction::SignShare(SignData {
+            plan: id.id,
+            attempt: id.attempt,
+            data: share,
+            signed: Transaction::empty_signed(),
+          })),
+        ),
         // TODO
         sign::ProcessorMessage::Completed { .. } => todo!(),
+        sign::ProcessorMessage::RequestFeeBump { key, id, bumps } => {
+          (key_genesis(&key), Some(Transaction::FeeBump(id, bumps, Transaction::empty_signed())))
+        }
       },
       ProcessorMessage::Coordinator(msg) => match msg {
-        // TODO
-        coordinator::ProcessorMessage::SubstrateBlockAck { .. } => todo!(),
-        coordinator::ProcessorMessage::BatchPreprocess { id, preprocess } => {
+        // Handled above, as it only causes a provided transaction
+        coordinator::ProcessorMessage::SubstrateBlockAck { .. } => continue,
+        // Acknowledged above
+        coordinator::ProcessorMessage::BatchSigned { .. } |
+        coordinator::ProcessorMessage::TransactionSigned { .. } |
+        coordinator::ProcessorMessage::ScannerCursor { .. } => continue,
+        coordinator::ProcessorMessage::BatchPreprocess { id, preprocess, .. } => (
+          key_genesis(&id.key),
           Some(Transaction::BatchPreprocess(SignData {
             plan: id.id,
             attempt: id.attempt,
             data: preprocess,
             signed: Transaction::empty_signed(),
-          }))
-        }
-        coordinator::ProcessorMessage::BatchShare { id, share } => {
+          })),
+        ),
+        coordinator::ProcessorMessage::BatchShare { id, share } => (
+          key_genesis(&id.key),
           Some(Transaction::BatchShare(SignData {
             plan: id.id,
             attempt: id.attempt,
             data: share.to_vec(),
             signed: Transaction::empty_signed(),
-          }))
-        }
+          })),
+        ),
       },
       ProcessorMessage::Substrate(msg) => match msg {
         // TODO
@@ -462,7 +643,7 @@ pub async fn run<D: Db, Pro: Processor, P: P2p>(
   tokio::spawn(handle_p2p(Ristretto::generator() * key.deref(), p2p, tributaries.clone()));
 
   // Handle all messages from processors
-  handle_processors(key, processor, tributaries).await;
+  handle_processors(raw_db, key, processor, tributaries).await;
 }
 
 #[tokio::main]
@@ -472,7 +653,10 @@ async fn main() {
   let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::ZERO); // TODO
   let p2p = LocalP2p::new(1).swap_remove(0); // TODO
 
-  let processor = processor::MemProcessor::new(); // TODO
+  let processor = ProcessorRouter::new();
+  tokio::spawn(monitor_processors(processor.clone()));
+  let listener = TcpListener::bind("127.0.0.1:5000").await.unwrap(); // TODO
+  tokio::spawn(accept_processors(processor.clone(), key.clone(), listener));
 
   let serai = || async {
     loop {
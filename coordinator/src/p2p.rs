@@ -1,4 +1,4 @@
-use core::fmt::Debug;
+use core::{hash::Hash, fmt::Debug};
 use std::{sync::Arc, io::Read, collections::VecDeque};
 
 use async_trait::async_trait;
@@ -68,7 +68,7 @@ pub struct Message<P: P2p> {
 
 #[async_trait]
 pub trait P2p: Send + Sync + Clone + Debug + TributaryP2p {
-  type Id: Send + Sync + Clone + Copy + Debug;
+  type Id: Send + Sync + Clone + Copy + PartialEq + Eq + Hash + Debug;
 
   async fn send_raw(&self, to: Self::Id, msg: Vec<u8>);
   async fn broadcast_raw(&self, msg: Vec<u8>);
@@ -1,8 +1,29 @@
-use std::{sync::Arc, collections::VecDeque};
+use core::ops::Deref;
+use std::{
+  io,
+  sync::{Arc, RwLock as SyncRwLock},
+  time::{Duration, Instant},
+  collections::{VecDeque, HashMap},
+};
 
-use tokio::sync::RwLock;
+use zeroize::Zeroizing;
+use rand_core::OsRng;
 
-use processor_messages::{ProcessorMessage, CoordinatorMessage};
+use ciphersuite::{group::ff::Field, Ciphersuite, Ristretto};
+use schnorr::SchnorrSignature;
+
+use tokio::{
+  io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
+  sync::{
+    RwLock, Mutex, Notify,
+    mpsc::{self, UnboundedSender, UnboundedReceiver},
+  },
+  task::JoinHandle,
+};
+
+use serai_client::{primitives::NetworkId, validator_sets::primitives::ValidatorSet};
+
+use processor_messages::{key_gen, substrate, Handshake, ProcessorMessage, CoordinatorMessage};
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Message {
@@ -17,25 +38,465 @@ pub trait Processor: 'static + Send + Sync + Clone {
   async fn ack(&mut self, msg: Message);
 }
 
-// TODO: Move this to tests
+// A processor connected to the router
+struct Connection<P: Processor> {
+  processor: P,
+  // Distinguishes this connection from prior connections for the same network
+  generation: u64,
+  acks: UnboundedSender<Message>,
+  task: JoinHandle<()>,
+}
+
+// The validator set each key is for, shared with the processors so they can sign messages for the
+// validator set they're for
+type KeySets = Arc<SyncRwLock<HashMap<Vec<u8>, ValidatorSet>>>;
+
+struct Routes<P: Processor> {
+  keys: KeySets,
+  connections: HashMap<NetworkId, Connection<P>>,
+  next_generation: u64,
+  // Messages for networks whose processor isn't currently connected
+  outboxes: HashMap<NetworkId, VecDeque<CoordinatorMessage>>,
+  // Messages for keys whose validator set isn't known yet
+  unrouted: HashMap<Vec<u8>, VecDeque<CoordinatorMessage>>,
+  last_seen: HashMap<NetworkId, Instant>,
+}
+
+impl<P: Processor> Routes<P> {
+  // Register the validator set a key is for, returning if any messages were waiting on it
+  fn register_key(&mut self, key: Vec<u8>, set: ValidatorSet) -> bool {
+    let existing = self.keys.write().unwrap().insert(key.clone(), set);
+    if let Some(existing) = existing {
+      assert_eq!(existing, set, "key was registered for multiple validator sets");
+    }
+
+    let Some(msgs) = self.unrouted.remove(&key) else { return false };
+    self.outboxes.entry(set.network).or_default().extend(msgs);
+    true
+  }
+
+  // The network a message is for, registering any keys it introduces
+  // Returns the message's key if the validator set it's for isn't known
+  fn network<'a>(&mut self, msg: &'a CoordinatorMessage) -> Result<NetworkId, &'a [u8]> {
+    if let CoordinatorMessage::Substrate(substrate::CoordinatorMessage::ConfirmKeyPair {
+      set,
+      key_pair,
+      ..
+    }) = msg
+    {
+      self.register_key(key_pair.0 .0.to_vec(), *set);
+      self.register_key(key_pair.1.to_vec(), *set);
+    }
+
+    let keys = self.keys.read().unwrap();
+    let Some(set) = msg.validator_set(|key| keys.get(key).copied()) else {
+      return Err(match msg {
+        CoordinatorMessage::Sign(msg) => msg.key(),
+        CoordinatorMessage::Coordinator(msg) => msg.key(),
+        CoordinatorMessage::Substrate(substrate::CoordinatorMessage::SubstrateBlock {
+          key,
+          ..
+        }) => key,
+        CoordinatorMessage::KeyGen(_) |
+        CoordinatorMessage::Substrate(substrate::CoordinatorMessage::ConfirmKeyPair { .. }) => {
+          unreachable!("message which specifies its validator set didn't have one")
+        }
+      });
+    };
+    Ok(set.network)
+  }
+
+  // Queue a message for the processor it's for, returning the network it was queued for
+  // Messages for a key whose validator set isn't known are held until it is, at which point they're
+  // queued after any messages already queued for that network
+  fn route(&mut self, msg: CoordinatorMessage) -> Option<NetworkId> {
+    match self.network(&msg) {
+      Ok(network) => {
+        self.outboxes.entry(network).or_default().push_back(msg);
+        Some(network)
+      }
+      Err(key) => {
+        let key = key.to_vec();
+        self.unrouted.entry(key).or_default().push_back(msg);
+        None
+      }
+    }
+  }
+}
+
+// Send all messages queued for a network, if its processor is connected
+//
+// The routes aren't locked while sending, allowing messages to be received and routed
+// Instead, sending is serialized by its own lock so messages are sent in the order they're queued
+async fn flush<P: Processor>(routes: &RwLock<Routes<P>>, sending: &Mutex<()>, network: NetworkId) {
+  let _sending = sending.lock().await;
+  let (processor, msgs) = {
+    let mut routes = routes.write().await;
+    let Some(connection) = routes.connections.get(&network) else { return };
+    let processor = connection.processor.clone();
+    (processor, routes.outboxes.remove(&network).unwrap_or_default())
+  };
+  for msg in msgs {
+    processor.send(msg).await;
+  }
+}
+
+// Forward messages from a processor to the router, only receiving the next message once the
+// prior one was acknowledged
+async fn forward<P: Processor>(
+  network: NetworkId,
+  generation: u64,
+  mut processor: P,
+  routes: Arc<RwLock<Routes<P>>>,
+  sending: Arc<Mutex<()>>,
+  inbound: UnboundedSender<(NetworkId, u64, Message)>,
+  mut acks: UnboundedReceiver<Message>,
+) {
+  loop {
+    let msg = processor.recv().await;
+    let unblocked = {
+      let mut routes = routes.write().await;
+      routes.last_seen.insert(network, Instant::now());
+      if let ProcessorMessage::KeyGen(key_gen::ProcessorMessage::GeneratedKeyPair {
+        id,
+        substrate_key,
+        coin_key,
+      }) = &msg.msg
+      {
+        assert_eq!(id.set.network, network, "processor generated a key for another network");
+        routes.register_key(substrate_key.to_vec(), id.set) |
+          routes.register_key(coin_key.clone(), id.set)
+      } else {
+        false
+      }
+    };
+    // If messages were waiting on these keys, send them
+    // This is spawned so this task being aborted, due to a reconnection, doesn't abort the flush
+    if unblocked {
+      let routes = routes.clone();
+      let sending = sending.clone();
+      tokio::spawn(async move { flush(&routes, &sending, network).await });
+    }
+
+    if inbound.send((network, generation, msg)).is_err() {
+      return;
+    }
+    let Some(msg) = acks.recv().await else { return };
+    processor.ack(msg).await;
+  }
+}
+
+/// A Processor which routes messages to the processor for the network they're for, allowing a
+/// single coordinator to be used with multiple processors.
+///
+/// Messages for a network whose processor isn't connected are queued until it connects.
 #[derive(Clone)]
-pub struct MemProcessor(pub Arc<RwLock<VecDeque<CoordinatorMessage>>>);
-impl MemProcessor {
+pub struct ProcessorRouter<P: Processor> {
+  routes: Arc<RwLock<Routes<P>>>,
+  sending: Arc<Mutex<()>>,
+  inbound: UnboundedSender<(NetworkId, u64, Message)>,
+  received: Arc<Mutex<UnboundedReceiver<(NetworkId, u64, Message)>>>,
+  // Messages which have been received yet not acknowledged, with the connection they're from
+  unacked: Arc<Mutex<VecDeque<(NetworkId, u64, Message)>>>,
+}
+
+impl<P: Processor> ProcessorRouter<P> {
   #[allow(clippy::new_without_default)]
-  pub fn new() -> MemProcessor {
-    MemProcessor(Arc::new(RwLock::new(VecDeque::new())))
+  pub fn new() -> ProcessorRouter<P> {
+    let (inbound, received) = mpsc::unbounded_channel();
+    ProcessorRouter {
+      routes: Arc::new(RwLock::new(Routes {
+        keys: Arc::new(SyncRwLock::new(HashMap::new())),
+        connections: HashMap::new(),
+        next_generation: 0,
+        outboxes: HashMap::new(),
+        unrouted: HashMap::new(),
+        last_seen: HashMap::new(),
+      })),
+      sending: Arc::new(Mutex::new(())),
+      inbound,
+      received: Arc::new(Mutex::new(received)),
+      unacked: Arc::new(Mutex::new(VecDeque::new())),
+    }
+  }
+
+  /// Connect the processor for a network, replacing any existing connection.
+  ///
+  /// Any messages queued for this network are sent to it.
+  ///
+  /// Returns the generation of this connection, used to disconnect it.
+  pub async fn connect(&self, network: NetworkId, processor: P) -> u64 {
+    let generation = {
+      let mut routes = self.routes.write().await;
+      if let Some(existing) = routes.connections.remove(&network) {
+        existing.task.abort();
+      }
+
+      let generation = routes.next_generation;
+      routes.next_generation += 1;
+
+      let (acks, acks_recv) = mpsc::unbounded_channel();
+      let task = tokio::spawn(forward(
+        network,
+        generation,
+        processor.clone(),
+        self.routes.clone(),
+        self.sending.clone(),
+        self.inbound.clone(),
+        acks_recv,
+      ));
+
+      routes.last_seen.insert(network, Instant::now());
+      routes.connections.insert(network, Connection { processor, generation, acks, task });
+      generation
+    };
+
+    flush(&self.routes, &self.sending, network).await;
+    generation
+  }
+
+  /// Disconnect the processor for a network, queueing all further messages for it.
+  ///
+  /// This is a no-op if the connection with this generation was already replaced.
+  pub async fn disconnect(&self, network: NetworkId, generation: u64) {
+    let mut routes = self.routes.write().await;
+    if routes.connections.get(&network).map(|connection| connection.generation) != Some(generation)
+    {
+      return;
+    }
+    routes.connections.remove(&network).unwrap().task.abort();
+  }
+
+  /// The networks whose processor is connected yet hasn't been seen within the timeout.
+  pub async fn unresponsive(&self, timeout: Duration) -> Vec<NetworkId> {
+    let routes = self.routes.read().await;
+    routes
+      .connections
+      .keys()
+      .filter(|network| routes.last_seen[*network].elapsed() > timeout)
+      .copied()
+      .collect()
+  }
+
+  /// The amount of messages queued for each network whose processor isn't connected.
+  pub async fn queued(&self) -> HashMap<NetworkId, usize> {
+    let routes = self.routes.read().await;
+    routes
+      .outboxes
+      .iter()
+      .filter(|(network, outbox)| !(routes.connections.contains_key(network) || outbox.is_empty()))
+      .map(|(network, outbox)| (*network, outbox.len()))
+      .collect()
   }
 }
 
 #[async_trait::async_trait]
-impl Processor for MemProcessor {
+impl<P: Processor> Processor for ProcessorRouter<P> {
   async fn send(&self, msg: CoordinatorMessage) {
-    self.0.write().await.push_back(msg)
+    let network = self.routes.write().await.route(msg);
+    if let Some(network) = network {
+      flush(&self.routes, &self.sending, network).await;
+    }
   }
+
   async fn recv(&mut self) -> Message {
-    todo!()
+    let (network, generation, msg) = self
+      .received
+      .lock()
+      .await
+      .recv()
+      .await
+      .expect("router was dropped while receiving a message");
+    self.unacked.lock().await.push_back((network, generation, msg.clone()));
+    msg
   }
-  async fn ack(&mut self, _: Message) {
-    todo!()
+
+  async fn ack(&mut self, msg: Message) {
+    let (network, generation) = {
+      let mut unacked = self.unacked.lock().await;
+      let i = unacked
+        .iter()
+        .position(|(_, _, unacked)| unacked == &msg)
+        .expect("acknowledging a message which wasn't received");
+      let (network, generation, _) = unacked.remove(i).unwrap();
+      (network, generation)
+    };
+
+    // If the processor disconnected, it'll send this message again once it reconnects
+    // If it reconnected, this message was received over the prior connection, and the new
+    // connection will send it again, so the acknowledgement is dropped
+    if let Some(connection) = self.routes.read().await.connections.get(&network) {
+      if connection.generation == generation {
+        let _ = connection.acks.send(msg);
+      }
+    }
+  }
+}
+
+// The maximum size of a message sent over a stream
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+  let len = usize::try_from(reader.read_u32_le().await?).unwrap();
+  if len > MAX_FRAME_SIZE {
+    Err(io::Error::new(io::ErrorKind::InvalidData, "message exceeded the maximum size"))?;
+  }
+  let mut frame = vec![0; len];
+  reader.read_exact(&mut frame).await?;
+  Ok(frame)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+  writer.write_u32_le(u32::try_from(frame.len()).unwrap()).await?;
+  writer.write_all(frame).await?;
+  writer.flush().await
+}
+
+/// A processor connected over a stream, such as a TCP connection.
+///
+/// Every message is prefixed by its length, as a little-endian u32. Once connected, the processor
+/// sends its Handshake, which the coordinator responds to with its own. Afterwards, the processor
+/// sends its messages prefixed by their ID, as a little-endian u64. The coordinator sends either
+/// a 0 byte, the message's ID, the coordinator's signature for the message, and the message, or a
+/// 1 byte and the ID of the message it's acknowledging.
+///
+/// The signature is a Schnorr signature, by the coordinator's key, for the message's challenge
+/// (see `CoordinatorMessage::challenge`).
+pub struct StreamProcessor<S> {
+  reader: Arc<Mutex<ReadHalf<S>>>,
+  writer: Arc<Mutex<WriteHalf<S>>>,
+  key: Zeroizing<<Ristretto as Ciphersuite>::F>,
+  sets: KeySets,
+  // TODO: These IDs should persist across reboots, as the processor won't handle an ID twice
+  next_id: Arc<Mutex<u64>>,
+  closed: Arc<Notify>,
+}
+
+impl<S> Clone for StreamProcessor<S> {
+  fn clone(&self) -> Self {
+    StreamProcessor {
+      reader: self.reader.clone(),
+      writer: self.writer.clone(),
+      key: self.key.clone(),
+      sets: self.sets.clone(),
+      next_id: self.next_id.clone(),
+      closed: self.closed.clone(),
+    }
+  }
+}
+
+impl<S: AsyncRead + AsyncWrite> StreamProcessor<S> {
+  fn new(stream: S, key: Zeroizing<<Ristretto as Ciphersuite>::F>, sets: KeySets) -> Self {
+    let (reader, writer) = tokio::io::split(stream);
+    StreamProcessor {
+      reader: Arc::new(Mutex::new(reader)),
+      writer: Arc::new(Mutex::new(writer)),
+      key,
+      sets,
+      next_id: Arc::new(Mutex::new(0)),
+      closed: Arc::new(Notify::new()),
+    }
+  }
+
+  fn close(&self, e: io::Error) {
+    log::warn!("connection to processor failed: {e}");
+    self.closed.notify_one();
+  }
+
+  async fn write(&self, frame: &[u8]) -> bool {
+    match write_frame(&mut *self.writer.lock().await, frame).await {
+      Ok(()) => true,
+      Err(e) => {
+        self.close(e);
+        false
+      }
+    }
+  }
+}
+
+#[async_trait::async_trait]
+impl<S: 'static + Send + AsyncRead + AsyncWrite> Processor for StreamProcessor<S> {
+  async fn send(&self, msg: CoordinatorMessage) {
+    // The router only sends messages once it knows the validator set they're for
+    let set = msg
+      .validator_set(|key| self.sets.read().unwrap().get(key).copied())
+      .expect("sending a message for a validator set which isn't known");
+
+    // Hold the ID's lock while writing so messages are written in order of their IDs
+    let mut id = self.next_id.lock().await;
+    let nonce = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+    let challenge = msg.challenge(
+      Ristretto::generator() * self.key.deref(),
+      Ristretto::generator() * nonce.deref(),
+      set,
+      *id,
+    );
+    let signature = SchnorrSignature::<Ristretto>::sign(&self.key, nonce, challenge);
+
+    let mut frame = vec![0];
+    frame.extend(id.to_le_bytes());
+    frame.extend(signature.serialize());
+    frame.extend(msg.serialize());
+    if self.write(&frame).await {
+      *id += 1;
+    }
   }
+
+  async fn recv(&mut self) -> Message {
+    let res = async {
+      let frame = read_frame(&mut *self.reader.lock().await).await?;
+      if frame.len() < 8 {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "message didn't have an ID"))?;
+      }
+      let id = u64::from_le_bytes(frame[.. 8].try_into().unwrap());
+      let msg = ProcessorMessage::deserialize(&frame[8 ..])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+      Ok::<_, io::Error>(Message { id, msg })
+    }
+    .await;
+
+    match res {
+      Ok(msg) => msg,
+      Err(e) => {
+        self.close(e);
+        // This connection will be replaced once the processor reconnects
+        std::future::pending().await
+      }
+    }
+  }
+
+  async fn ack(&mut self, msg: Message) {
+    let mut frame = vec![1];
+    frame.extend(msg.id.to_le_bytes());
+    self.write(&frame).await;
+  }
+}
+
+// Receive the handshake from a processor which just connected, responding with our own
+// If the processor is running an incompatible version, this errors without responding
+async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S) -> io::Result<NetworkId> {
+  let theirs = Handshake::deserialize(&read_frame(stream).await?)
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid handshake: {e:?}")))?;
+  write_frame(stream, &Handshake::new(theirs.network).serialize()).await?;
+  Ok(theirs.network)
+}
+
+/// Accept a connection from a processor, connecting it to the router for the network it's for.
+///
+/// Messages sent to the processor are signed with the coordinator's key. Returns once the
+/// connection fails, after disconnecting it.
+pub async fn accept<S: 'static + Send + Unpin + AsyncRead + AsyncWrite>(
+  router: &ProcessorRouter<StreamProcessor<S>>,
+  key: Zeroizing<<Ristretto as Ciphersuite>::F>,
+  mut stream: S,
+) -> io::Result<()> {
+  let network = handshake(&mut stream).await?;
+  log::info!("processor for {network:?} connected");
+
+  let sets = router.routes.read().await.keys.clone();
+  let processor = StreamProcessor::new(stream, key, sets);
+  let generation = router.connect(network, processor.clone()).await;
+  processor.closed.notified().await;
+  router.disconnect(network, generation).await;
+  Ok(())
 }
@@ -10,7 +10,7 @@ use serai_client::{
   SeraiError, Block, Serai,
   primitives::BlockHash,
   validator_sets::{
-    primitives::{Session, ValidatorSet, KeyPair},
+    primitives::{ValidatorSet, KeyPair},
     ValidatorSetsEvent,
   },
   in_instructions::InInstructionsEvent,
@@ -21,7 +21,7 @@ use serai_db::DbTxn;
 
 use processor_messages::{SubstrateContext, key_gen::KeyGenId, CoordinatorMessage};
 
-use crate::{Db, processor::Processor, tributary::TributarySpec};
+use crate::{Db, db::MainDb, processor::Processor, tributary::TributarySpec};
 
 mod db;
 pub use db::*;
@@ -29,9 +29,10 @@ pub use db::*;
 async fn in_set(
   key: &Zeroizing<<Ristretto as Ciphersuite>::F>,
   serai: &Serai,
+  block: &Block,
   set: ValidatorSet,
 ) -> Result<Option<bool>, SeraiError> {
-  let Some(data) = serai.get_validator_set(set).await? else {
+  let Some(data) = serai.get_validator_set(block.hash(), set).await? else {
     return Ok(None);
   };
   let key = (Ristretto::generator() * key.deref()).to_bytes();
@@ -52,8 +53,11 @@ async fn handle_new_set<
   block: &Block,
   set: ValidatorSet,
 ) -> Result<(), SeraiError> {
-  if in_set(key, serai, set).await?.expect("NewSet for set which doesn't exist") {
-    let set_data = serai.get_validator_set(set).await?.expect("NewSet for set which doesn't exist");
+  if in_set(key, serai, block, set).await?.expect("NewSet for set which doesn't exist") {
+    let set_data = serai
+      .get_validator_set(block.hash(), set)
+      .await?
+      .expect("NewSet for set which doesn't exist");
 
     let spec = TributarySpec::new(block.hash(), block.time().unwrap(), set, set_data);
     create_new_tributary(db, spec.clone());
@@ -75,6 +79,11 @@ async fn handle_new_set<
               .expect("In set for a set we aren't in set for"),
           )
           .unwrap(),
+          serai_genesis: serai
+            .get_block_by_number(0)
+            .await?
+            .expect("Serai didn't have a genesis block")
+            .hash(),
         },
       ))
       .await;
@@ -83,7 +92,8 @@ async fn handle_new_set<
   Ok(())
 }
 
-async fn handle_key_gen<Pro: Processor>(
+async fn handle_key_gen<D: Db, Pro: Processor>(
+  db: &mut D,
   key: &Zeroizing<<Ristretto as Ciphersuite>::F>,
   processor: &Pro,
   serai: &Serai,
@@ -91,7 +101,12 @@ async fn handle_key_gen<Pro: Processor>(
   set: ValidatorSet,
   key_pair: KeyPair,
 ) -> Result<(), SeraiError> {
-  if in_set(key, serai, set).await?.expect("KeyGen occurred for a set which doesn't exist") {
+  // Register the confirmed keys so the Tributary scanner can identify what they're signing with
+  let mut txn = db.txn();
+  MainDb::<D>::save_set_keys(&mut txn, set, &key_pair);
+  txn.commit();
+
+  if in_set(key, serai, block, set).await?.expect("KeyGen occurred for a set which doesn't exist") {
     // TODO: Check how the processor handles this being fired multiple times
     processor
       .send(CoordinatorMessage::Substrate(
@@ -115,7 +130,8 @@ async fn handle_key_gen<Pro: Processor>(
   Ok(())
 }
 
-async fn handle_batch_and_burns<Pro: Processor>(
+async fn handle_batch_and_burns<D: Db, Pro: Processor>(
+  db: &D,
   processor: &Pro,
   serai: &Serai,
   block: &Block,
@@ -191,11 +207,18 @@ async fn handle_batch_and_burns<Pro: Processor>(
           },
           network,
           block: block.number(),
-          key: serai
-            .get_keys(ValidatorSet { network, session: Session(0) }) // TODO2
-            .await?
-            .map(|keys| keys.1.into_inner())
-            .expect("batch/burn for network which never set keys"),
+          key: MainDb::<D>::set_keys(
+            db,
+            ValidatorSet {
+              network,
+              session: serai
+                .get_session(hash, network)
+                .await?
+                .expect("batch/burn for network which never had a session"),
+            },
+          )
+          .map(|keys| keys.1.into_inner())
+          .expect("batch/burn for network which never set keys"),
           burns: burns.remove(&network).unwrap(),
         },
       ))
@@ -249,7 +272,7 @@ async fn handle_block<
   for key_gen in serai.get_key_gen_events(hash).await? {
     if !SubstrateDb::<D>::handled_event(&db.0, hash, event_id) {
       if let ValidatorSetsEvent::KeyGen { set, key_pair } = key_gen {
-        handle_key_gen(key, processor, serai, &block, set, key_pair).await?;
+        handle_key_gen(&mut db.0, key, processor, serai, &block, set, key_pair).await?;
       } else {
         panic!("KeyGen event wasn't KeyGen: {key_gen:?}");
       }
@@ -266,7 +289,7 @@ async fn handle_block<
   // This does break the uniqueness of (hash, event_id) -> one event, yet
   // (network, (hash, event_id)) remains valid as a unique ID for an event
   if !SubstrateDb::<D>::handled_event(&db.0, hash, event_id) {
-    handle_batch_and_burns(processor, serai, &block).await?;
+    handle_batch_and_burns(&db.0, processor, serai, &block).await?;
   }
   let mut txn = db.0.txn();
   SubstrateDb::<D>::handle_event(&mut txn, hash, event_id);
@@ -1 +1,33 @@
+use std::{sync::Arc, collections::VecDeque};
+
+use tokio::sync::RwLock;
+
+use processor_messages::CoordinatorMessage;
+
+use crate::processor::{Message, Processor};
+
 pub mod tributary;
+
+mod processor;
+
+#[derive(Clone)]
+pub struct MemProcessor(pub Arc<RwLock<VecDeque<CoordinatorMessage>>>);
+impl MemProcessor {
+  #[allow(clippy::new_without_default)]
+  pub fn new() -> MemProcessor {
+    MemProcessor(Arc::new(RwLock::new(VecDeque::new())))
+  }
+}
+
+#[async_trait::async_trait]
+impl Processor for MemProcessor {
+  async fn send(&self, msg: CoordinatorMessage) {
+    self.0.write().await.push_back(msg)
+  }
+  async fn recv(&mut self) -> Message {
+    todo!()
+  }
+  async fn ack(&mut self, _: Message) {
+    todo!()
+  }
+}
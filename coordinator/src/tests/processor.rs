@@ -0,0 +1,294 @@
+use core::{ops::Deref, time::Duration};
+use std::{sync::Arc, collections::VecDeque};
+
+use zeroize::Zeroizing;
+use rand_core::OsRng;
+
+use ciphersuite::{group::ff::Field, Ciphersuite, Ristretto};
+use schnorr::SchnorrSignature;
+use frost::{Participant, ThresholdParams};
+
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex},
+  sync::RwLock,
+  time::{sleep, timeout},
+};
+
+use serai_client::{
+  primitives::NetworkId,
+  validator_sets::primitives::{Session, ValidatorSet},
+};
+
+use processor_messages::{
+  PROTOCOL_VERSION, key_gen, sign, Handshake, CoordinatorMessage, ProcessorMessage,
+};
+
+use crate::processor::{Message, Processor, ProcessorRouter, StreamProcessor, accept};
+
+#[derive(Clone)]
+struct TestProcessor {
+  // Messages the coordinator sent to this processor
+  received: Arc<RwLock<Vec<CoordinatorMessage>>>,
+  // Messages this processor will send to the coordinator
+  outbound: Arc<RwLock<VecDeque<Message>>>,
+  acked: Arc<RwLock<Vec<Message>>>,
+}
+
+impl TestProcessor {
+  fn new() -> TestProcessor {
+    TestProcessor {
+      received: Arc::new(RwLock::new(vec![])),
+      outbound: Arc::new(RwLock::new(VecDeque::new())),
+      acked: Arc::new(RwLock::new(vec![])),
+    }
+  }
+
+  async fn received(&self, amount: usize) -> Vec<CoordinatorMessage> {
+    timeout(Duration::from_secs(5), async {
+      loop {
+        let received = self.received.read().await;
+        if received.len() >= amount {
+          return received.clone();
+        }
+        drop(received);
+        sleep(Duration::from_millis(10)).await;
+      }
+    })
+    .await
+    .expect("processor didn't receive the expected messages")
+  }
+}
+
+#[async_trait::async_trait]
+impl Processor for TestProcessor {
+  async fn send(&self, msg: CoordinatorMessage) {
+    self.received.write().await.push(msg);
+  }
+  async fn recv(&mut self) -> Message {
+    loop {
+      if let Some(msg) = self.outbound.write().await.pop_front() {
+        return msg;
+      }
+      sleep(Duration::from_millis(10)).await;
+    }
+  }
+  async fn ack(&mut self, msg: Message) {
+    self.acked.write().await.push(msg);
+  }
+}
+
+fn key_gen_id(network: NetworkId) -> key_gen::KeyGenId {
+  key_gen::KeyGenId { set: ValidatorSet { session: Session(0), network }, attempt: 0 }
+}
+
+fn generate_key(network: NetworkId) -> CoordinatorMessage {
+  CoordinatorMessage::KeyGen(key_gen::CoordinatorMessage::GenerateKey {
+    id: key_gen_id(network),
+    params: ThresholdParams::new(1, 1, Participant::new(1).unwrap()).unwrap(),
+    serai_genesis: [0xff; 32],
+  })
+}
+
+fn reattempt(key: &[u8]) -> CoordinatorMessage {
+  CoordinatorMessage::Sign(sign::CoordinatorMessage::Reattempt {
+    id: sign::SignId { key: key.to_vec(), id: [0xff; 32], attempt: 1 },
+  })
+}
+
+fn generated_key_pair(id: u64, network: NetworkId, coin_key: &[u8]) -> Message {
+  Message {
+    id,
+    msg: ProcessorMessage::KeyGen(key_gen::ProcessorMessage::GeneratedKeyPair {
+      id: key_gen_id(network),
+      substrate_key: [0xaa; 32],
+      coin_key: coin_key.to_vec(),
+    }),
+  }
+}
+
+#[tokio::test]
+async fn route_by_network() {
+  let router = ProcessorRouter::new();
+  let bitcoin = TestProcessor::new();
+  let monero = TestProcessor::new();
+  router.connect(NetworkId::Bitcoin, bitcoin.clone()).await;
+  router.connect(NetworkId::Monero, monero.clone()).await;
+
+  router.send(generate_key(NetworkId::Monero)).await;
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+
+  assert_eq!(bitcoin.received(1).await, vec![generate_key(NetworkId::Bitcoin)]);
+  assert_eq!(monero.received(1).await, vec![generate_key(NetworkId::Monero)]);
+}
+
+#[tokio::test]
+async fn route_by_key() {
+  let mut router = ProcessorRouter::new();
+  let bitcoin = TestProcessor::new();
+  router.connect(NetworkId::Bitcoin, bitcoin.clone()).await;
+
+  // A message for a key whose network isn't known should be held
+  let coin_key = [0xbb; 33];
+  router.send(reattempt(&coin_key)).await;
+  sleep(Duration::from_millis(100)).await;
+  assert!(bitcoin.received.read().await.is_empty());
+
+  // Until the processor reports generating that key
+  let generated = generated_key_pair(0, NetworkId::Bitcoin, &coin_key);
+  bitcoin.outbound.write().await.push_back(generated.clone());
+  assert_eq!(router.recv().await, generated);
+  assert_eq!(bitcoin.received(1).await, vec![reattempt(&coin_key)]);
+
+  // Further messages for the key should be routed immediately
+  router.send(reattempt(&coin_key)).await;
+  assert_eq!(bitcoin.received(2).await.len(), 2);
+
+  router.ack(generated.clone()).await;
+  sleep(Duration::from_millis(100)).await;
+  assert_eq!(*bitcoin.acked.read().await, vec![generated]);
+}
+
+#[tokio::test]
+async fn queue_while_disconnected() {
+  let router = ProcessorRouter::new();
+
+  // Messages for a network without a connected processor should be queued
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  assert_eq!(router.queued().await[&NetworkId::Bitcoin], 2);
+
+  // And sent once it connects
+  let bitcoin = TestProcessor::new();
+  let generation = router.connect(NetworkId::Bitcoin, bitcoin.clone()).await;
+  assert_eq!(bitcoin.received(2).await.len(), 2);
+  assert!(router.queued().await.is_empty());
+
+  // After it disconnects, messages should be queued again, and sent to the next connection
+  router.disconnect(NetworkId::Bitcoin, generation).await;
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  assert_eq!(router.queued().await[&NetworkId::Bitcoin], 1);
+
+  let reconnected = TestProcessor::new();
+  router.connect(NetworkId::Bitcoin, reconnected.clone()).await;
+  assert_eq!(reconnected.received(1).await, vec![generate_key(NetworkId::Bitcoin)]);
+  assert_eq!(bitcoin.received.read().await.len(), 2);
+
+  // Disconnecting the prior connection again shouldn't disconnect the new one
+  router.disconnect(NetworkId::Bitcoin, generation).await;
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  assert_eq!(reconnected.received(2).await.len(), 2);
+}
+
+#[tokio::test]
+async fn reconnect() {
+  let mut router = ProcessorRouter::new();
+  let bitcoin = TestProcessor::new();
+  router.connect(NetworkId::Bitcoin, bitcoin.clone()).await;
+
+  let msg = generated_key_pair(0, NetworkId::Bitcoin, &[0xbb; 33]);
+  bitcoin.outbound.write().await.push_back(msg.clone());
+  assert_eq!(router.recv().await, msg);
+
+  // The processor reconnects before the message is acknowledged
+  let reconnected = TestProcessor::new();
+  router.connect(NetworkId::Bitcoin, reconnected.clone()).await;
+
+  // The acknowledgement is for the prior connection, and shouldn't be sent to the new one
+  router.ack(msg.clone()).await;
+  sleep(Duration::from_millis(100)).await;
+  assert!(bitcoin.acked.read().await.is_empty());
+  assert!(reconnected.acked.read().await.is_empty());
+
+  // The new connection sends the message again, and that should be acknowledged
+  reconnected.outbound.write().await.push_back(msg.clone());
+  assert_eq!(router.recv().await, msg);
+  router.ack(msg.clone()).await;
+  sleep(Duration::from_millis(100)).await;
+  assert_eq!(*reconnected.acked.read().await, vec![msg]);
+}
+
+async fn write_frame(stream: &mut DuplexStream, frame: &[u8]) {
+  stream.write_u32_le(u32::try_from(frame.len()).unwrap()).await.unwrap();
+  stream.write_all(frame).await.unwrap();
+}
+
+async fn read_frame(stream: &mut DuplexStream) -> Vec<u8> {
+  let mut frame = vec![0; usize::try_from(stream.read_u32_le().await.unwrap()).unwrap()];
+  stream.read_exact(&mut frame).await.unwrap();
+  frame
+}
+
+#[tokio::test]
+async fn stream_processor() {
+  let mut router = ProcessorRouter::<StreamProcessor<DuplexStream>>::new();
+  let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+  let public = Ristretto::generator() * key.deref();
+  let (coordinator, mut processor) = duplex(1024 * 1024);
+  let accepted = tokio::spawn({
+    let router = router.clone();
+    let key = key.clone();
+    async move { accept(&router, key, coordinator).await }
+  });
+
+  // The coordinator should respond to our handshake with its own
+  write_frame(&mut processor, &Handshake::new(NetworkId::Bitcoin).serialize()).await;
+  assert_eq!(
+    Handshake::deserialize(&read_frame(&mut processor).await).unwrap(),
+    Handshake::new(NetworkId::Bitcoin)
+  );
+
+  // Messages for our network should be sent to us, with sequential IDs, signed by the coordinator
+  for id in 0u64 .. 2 {
+    router.send(generate_key(NetworkId::Bitcoin)).await;
+    let frame = read_frame(&mut processor).await;
+    assert_eq!(frame[0], 0);
+    assert_eq!(frame[1 .. 9], id.to_le_bytes());
+    let signature = SchnorrSignature::<Ristretto>::read(&mut &frame[9 .. 73]).unwrap();
+    let msg = CoordinatorMessage::deserialize(&frame[73 ..]).unwrap();
+    assert_eq!(msg, generate_key(NetworkId::Bitcoin));
+    let set = key_gen_id(NetworkId::Bitcoin).set;
+    assert!(signature.verify(public, msg.challenge(public, signature.R, set, id)));
+    // The signature should be bound to the message's ID
+    assert!(!signature.verify(public, msg.challenge(public, signature.R, set, id + 1)));
+  }
+
+  // Our messages should be received by the router, and acknowledged once it acknowledges them
+  let msg = generated_key_pair(5, NetworkId::Bitcoin, &[0xbb; 33]);
+  let mut frame = msg.id.to_le_bytes().to_vec();
+  frame.extend(msg.msg.serialize());
+  write_frame(&mut processor, &frame).await;
+  assert_eq!(router.recv().await, msg);
+
+  router.ack(msg.clone()).await;
+  let mut ack = vec![1];
+  ack.extend(msg.id.to_le_bytes());
+  assert_eq!(read_frame(&mut processor).await, ack);
+
+  // Once the connection closes, messages should be queued
+  drop(processor);
+  accepted.await.unwrap().unwrap();
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  assert_eq!(router.queued().await[&NetworkId::Bitcoin], 1);
+}
+
+#[tokio::test]
+async fn stream_processor_incompatible_version() {
+  let router = ProcessorRouter::<StreamProcessor<DuplexStream>>::new();
+  let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+  let (coordinator, mut processor) = duplex(1024);
+  let accepted = tokio::spawn({
+    let router = router.clone();
+    async move { accept(&router, key, coordinator).await }
+  });
+
+  // A handshake from a distinct version of the protocol should be rejected, without a response
+  let mut handshake = Handshake::new(NetworkId::Bitcoin).serialize();
+  handshake[.. 2].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+  write_frame(&mut processor, &handshake).await;
+  assert!(accepted.await.unwrap().is_err());
+  assert!(processor.read_u32_le().await.is_err());
+
+  // Messages for its network should remain queued
+  router.send(generate_key(NetworkId::Bitcoin)).await;
+  assert_eq!(router.queued().await[&NetworkId::Bitcoin], 1);
+}
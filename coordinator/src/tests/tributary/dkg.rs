@@ -19,10 +19,12 @@ use processor_messages::{
 use tributary::{Transaction as TransactionTrait, Tributary};
 
 use crate::{
-  processor::MemProcessor,
   LocalP2p,
   tributary::{TributaryDb, Transaction, TributarySpec, scanner::handle_new_blocks},
-  tests::tributary::{new_keys, new_spec, new_tributaries, run_tributaries, wait_for_tx_inclusion},
+  tests::{
+    MemProcessor,
+    tributary::{new_keys, new_spec, new_tributaries, run_tributaries, wait_for_tx_inclusion},
+  },
 };
 
 #[tokio::test]
@@ -81,7 +83,7 @@ async fn dkg_test() {
   ) -> (TributaryDb<MemDb>, MemProcessor) {
     let mut scanner_db = TributaryDb(MemDb::new());
     let processor = MemProcessor::new();
-    handle_new_blocks(&mut scanner_db, key, &processor, spec, &tributary.reader()).await;
+    handle_new_blocks(&mut scanner_db, key, &processor, spec, &tributary.reader(), false).await;
     (scanner_db, processor)
   }
 
@@ -96,7 +98,15 @@ async fn dkg_test() {
   sleep(Duration::from_secs(Tributary::<MemDb, Transaction, LocalP2p>::block_time().into())).await;
 
   // Verify the scanner emits a KeyGen::Commitments message
-  handle_new_blocks(&mut scanner_db, &keys[0], &processor, &spec, &tributaries[0].1.reader()).await;
+  handle_new_blocks(
+    &mut scanner_db,
+    &keys[0],
+    &processor,
+    &spec,
+    &tributaries[0].1.reader(),
+    false,
+  )
+  .await;
   {
     let mut msgs = processor.0.write().await;
     assert_eq!(msgs.pop_front().unwrap(), expected_commitments);
@@ -137,7 +147,15 @@ async fn dkg_test() {
   }
 
   // With just 4 sets of shares, nothing should happen yet
-  handle_new_blocks(&mut scanner_db, &keys[0], &processor, &spec, &tributaries[0].1.reader()).await;
+  handle_new_blocks(
+    &mut scanner_db,
+    &keys[0],
+    &processor,
+    &spec,
+    &tributaries[0].1.reader(),
+    false,
+  )
+  .await;
   assert!(processor.0.write().await.is_empty());
 
   // Publish the final set of shares
@@ -168,7 +186,15 @@ async fn dkg_test() {
   };
 
   // Any scanner which has handled the prior blocks should only emit the new event
-  handle_new_blocks(&mut scanner_db, &keys[0], &processor, &spec, &tributaries[0].1.reader()).await;
+  handle_new_blocks(
+    &mut scanner_db,
+    &keys[0],
+    &processor,
+    &spec,
+    &tributaries[0].1.reader(),
+    false,
+  )
+  .await;
   {
     let mut msgs = processor.0.write().await;
     assert_eq!(msgs.pop_front().unwrap(), shares_for(0));
@@ -5,7 +5,7 @@ use rand_core::{RngCore, OsRng};
 
 use frost::Participant;
 
-use tributary::{ReadWrite, tests::random_signed};
+use tributary::{ReadWrite, TransactionKind, Transaction as TransactionTrait, tests::random_signed};
 
 use crate::tributary::{SignData, Transaction};
 
@@ -17,6 +17,8 @@ mod tx;
 mod dkg;
 // TODO: Test the other transactions
 
+mod scanner;
+
 mod handle_p2p;
 mod sync;
 
@@ -49,6 +51,21 @@ fn test_read_write<RW: Eq + Debug + ReadWrite>(value: RW) {
   assert_eq!(value, RW::read::<&[u8]>(&mut value.serialize().as_ref()).unwrap());
 }
 
+fn test_read_write_transaction(tx: Transaction) {
+  // Within blocks, transactions are written without their signature's s
+  let mut without_s = vec![];
+  tx.write_without_s(&mut without_s).unwrap();
+  let mut expected = tx.serialize();
+  if let TransactionKind::Signed(_) = tx.kind() {
+    expected.truncate(expected.len() - 32);
+  }
+  assert_eq!(without_s, expected);
+  let read = Transaction::read_without_s::<&[u8]>(&mut without_s.as_ref()).unwrap();
+  assert_eq!(read.hash(), tx.hash());
+
+  test_read_write(tx);
+}
+
 #[test]
 fn serialize_sign_data() {
   test_read_write(random_sign_data(&mut OsRng));
@@ -56,7 +73,7 @@ fn serialize_sign_data() {
 
 #[test]
 fn serialize_transaction() {
-  test_read_write(Transaction::DkgCommitments(
+  test_read_write_transaction(Transaction::DkgCommitments(
     random_u32(&mut OsRng),
     random_vec(&mut OsRng, 512),
     random_signed(&mut OsRng),
@@ -75,7 +92,7 @@ fn serialize_transaction() {
       shares.insert(Participant::new(u16::try_from(i + 1).unwrap()).unwrap(), share);
     }
 
-    test_read_write(Transaction::DkgShares(
+    test_read_write_transaction(Transaction::DkgShares(
       random_u32(&mut OsRng),
       shares,
       random_signed(&mut OsRng),
@@ -85,13 +102,25 @@ fn serialize_transaction() {
   {
     let mut ext_block = [0; 32];
     OsRng.fill_bytes(&mut ext_block);
-    test_read_write(Transaction::ExternalBlock(ext_block));
+    let mut batch = [0; 32];
+    OsRng.fill_bytes(&mut batch);
+    test_read_write_transaction(Transaction::Batch(ext_block, batch));
   }
-  test_read_write(Transaction::SubstrateBlock(OsRng.next_u64()));
+  test_read_write_transaction(Transaction::SubstrateBlock(OsRng.next_u64()));
+
+  test_read_write_transaction(Transaction::BatchPreprocess(random_sign_data(&mut OsRng)));
+  test_read_write_transaction(Transaction::BatchShare(random_sign_data(&mut OsRng)));
 
-  test_read_write(Transaction::BatchPreprocess(random_sign_data(&mut OsRng)));
-  test_read_write(Transaction::BatchShare(random_sign_data(&mut OsRng)));
+  test_read_write_transaction(Transaction::SignPreprocess(random_sign_data(&mut OsRng)));
+  test_read_write_transaction(Transaction::SignShare(random_sign_data(&mut OsRng)));
 
-  test_read_write(Transaction::SignPreprocess(random_sign_data(&mut OsRng)));
-  test_read_write(Transaction::SignShare(random_sign_data(&mut OsRng)));
+  {
+    let mut plan = [0; 32];
+    OsRng.fill_bytes(&mut plan);
+    test_read_write_transaction(Transaction::FeeBump(
+      plan,
+      random_u32(&mut OsRng),
+      random_signed(&mut OsRng),
+    ));
+  }
 }
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use rand_core::{RngCore, OsRng};
+
+use blake2::{Digest, Blake2s256};
+
+use ciphersuite::{group::ff::Field, Ciphersuite, Ristretto};
+
+use frost::Participant;
+
+use serai_db::{DbTxn, Db, MemDb};
+
+use tributary::{
+  TransactionError, Transaction as TransactionTrait, Block, BlockHeader, tests::random_signed,
+};
+
+use crate::{
+  tributary::{
+    TributaryDb, DataLabel, Transaction,
+    scanner::{
+      RECOGNIZED_ID_LIFETIME, ProvidedOrderError, IdStatus, id_status, check_provided_order,
+      handle_block,
+    },
+  },
+  tests::{
+    MemProcessor,
+    tributary::{new_keys, new_spec, random_sign_data},
+  },
+};
+
+#[test]
+fn id_expiry() {
+  let mut db = MemDb::new();
+
+  let mut genesis = [0; 32];
+  OsRng.fill_bytes(&mut genesis);
+  let mut id = [0; 32];
+  OsRng.fill_bytes(&mut id);
+
+  let status = |db: &MemDb, label, block| id_status::<MemDb, _>(db, label, genesis, id, block);
+
+  // An ID which was never recognized shouldn't be accepted
+  assert_eq!(status(&db, "batch", 0), IdStatus::Unrecognized);
+
+  let recognized = 5;
+  let mut txn = db.txn();
+  TributaryDb::<MemDb>::recognize_id(&mut txn, "batch", genesis, id, recognized);
+  txn.commit();
+
+  // The ID should be active for its lifetime, and expire after
+  assert_eq!(status(&db, "batch", recognized), IdStatus::Active);
+  assert_eq!(status(&db, "batch", recognized + RECOGNIZED_ID_LIFETIME), IdStatus::Active);
+  assert_eq!(status(&db, "batch", recognized + RECOGNIZED_ID_LIFETIME + 1), IdStatus::Expired);
+
+  // Recognition is specific to the label
+  assert_eq!(status(&db, "sign", recognized), IdStatus::Unrecognized);
+
+  // Completing the ID's signing protocol should expire it immediately
+  let mut txn = db.txn();
+  TributaryDb::<MemDb>::complete_id(&mut txn, "batch", genesis, id);
+  txn.commit();
+  assert_eq!(status(&db, "batch", recognized), IdStatus::Expired);
+}
+
+#[test]
+fn pruned_data_dedupes() {
+  let mut db = MemDb::new();
+
+  let mut genesis = [0; 32];
+  OsRng.fill_bytes(&mut genesis);
+  let mut id = [0; 32];
+  OsRng.fill_bytes(&mut id);
+  let signer = Ristretto::generator() * <Ristretto as Ciphersuite>::F::random(&mut OsRng);
+
+  let label = DataLabel::SignPreprocess;
+  let mut data = vec![0; 64];
+  OsRng.fill_bytes(&mut data);
+  let hash: [u8; 32] = Blake2s256::digest(&data).into();
+
+  let mut txn = db.txn();
+  assert_eq!(TributaryDb::<MemDb>::set_data(label, &mut txn, genesis, id, 0, signer, &data), 1);
+  txn.commit();
+  assert_eq!(TributaryDb::<MemDb>::data(label, &db, genesis, id, 0, signer), Some(data.clone()));
+  assert_eq!(TributaryDb::<MemDb>::data_hash(label, &db, genesis, id, 0, signer), Some(hash));
+
+  let mut txn = db.txn();
+  TributaryDb::<MemDb>::prune_data(label, &mut txn, genesis, id, 0, signer);
+  txn.commit();
+
+  // The data itself should be gone, yet its hash should remain so a republication is detected
+  assert_eq!(TributaryDb::<MemDb>::data(label, &db, genesis, id, 0, signer), None);
+  assert_eq!(TributaryDb::<MemDb>::data_hash(label, &db, genesis, id, 0, signer), Some(hash));
+
+  // The hash is specific to the label, attempt, and signer
+  assert_eq!(
+    TributaryDb::<MemDb>::data_hash(DataLabel::SignShare, &db, genesis, id, 0, signer),
+    None
+  );
+  assert_eq!(TributaryDb::<MemDb>::data_hash(label, &db, genesis, id, 1, signer), None);
+}
+
+#[test]
+fn data_size_bounds() {
+  let sign_data = |len| {
+    let mut data = random_sign_data(&mut OsRng);
+    data.data = vec![0; len];
+    data
+  };
+
+  assert!(Transaction::BatchPreprocess(sign_data(64)).verify().is_ok());
+  assert_eq!(
+    Transaction::BatchPreprocess(sign_data(65)).verify(),
+    Err(TransactionError::InvalidContent)
+  );
+
+  // Batch shares have a fixed size
+  assert!(Transaction::BatchShare(sign_data(32)).verify().is_ok());
+  assert!(Transaction::BatchShare(sign_data(31)).verify().is_err());
+  assert!(Transaction::BatchShare(sign_data(33)).verify().is_err());
+
+  let shares = |len| {
+    let mut shares = HashMap::new();
+    shares.insert(Participant::new(1).unwrap(), vec![0; 32]);
+    shares.insert(Participant::new(2).unwrap(), vec![0; len]);
+    Transaction::DkgShares(0, shares, random_signed(&mut OsRng))
+  };
+  let max = DataLabel::DkgShares.max_size();
+  assert!(shares(max).verify().is_ok());
+  assert_eq!(shares(max + 1).verify(), Err(TransactionError::InvalidContent));
+
+  // Data for signing coin transactions is only bound by what can be serialized
+  let max = usize::from(u16::MAX);
+  assert!(Transaction::SignPreprocess(sign_data(max)).verify().is_ok());
+  assert!(Transaction::SignShare(sign_data(max + 1)).verify().is_err());
+}
+
+#[test]
+fn provided_order() {
+  let mut db = MemDb::new();
+
+  let mut genesis = [0; 32];
+  OsRng.fill_bytes(&mut genesis);
+
+  let check = |db: &MemDb, kind, this| check_provided_order::<MemDb, _>(db, kind, genesis, this);
+
+  // Nothing was provided yet, so anything is in order
+  assert_eq!(check(&db, "external", 5), Ok(()));
+
+  let mut txn = db.txn();
+  TributaryDb::<MemDb>::set_last_provided(&mut txn, "external", genesis, 5);
+  txn.commit();
+
+  assert_eq!(check(&db, "external", 5), Err(ProvidedOrderError::Duplicate(5)));
+  assert_eq!(check(&db, "external", 4), Err(ProvidedOrderError::OutOfOrder { last: 5, this: 4 }));
+  assert_eq!(check(&db, "external", 6), Ok(()));
+
+  // Order is tracked per kind of provided transaction
+  assert_eq!(check(&db, "serai", 4), Ok(()));
+}
+
+#[tokio::test]
+async fn not_provided() {
+  let keys = new_keys(&mut OsRng);
+  let spec = new_spec(&mut OsRng, &keys);
+  let genesis = spec.genesis();
+  let processor = MemProcessor::new();
+  let mut db = TributaryDb(MemDb::new());
+
+  let block = |tx| Block {
+    header: BlockHeader { parent: [0; 32], transactions: [0; 32] },
+    transactions: vec![tx],
+    signature: None,
+  };
+
+  let mut ext_block = [0; 32];
+  OsRng.fill_bytes(&mut ext_block);
+  let mut batch = [0; 32];
+  OsRng.fill_bytes(&mut batch);
+
+  // A batch we never provided can't be handled
+  let key = &keys[0];
+  let tx = Transaction::Batch(ext_block, batch);
+  assert_eq!(
+    handle_block(&mut db, key, &processor, &spec, 1, block(tx.clone()), false).await,
+    Err(ProvidedOrderError::NotProvided)
+  );
+  assert_eq!(TributaryDb::<MemDb>::last_provided(&db.0, "external", genesis), None);
+
+  // Once provided, it can be
+  let mut txn = db.0.txn();
+  TributaryDb::<MemDb>::set_batch(&mut txn, genesis, batch, ext_block);
+  txn.commit();
+  assert_eq!(handle_block(&mut db, key, &processor, &spec, 1, block(tx), false).await, Ok(()));
+  assert_eq!(id_status::<MemDb, _>(&db.0, "batch", genesis, batch, 1), IdStatus::Active);
+
+  // The same is true for Substrate blocks
+  let tx = Transaction::SubstrateBlock(1);
+  assert_eq!(
+    handle_block(&mut db, key, &processor, &spec, 1, block(tx.clone()), false).await,
+    Err(ProvidedOrderError::NotProvided)
+  );
+  let mut txn = db.0.txn();
+  TributaryDb::<MemDb>::set_plan_ids(&mut txn, genesis, 1, &[]);
+  txn.commit();
+  assert_eq!(handle_block(&mut db, key, &processor, &spec, 1, block(tx), false).await, Ok(()));
+  assert_eq!(TributaryDb::<MemDb>::last_provided(&db.0, "serai", genesis), Some(1));
+}
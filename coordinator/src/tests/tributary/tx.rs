@@ -47,6 +47,7 @@ async fn tx_test() {
   // All tributaries should have acknowledged this transaction in a block
   for (_, tributary) in tributaries {
     let block = tributary.reader().block(&included_in).unwrap();
-    assert_eq!(block.transactions, vec![tx.clone()]);
+    // Blocks don't carry the s of their transactions' signatures, so compare by hash
+    assert_eq!(block.transactions.iter().map(|tx| tx.hash()).collect::<Vec<_>>(), vec![tx.hash()]);
   }
 }
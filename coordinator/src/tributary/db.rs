@@ -1,7 +1,11 @@
 use std::io::Read;
 
+use blake2::{Digest, Blake2s256};
+
 use ciphersuite::{group::GroupEncoding, Ciphersuite, Ristretto};
 
+use crate::tributary::DataLabel;
+
 pub use serai_db::*;
 
 #[derive(Debug)]
@@ -18,21 +22,62 @@ impl<D: Db> TributaryDb<D> {
   fn block_key(genesis: [u8; 32]) -> Vec<u8> {
     Self::tributary_key(b"block", genesis)
   }
+  fn block_number_key(genesis: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"block_number", genesis)
+  }
   pub fn set_last_block(&mut self, genesis: [u8; 32], block: [u8; 32]) {
+    let number = self.last_block_number(genesis) + 1;
     let mut txn = self.0.txn();
     txn.put(Self::block_key(genesis), block);
+    txn.put(Self::block_number_key(genesis), number.to_le_bytes());
     txn.commit();
   }
   pub fn last_block(&self, genesis: [u8; 32]) -> [u8; 32] {
     self.0.get(Self::block_key(genesis)).map(|last| last.try_into().unwrap()).unwrap_or(genesis)
   }
+  // The number of the last handled block, where the genesis block is 0
+  pub fn last_block_number(&self, genesis: [u8; 32]) -> u64 {
+    self
+      .0
+      .get(Self::block_number_key(genesis))
+      .map(|number| u64::from_le_bytes(number.try_into().unwrap()))
+      .unwrap_or(0)
+  }
 
   // This shouldn't need genesis? Yet it's saner to have then quibble about.
-  fn batch_id_key(genesis: &[u8], ext_block: [u8; 32]) -> Vec<u8> {
-    Self::tributary_key(b"batch_id", [genesis, ext_block.as_ref()].concat())
+  fn batch_key(genesis: &[u8], batch: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"batch", [genesis, batch.as_ref()].concat())
+  }
+  fn external_blocks_key(genesis: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"external_blocks", genesis)
   }
-  pub fn batch_id<G: Get>(getter: &G, genesis: [u8; 32], ext_block: [u8; 32]) -> Option<[u8; 32]> {
-    getter.get(Self::batch_id_key(&genesis, ext_block)).map(|bytes| bytes.try_into().unwrap())
+  // Returns the external block for a batch, along with the index the batch was provided at
+  pub fn batch<G: Get>(getter: &G, genesis: [u8; 32], batch: [u8; 32]) -> Option<([u8; 32], u64)> {
+    getter.get(Self::batch_key(&genesis, batch)).map(|bytes| {
+      (bytes[.. 32].try_into().unwrap(), u64::from_le_bytes(bytes[32 ..].try_into().unwrap()))
+    })
+  }
+  pub fn set_batch(
+    txn: &mut D::Transaction<'_>,
+    genesis: [u8; 32],
+    batch: [u8; 32],
+    ext_block: [u8; 32],
+  ) {
+    // If this batch was already provided, keep the index it was originally provided at
+    if Self::batch(txn, genesis, batch).is_some() {
+      return;
+    }
+
+    let external_blocks_key = Self::external_blocks_key(genesis);
+    let index = txn
+      .get(&external_blocks_key)
+      .map(|index| u64::from_le_bytes(index.try_into().unwrap()))
+      .unwrap_or(0);
+    txn.put(external_blocks_key, (index + 1).to_le_bytes());
+    txn.put(
+      Self::batch_key(&genesis, batch),
+      [ext_block.as_ref(), index.to_le_bytes().as_ref()].concat(),
+    );
   }
 
   fn plan_ids_key(genesis: &[u8], block: u64) -> Vec<u8> {
@@ -50,25 +95,88 @@ impl<D: Db> TributaryDb<D> {
       res
     })
   }
+  pub fn set_plan_ids(
+    txn: &mut D::Transaction<'_>,
+    genesis: [u8; 32],
+    block: u64,
+    plans: &[[u8; 32]],
+  ) {
+    txn.put(Self::plan_ids_key(&genesis, block), plans.concat());
+  }
+
+  // The highest index (for external blocks) or number (for Substrate blocks) of any provided
+  // transaction of this kind which has been handled
+  fn last_provided_key(kind: &'static str, genesis: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"last_provided", [kind.as_bytes(), genesis.as_ref()].concat())
+  }
+  pub fn last_provided<G: Get>(getter: &G, kind: &'static str, genesis: [u8; 32]) -> Option<u64> {
+    getter
+      .get(Self::last_provided_key(kind, genesis))
+      .map(|last| u64::from_le_bytes(last.try_into().unwrap()))
+  }
+  pub fn set_last_provided(
+    txn: &mut D::Transaction<'_>,
+    kind: &'static str,
+    genesis: [u8; 32],
+    last: u64,
+  ) {
+    txn.put(Self::last_provided_key(kind, genesis), last.to_le_bytes());
+  }
 
   fn recognized_id_key(label: &'static str, genesis: [u8; 32], id: [u8; 32]) -> Vec<u8> {
     Self::tributary_key(b"recognized", [label.as_bytes(), genesis.as_ref(), id.as_ref()].concat())
   }
+  // Returns the number of the Tributary block which recognized this ID, if it was recognized
   pub fn recognized_id<G: Get>(
     getter: &G,
     label: &'static str,
     genesis: [u8; 32],
     id: [u8; 32],
-  ) -> bool {
-    getter.get(Self::recognized_id_key(label, genesis, id)).is_some()
+  ) -> Option<u64> {
+    getter
+      .get(Self::recognized_id_key(label, genesis, id))
+      .map(|block| u64::from_le_bytes(block.try_into().unwrap()))
   }
   pub fn recognize_id(
     txn: &mut D::Transaction<'_>,
     label: &'static str,
     genesis: [u8; 32],
     id: [u8; 32],
+    block: u64,
   ) {
-    txn.put(Self::recognized_id_key(label, genesis, id), [])
+    txn.put(Self::recognized_id_key(label, genesis, id), block.to_le_bytes())
+  }
+
+  fn completed_id_key(label: &'static str, genesis: [u8; 32], id: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"completed", [label.as_bytes(), genesis.as_ref(), id.as_ref()].concat())
+  }
+  // Mark the signing protocol for this ID as completed, expiring the ID
+  pub fn complete_id(
+    txn: &mut D::Transaction<'_>,
+    label: &'static str,
+    genesis: [u8; 32],
+    id: [u8; 32],
+  ) {
+    txn.put(Self::completed_id_key(label, genesis, id), [])
+  }
+  // Reopen an ID whose signing protocol completed, so it may be signed again, as of this block
+  pub fn reopen_id(
+    txn: &mut D::Transaction<'_>,
+    label: &'static str,
+    genesis: [u8; 32],
+    id: [u8; 32],
+    block: u64,
+  ) {
+    txn.del(Self::completed_id_key(label, genesis, id));
+    Self::recognize_id(txn, label, genesis, id, block);
+  }
+  pub fn completed_id<G: Get>(
+    getter: &G,
+    label: &'static str,
+    genesis: [u8; 32],
+    id: [u8; 32],
+  ) -> bool {
+    getter.get(Self::completed_id_key(label, genesis, id)).is_some()
   }
 
   fn attempt_key(genesis: [u8; 32], id: [u8; 32]) -> Vec<u8> {
@@ -80,39 +188,66 @@ impl<D: Db> TributaryDb<D> {
       getter.get(Self::attempt_key(genesis, id)).unwrap_or(vec![0; 4]).try_into().unwrap(),
     )
   }
+  pub fn set_attempt(txn: &mut D::Transaction<'_>, genesis: [u8; 32], id: [u8; 32], attempt: u32) {
+    txn.put(Self::attempt_key(genesis, id), attempt.to_le_bytes())
+  }
+
+  // How many times the fee of the transaction for this plan has been bumped
+  fn fee_bumps_key(genesis: [u8; 32], id: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"fee_bumps", [genesis, id].concat())
+  }
+  pub fn fee_bumps<G: Get>(getter: &G, genesis: [u8; 32], id: [u8; 32]) -> u32 {
+    getter
+      .get(Self::fee_bumps_key(genesis, id))
+      .map(|bumps| u32::from_le_bytes(bumps.try_into().unwrap()))
+      .unwrap_or(0)
+  }
+  pub fn set_fee_bumps(txn: &mut D::Transaction<'_>, genesis: [u8; 32], id: [u8; 32], bumps: u32) {
+    txn.put(Self::fee_bumps_key(genesis, id), bumps.to_le_bytes())
+  }
 
-  fn data_received_key(
-    label: &'static [u8],
+  fn data_received_key(label: DataLabel, genesis: [u8; 32], id: [u8; 32], attempt: u32) -> Vec<u8> {
+    Self::tributary_key(
+      b"data_received",
+      [label.bytes(), genesis.as_ref(), id.as_ref(), attempt.to_le_bytes().as_ref()].concat(),
+    )
+  }
+  fn data_item(
+    label: DataLabel,
     genesis: [u8; 32],
     id: [u8; 32],
     attempt: u32,
+    signer: <Ristretto as Ciphersuite>::G,
   ) -> Vec<u8> {
-    Self::tributary_key(
-      b"data_received",
-      [label, genesis.as_ref(), id.as_ref(), attempt.to_le_bytes().as_ref()].concat(),
-    )
+    [
+      label.bytes(),
+      genesis.as_ref(),
+      id.as_ref(),
+      attempt.to_le_bytes().as_ref(),
+      signer.to_bytes().as_ref(),
+    ]
+    .concat()
   }
   fn data_key(
-    label: &'static [u8],
+    label: DataLabel,
     genesis: [u8; 32],
     id: [u8; 32],
     attempt: u32,
     signer: <Ristretto as Ciphersuite>::G,
   ) -> Vec<u8> {
-    Self::tributary_key(
-      b"data",
-      [
-        label,
-        genesis.as_ref(),
-        id.as_ref(),
-        attempt.to_le_bytes().as_ref(),
-        signer.to_bytes().as_ref(),
-      ]
-      .concat(),
-    )
+    Self::tributary_key(b"data", Self::data_item(label, genesis, id, attempt, signer))
+  }
+  fn data_hash_key(
+    label: DataLabel,
+    genesis: [u8; 32],
+    id: [u8; 32],
+    attempt: u32,
+    signer: <Ristretto as Ciphersuite>::G,
+  ) -> Vec<u8> {
+    Self::tributary_key(b"data_hash", Self::data_item(label, genesis, id, attempt, signer))
   }
   pub fn data<G: Get>(
-    label: &'static [u8],
+    label: DataLabel,
     getter: &G,
     genesis: [u8; 32],
     id: [u8; 32],
@@ -122,7 +257,7 @@ impl<D: Db> TributaryDb<D> {
     getter.get(Self::data_key(label, genesis, id, attempt, signer))
   }
   pub fn set_data(
-    label: &'static [u8],
+    label: DataLabel,
     txn: &mut D::Transaction<'_>,
     genesis: [u8; 32],
     id: [u8; 32],
@@ -140,15 +275,58 @@ impl<D: Db> TributaryDb<D> {
 
     received
   }
+  // The hash of the data, available even after the data itself has been pruned
+  pub fn data_hash<G: Get>(
+    label: DataLabel,
+    getter: &G,
+    genesis: [u8; 32],
+    id: [u8; 32],
+    attempt: u32,
+    signer: <Ristretto as Ciphersuite>::G,
+  ) -> Option<[u8; 32]> {
+    Self::data(label, getter, genesis, id, attempt, signer)
+      .map(|data| Blake2s256::digest(data).into())
+      .or_else(|| {
+        getter
+          .get(Self::data_hash_key(label, genesis, id, attempt, signer))
+          .map(|hash| hash.try_into().unwrap())
+      })
+  }
+  // Replace the data with its hash, once it's no longer needed
+  pub fn prune_data(
+    label: DataLabel,
+    txn: &mut D::Transaction<'_>,
+    genesis: [u8; 32],
+    id: [u8; 32],
+    attempt: u32,
+    signer: <Ristretto as Ciphersuite>::G,
+  ) {
+    let data_key = Self::data_key(label, genesis, id, attempt, signer);
+    if let Some(data) = txn.get(&data_key) {
+      let hash: [u8; 32] = Blake2s256::digest(data).into();
+      txn.put(Self::data_hash_key(label, genesis, id, attempt, signer), hash);
+      txn.del(data_key);
+    }
+  }
 
-  fn event_key(id: &[u8], index: u32) -> Vec<u8> {
-    Self::tributary_key(b"event", [id, index.to_le_bytes().as_ref()].concat())
+  // Transactions are identified by their hash, not their position in the block, so the order
+  // they're handled in doesn't affect which are considered handled
+  fn handled_transaction_key(block: [u8; 32], tx: [u8; 32]) -> Vec<u8> {
+    Self::tributary_key(b"handled_transaction", [block, tx].concat())
   }
-  pub fn handled_event<G: Get>(getter: &G, id: [u8; 32], index: u32) -> bool {
-    getter.get(Self::event_key(&id, index)).is_some()
+  // Returns the index of this transaction within its block, if it was handled
+  pub fn handled_transaction<G: Get>(getter: &G, block: [u8; 32], tx: [u8; 32]) -> Option<u32> {
+    getter
+      .get(Self::handled_transaction_key(block, tx))
+      .map(|index| u32::from_le_bytes(index.try_into().unwrap()))
   }
-  pub fn handle_event(txn: &mut D::Transaction<'_>, id: [u8; 32], index: u32) {
-    assert!(!Self::handled_event(txn, id, index));
-    txn.put(Self::event_key(&id, index), []);
+  pub fn handle_transaction(
+    txn: &mut D::Transaction<'_>,
+    block: [u8; 32],
+    index: u32,
+    tx: [u8; 32],
+  ) {
+    assert!(Self::handled_transaction(txn, block, tx).is_none());
+    txn.put(Self::handled_transaction_key(block, tx), index.to_le_bytes());
   }
 }
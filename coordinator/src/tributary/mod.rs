@@ -15,7 +15,10 @@ use ciphersuite::{
   Ciphersuite, Ristretto,
 };
 use schnorr::SchnorrSignature;
-use frost::Participant;
+use frost::{
+  Participant,
+  dkg::{DkgError, participants::ParticipantSet},
+};
 
 use scale::{Encode, Decode};
 
@@ -40,6 +43,8 @@ pub struct TributarySpec {
   start_time: u64,
   set: ValidatorSet,
   validators: Vec<(<Ristretto as Ciphersuite>::G, u64)>,
+  // Derived from the validators, yet cached as it's needed for every Participant lookup
+  participants: ParticipantSet<Ristretto>,
 }
 
 impl TributarySpec {
@@ -58,7 +63,16 @@ impl TributarySpec {
       validators.push((participant, amount.0 / set_data.bond.0));
     }
 
-    Self { serai_block, start_time, set, validators }
+    let participants =
+      Self::participant_set(&validators).expect("validator set had duplicated validators");
+    Self { serai_block, start_time, set, validators, participants }
+  }
+
+  fn participant_set(
+    validators: &[(<Ristretto as Ciphersuite>::G, u64)],
+  ) -> Result<ParticipantSet<Ristretto>, DkgError<()>> {
+    // TODO: Support multiple key shares, assigning each validator as many indexes as their weight
+    ParticipantSet::new(validators.iter().map(|(validator, _)| *validator).collect())
   }
 
   pub fn set(&self) -> ValidatorSet {
@@ -91,18 +105,12 @@ impl TributarySpec {
     (2 * (self.n() / 3)) + 1
   }
 
+  pub fn participants(&self) -> &ParticipantSet<Ristretto> {
+    &self.participants
+  }
+
   pub fn i(&self, key: <Ristretto as Ciphersuite>::G) -> Option<Participant> {
-    let mut i = 1;
-    // TODO: Support multiple key shares
-    for (validator, _weight) in &self.validators {
-      if validator == &key {
-        // return (i .. (i + weight)).to_vec();
-        return Some(Participant::new(i).unwrap());
-      }
-      // i += weight;
-      i += 1;
-    }
-    None
+    self.participants().i(key)
   }
 
   pub fn validators(&self) -> Vec<(<Ristretto as Ciphersuite>::G, u64)> {
@@ -159,7 +167,33 @@ impl TributarySpec {
       validators.push((key, u64::from_le_bytes(bond)));
     }
 
-    Ok(Self { serai_block, start_time, set: ValidatorSet { session, network }, validators })
+    let participants = Self::participant_set(&validators)
+      .map_err(|_| io::Error::new(io::ErrorKind::Other, "invalid validators"))?;
+
+    Ok(Self {
+      serai_block,
+      start_time,
+      set: ValidatorSet { session, network },
+      validators,
+      participants,
+    })
+  }
+}
+
+// Transactions within blocks are serialized without their signature's s, which is aggregated
+fn read_signed<R: io::Read>(reader: &mut R, with_s: bool) -> io::Result<Signed> {
+  if with_s {
+    Signed::read(reader)
+  } else {
+    Signed::read_without_s(reader)
+  }
+}
+
+fn write_signed<W: io::Write>(signed: &Signed, writer: &mut W, with_s: bool) -> io::Result<()> {
+  if with_s {
+    signed.write(writer)
+  } else {
+    signed.write_without_s(writer)
   }
 }
 
@@ -173,8 +207,8 @@ pub struct SignData {
   pub signed: Signed,
 }
 
-impl ReadWrite for SignData {
-  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+impl SignData {
+  fn read_inner<R: io::Read>(reader: &mut R, with_s: bool) -> io::Result<Self> {
     let mut plan = [0; 32];
     reader.read_exact(&mut plan)?;
 
@@ -190,12 +224,12 @@ impl ReadWrite for SignData {
       data
     };
 
-    let signed = Signed::read(reader)?;
+    let signed = read_signed(reader, with_s)?;
 
     Ok(SignData { plan, attempt, data, signed })
   }
 
-  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+  fn write_inner<W: io::Write>(&self, writer: &mut W, with_s: bool) -> io::Result<()> {
     writer.write_all(&self.plan)?;
     writer.write_all(&self.attempt.to_le_bytes())?;
 
@@ -210,7 +244,62 @@ impl ReadWrite for SignData {
     writer.write_all(&u16::try_from(self.data.len()).unwrap().to_le_bytes())?;
     writer.write_all(&self.data)?;
 
-    self.signed.write(writer)
+    write_signed(&self.signed, writer, with_s)
+  }
+}
+
+impl ReadWrite for SignData {
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read_inner(reader, true)
+  }
+
+  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write_inner(writer, true)
+  }
+}
+
+/// The kinds of data published on a Tributary, used to namespace the data when it's stored.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DataLabel {
+  DkgCommitments,
+  DkgShares,
+  BatchPreprocess,
+  BatchShare,
+  SignPreprocess,
+  SignShare,
+  FeeBump,
+}
+
+impl DataLabel {
+  pub fn bytes(&self) -> &'static [u8] {
+    match self {
+      DataLabel::DkgCommitments => b"dkg_commitments",
+      DataLabel::DkgShares => b"dkg_shares",
+      DataLabel::BatchPreprocess => b"batch_preprocess",
+      DataLabel::BatchShare => b"batch_share",
+      DataLabel::SignPreprocess => b"sign_preprocess",
+      DataLabel::SignShare => b"sign_share",
+      DataLabel::FeeBump => b"fee_bump",
+    }
+  }
+
+  /// The maximum size of a single piece of data with this label.
+  ///
+  /// For DkgShares, this is the maximum size of the share for a single participant.
+  pub fn max_size(&self) -> usize {
+    match self {
+      // The commitments for both the Substrate and coin keys
+      DataLabel::DkgCommitments => u16::MAX.into(),
+      // A single participant's encrypted shares for both the Substrate and coin keys
+      DataLabel::DkgShares => 1024,
+      // Schnorrkel's two nonce commitments
+      DataLabel::BatchPreprocess => 64,
+      DataLabel::BatchShare => 32,
+      // The preprocesses and shares for coin transactions scale with their amount of inputs
+      DataLabel::SignPreprocess | DataLabel::SignShare => u16::MAX.into(),
+      // Requesting a fee bump solely votes for it
+      DataLabel::FeeBump => 0,
+    }
   }
 }
 
@@ -220,9 +309,10 @@ pub enum Transaction {
   DkgCommitments(u32, Vec<u8>, Signed),
   DkgShares(u32, HashMap<Participant, Vec<u8>>, Signed),
 
-  // When an external block is finalized, we can allow the associated batch IDs
+  // When an external block's batch is finalized, we can allow the batch's ID
   // Commits to the full block so eclipsed nodes don't continue on their eclipsed state
-  ExternalBlock([u8; 32]),
+  // A block may have multiple batches, each of which is provided as its own transaction
+  Batch([u8; 32], [u8; 32]),
   // When a Serai block is finalized, with the contained batches, we can allow the associated plan
   // IDs
   SubstrateBlock(u64),
@@ -232,10 +322,13 @@ pub enum Transaction {
 
   SignPreprocess(SignData),
   SignShare(SignData),
+  // Vote to re-sign a plan's transaction, which has lingered without confirming, with its fee
+  // bumped the specified amount of times
+  FeeBump([u8; 32], u32, Signed),
 }
 
-impl ReadWrite for Transaction {
-  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+impl Transaction {
+  fn read_inner<R: io::Read>(reader: &mut R, with_s: bool) -> io::Result<Self> {
     let mut kind = [0];
     reader.read_exact(&mut kind)?;
 
@@ -253,7 +346,7 @@ impl ReadWrite for Transaction {
           commitments
         };
 
-        let signed = Signed::read(reader)?;
+        let signed = read_signed(reader, with_s)?;
 
         Ok(Transaction::DkgCommitments(attempt, commitments, signed))
       }
@@ -281,7 +374,7 @@ impl ReadWrite for Transaction {
           shares
         };
 
-        let signed = Signed::read(reader)?;
+        let signed = read_signed(reader, with_s)?;
 
         Ok(Transaction::DkgShares(attempt, shares, signed))
       }
@@ -289,7 +382,9 @@ impl ReadWrite for Transaction {
       2 => {
         let mut block = [0; 32];
         reader.read_exact(&mut block)?;
-        Ok(Transaction::ExternalBlock(block))
+        let mut batch = [0; 32];
+        reader.read_exact(&mut batch)?;
+        Ok(Transaction::Batch(block, batch))
       }
 
       3 => {
@@ -298,17 +393,30 @@ impl ReadWrite for Transaction {
         Ok(Transaction::SubstrateBlock(u64::from_le_bytes(block)))
       }
 
-      4 => SignData::read(reader).map(Transaction::BatchPreprocess),
-      5 => SignData::read(reader).map(Transaction::BatchShare),
+      4 => SignData::read_inner(reader, with_s).map(Transaction::BatchPreprocess),
+      5 => SignData::read_inner(reader, with_s).map(Transaction::BatchShare),
+
+      6 => SignData::read_inner(reader, with_s).map(Transaction::SignPreprocess),
+      7 => SignData::read_inner(reader, with_s).map(Transaction::SignShare),
+
+      8 => {
+        let mut plan = [0; 32];
+        reader.read_exact(&mut plan)?;
+
+        let mut bumps = [0; 4];
+        reader.read_exact(&mut bumps)?;
+        let bumps = u32::from_le_bytes(bumps);
 
-      6 => SignData::read(reader).map(Transaction::SignPreprocess),
-      7 => SignData::read(reader).map(Transaction::SignShare),
+        let signed = read_signed(reader, with_s)?;
+
+        Ok(Transaction::FeeBump(plan, bumps, signed))
+      }
 
       _ => Err(io::Error::new(io::ErrorKind::Other, "invalid transaction type")),
     }
   }
 
-  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+  fn write_inner<W: io::Write>(&self, writer: &mut W, with_s: bool) -> io::Result<()> {
     match self {
       Transaction::DkgCommitments(attempt, commitments, signed) => {
         writer.write_all(&[0])?;
@@ -319,7 +427,7 @@ impl ReadWrite for Transaction {
         }
         writer.write_all(&u16::try_from(commitments.len()).unwrap().to_le_bytes())?;
         writer.write_all(commitments)?;
-        signed.write(writer)
+        write_signed(signed, writer, with_s)
       }
 
       Transaction::DkgShares(attempt, shares, signed) => {
@@ -346,12 +454,13 @@ impl ReadWrite for Transaction {
 
           writer.write_all(share)?;
         }
-        signed.write(writer)
+        write_signed(signed, writer, with_s)
       }
 
-      Transaction::ExternalBlock(block) => {
+      Transaction::Batch(block, batch) => {
         writer.write_all(&[2])?;
-        writer.write_all(block)
+        writer.write_all(block)?;
+        writer.write_all(batch)
       }
 
       Transaction::SubstrateBlock(block) => {
@@ -361,32 +470,49 @@ impl ReadWrite for Transaction {
 
       Transaction::BatchPreprocess(data) => {
         writer.write_all(&[4])?;
-        data.write(writer)
+        data.write_inner(writer, with_s)
       }
       Transaction::BatchShare(data) => {
         writer.write_all(&[5])?;
-        data.write(writer)
+        data.write_inner(writer, with_s)
       }
 
       Transaction::SignPreprocess(data) => {
         writer.write_all(&[6])?;
-        data.write(writer)
+        data.write_inner(writer, with_s)
       }
       Transaction::SignShare(data) => {
         writer.write_all(&[7])?;
-        data.write(writer)
+        data.write_inner(writer, with_s)
+      }
+
+      Transaction::FeeBump(plan, bumps, signed) => {
+        writer.write_all(&[8])?;
+        writer.write_all(plan)?;
+        writer.write_all(&bumps.to_le_bytes())?;
+        write_signed(signed, writer, with_s)
       }
     }
   }
 }
 
+impl ReadWrite for Transaction {
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read_inner(reader, true)
+  }
+
+  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write_inner(writer, true)
+  }
+}
+
 impl TransactionTrait for Transaction {
   fn kind(&self) -> TransactionKind<'_> {
     match self {
       Transaction::DkgCommitments(_, _, signed) => TransactionKind::Signed(signed),
       Transaction::DkgShares(_, _, signed) => TransactionKind::Signed(signed),
 
-      Transaction::ExternalBlock(_) => TransactionKind::Provided("external"),
+      Transaction::Batch(_, _) => TransactionKind::Provided("external"),
       Transaction::SubstrateBlock(_) => TransactionKind::Provided("serai"),
 
       Transaction::BatchPreprocess(data) => TransactionKind::Signed(&data.signed),
@@ -394,6 +520,8 @@ impl TransactionTrait for Transaction {
 
       Transaction::SignPreprocess(data) => TransactionKind::Signed(&data.signed),
       Transaction::SignShare(data) => TransactionKind::Signed(&data.signed),
+
+      Transaction::FeeBump(_, _, signed) => TransactionKind::Signed(signed),
     }
   }
 
@@ -406,11 +534,47 @@ impl TransactionTrait for Transaction {
     Blake2s256::digest(tx).into()
   }
 
+  fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read_inner(reader, false)
+  }
+
+  fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write_inner(writer, false)
+  }
+
   fn verify(&self) -> Result<(), TransactionError> {
-    if let Transaction::BatchShare(data) = self {
-      if data.data.len() != 32 {
+    // Bound the size of all data so oversized data never makes it on chain
+    fn within(label: DataLabel, data: &[u8]) -> Result<(), TransactionError> {
+      if data.len() > label.max_size() {
         Err(TransactionError::InvalidContent)?;
       }
+      Ok(())
+    }
+
+    match self {
+      Transaction::DkgCommitments(_, commitments, _) => {
+        within(DataLabel::DkgCommitments, commitments)?
+      }
+      Transaction::DkgShares(_, shares, _) => {
+        for share in shares.values() {
+          within(DataLabel::DkgShares, share)?;
+        }
+      }
+
+      Transaction::Batch(_, _) => {}
+      Transaction::SubstrateBlock(_) => {}
+
+      Transaction::BatchPreprocess(data) => within(DataLabel::BatchPreprocess, &data.data)?,
+      Transaction::BatchShare(data) => {
+        if data.data.len() != DataLabel::BatchShare.max_size() {
+          Err(TransactionError::InvalidContent)?;
+        }
+      }
+
+      Transaction::SignPreprocess(data) => within(DataLabel::SignPreprocess, &data.data)?,
+      Transaction::SignShare(data) => within(DataLabel::SignShare, &data.data)?,
+
+      Transaction::FeeBump(_, _, _) => {}
     }
 
     Ok(())
@@ -443,7 +607,7 @@ impl Transaction {
         Transaction::DkgCommitments(_, _, ref mut signed) => signed,
         Transaction::DkgShares(_, _, ref mut signed) => signed,
 
-        Transaction::ExternalBlock(_) => panic!("signing ExternalBlock"),
+        Transaction::Batch(_, _) => panic!("signing Batch"),
         Transaction::SubstrateBlock(_) => panic!("signing SubstrateBlock"),
 
         Transaction::BatchPreprocess(ref mut data) => &mut data.signed,
@@ -451,18 +615,20 @@ impl Transaction {
 
         Transaction::SignPreprocess(ref mut data) => &mut data.signed,
         Transaction::SignShare(ref mut data) => &mut data.signed,
+
+        Transaction::FeeBump(_, _, ref mut signed) => signed,
       }
     }
 
+    let sig_nonce = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(rng));
+
     let signed_ref = signed(self);
     signed_ref.signer = Ristretto::generator() * key.deref();
     signed_ref.nonce = nonce;
+    // The sig hash binds to the signature's nonce, so it must be set first
+    signed_ref.signature.R = Ristretto::generator() * sig_nonce.deref();
 
     let sig_hash = self.sig_hash(genesis);
-    signed(self).signature = SchnorrSignature::<Ristretto>::sign(
-      key,
-      Zeroizing::new(<Ristretto as Ciphersuite>::F::random(rng)),
-      sig_hash,
-    );
+    signed(self).signature = SchnorrSignature::<Ristretto>::sign(key, sig_nonce, sig_hash);
   }
 }
@@ -3,9 +3,11 @@ use std::collections::HashMap;
 
 use zeroize::Zeroizing;
 
+use blake2::{Digest, Blake2s256};
+
 use ciphersuite::{Ciphersuite, Ristretto};
 
-use tributary::{Signed, Block, TributaryReader};
+use tributary::{Signed, Block, TributaryReader, Transaction as TransactionTrait};
 
 use processor_messages::{
   key_gen::{self, KeyGenId},
@@ -13,29 +15,98 @@ use processor_messages::{
   coordinator, CoordinatorMessage,
 };
 
-use serai_db::DbTxn;
+use serai_client::validator_sets::primitives::KeyPair;
+
+use serai_db::{Get, DbTxn};
 
 use crate::{
   Db,
+  db::MainDb,
   processor::Processor,
-  tributary::{TributaryDb, TributarySpec, Transaction},
+  tributary::{TributaryDb, TributarySpec, DataLabel, Transaction},
 };
 
+// How many Tributary blocks an ID remains recognized for (~1 hour)
+// Data for an ID after this is either a replay or too late to be used
+pub const RECOGNIZED_ID_LIFETIME: u64 = 600;
+
+// The key pair Serai confirmed for the set this Tributary is for
+fn set_keys<D: Db, G: Get>(getter: &G, spec: &TributarySpec) -> KeyPair {
+  MainDb::<D>::set_keys(getter, spec.set()).expect("signing with a set which never confirmed keys")
+}
+
+/// A provided transaction which violated the invariants provided transactions are expected to
+/// uphold.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProvidedOrderError {
+  /// We haven't provided this transaction ourselves, and accordingly lack the data to handle it.
+  NotProvided,
+  /// A provided transaction of this kind, with this index or number, was already handled.
+  Duplicate(u64),
+  /// A provided transaction of this kind which was provided after this one was already handled.
+  OutOfOrder { last: u64, this: u64 },
+}
+
+// Check a provided transaction comes after every provided transaction of its kind handled so far
+pub fn check_provided_order<D: Db, G: Get>(
+  getter: &G,
+  kind: &'static str,
+  genesis: [u8; 32],
+  this: u64,
+) -> Result<(), ProvidedOrderError> {
+  match TributaryDb::<D>::last_provided(getter, kind, genesis) {
+    Some(last) if last == this => Err(ProvidedOrderError::Duplicate(this)),
+    Some(last) if last > this => Err(ProvidedOrderError::OutOfOrder { last, this }),
+    _ => Ok(()),
+  }
+}
+
+/// The status of an ID, for the purposes of accepting data for it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IdStatus {
+  /// This ID was never recognized.
+  Unrecognized,
+  /// This ID is recognized and data for it may still be used.
+  Active,
+  /// This ID's lifetime elapsed or its signing protocol completed.
+  Expired,
+}
+
+// The status of an ID as of the specified Tributary block
+pub fn id_status<D: Db, G: Get>(
+  getter: &G,
+  label: &'static str,
+  genesis: [u8; 32],
+  id: [u8; 32],
+  block_number: u64,
+) -> IdStatus {
+  let Some(recognized) = TributaryDb::<D>::recognized_id(getter, label, genesis, id) else {
+    return IdStatus::Unrecognized;
+  };
+  if TributaryDb::<D>::completed_id(getter, label, genesis, id) ||
+    (block_number.saturating_sub(recognized) > RECOGNIZED_ID_LIFETIME)
+  {
+    return IdStatus::Expired;
+  }
+  IdStatus::Active
+}
+
 // Handle a specific Tributary block
-async fn handle_block<D: Db, Pro: Processor>(
+pub async fn handle_block<D: Db, Pro: Processor>(
   db: &mut TributaryDb<D>,
   key: &Zeroizing<<Ristretto as Ciphersuite>::F>,
   processor: &Pro,
   spec: &TributarySpec,
+  block_number: u64,
   block: Block<Transaction>,
-) {
+  prune: bool,
+) -> Result<(), ProvidedOrderError> {
   let genesis = spec.genesis();
   let hash = block.hash();
 
-  let mut event_id = 0;
-  #[allow(clippy::explicit_counter_loop)] // event_id isn't TX index. It just currently lines up
-  for tx in block.transactions {
-    if !TributaryDb::<D>::handled_event(&db.0, hash, event_id) {
+  for (index, tx) in block.transactions.into_iter().enumerate() {
+    let tx_hash = tx.hash();
+    if TributaryDb::<D>::handled_transaction(&db.0, hash, tx_hash).is_none() {
       let mut txn = db.0.txn();
 
       // Used to determine if an ID is acceptable
@@ -59,7 +130,7 @@ async fn handle_block<D: Db, Pro: Processor>(
       }
 
       let mut handle = |zone: Zone,
-                        label,
+                        label: DataLabel,
                         needed,
                         id,
                         attempt,
@@ -68,15 +139,27 @@ async fn handle_block<D: Db, Pro: Processor>(
         if zone == Zone::Dkg {
           // Since Dkg doesn't have an ID, solely attempts, this should just be [0; 32]
           assert_eq!(id, [0; 32], "DKG, which shouldn't have IDs, had a non-0 ID");
-        } else if !TributaryDb::<D>::recognized_id(&txn, zone.label(), genesis, id) {
-          // TODO: Full slash
-          todo!();
+        } else {
+          match id_status::<D, _>(&txn, zone.label(), genesis, id, block_number) {
+            IdStatus::Unrecognized => {
+              // TODO: Full slash
+              todo!();
+            }
+            // Data for an expired ID is either late or for a protocol which already completed
+            // Ignore it, as done for late attempts
+            IdStatus::Expired => {
+              // TODO: Slash for being late
+              return None;
+            }
+            IdStatus::Active => {}
+          }
         }
 
         // If they've already published a TX for this attempt, slash
-        if let Some(data) = TributaryDb::<D>::data(label, &txn, genesis, id, attempt, signed.signer)
+        if let Some(hash) =
+          TributaryDb::<D>::data_hash(label, &txn, genesis, id, attempt, signed.signer)
         {
-          if data != bytes {
+          if hash != <[u8; 32]>::from(Blake2s256::digest(&bytes)) {
             // TODO: Full slash
             todo!();
           }
@@ -125,15 +208,27 @@ async fn handle_block<D: Db, Pro: Processor>(
           }
           assert_eq!(data.len(), usize::from(needed));
 
+          // The processor is about to consume this data, so it no longer has to be stored
+          if prune {
+            for validator in spec.validators().iter().map(|validator| validator.0) {
+              TributaryDb::<D>::prune_data(label, &mut txn, genesis, id, attempt, validator);
+            }
+          }
+
           return Some(data);
         }
+
+        // Data received after the processor was already sent the needed data will never be used
+        if prune && (received > needed) {
+          TributaryDb::<D>::prune_data(label, &mut txn, genesis, id, attempt, signed.signer);
+        }
         None
       };
 
       match tx {
         Transaction::DkgCommitments(attempt, bytes, signed) => {
           if let Some(commitments) =
-            handle(Zone::Dkg, b"dkg_commitments", spec.n(), [0; 32], attempt, bytes, signed)
+            handle(Zone::Dkg, DataLabel::DkgCommitments, spec.n(), [0; 32], attempt, bytes, signed)
           {
             processor
               .send(CoordinatorMessage::KeyGen(key_gen::CoordinatorMessage::Commitments {
@@ -159,7 +254,7 @@ async fn handle_block<D: Db, Pro: Processor>(
             .unwrap();
 
           if let Some(shares) =
-            handle(Zone::Dkg, b"dkg_shares", spec.n(), [0; 32], attempt, bytes, signed)
+            handle(Zone::Dkg, DataLabel::DkgShares, spec.n(), [0; 32], attempt, bytes, signed)
           {
             processor
               .send(CoordinatorMessage::KeyGen(key_gen::CoordinatorMessage::Shares {
@@ -170,35 +265,61 @@ async fn handle_block<D: Db, Pro: Processor>(
           }
         }
 
-        Transaction::ExternalBlock(block) => {
-          // Because this external block has been finalized, its batch ID should be authorized
-
+        Transaction::Batch(block, batch_id) => {
           // If we didn't provide this transaction, we should halt until we do
           // If we provided a distinct transaction, we should error
-          // If we did provide this transaction, we should've set the batch ID for the block
-          let batch_id = TributaryDb::<D>::batch_id(&txn, genesis, block).expect(
-            "synced a tributary block finalizing a external block in a provided transaction \
-            despite us not providing that transaction",
-          );
-
-          TributaryDb::<D>::recognize_id(&mut txn, Zone::Batch.label(), genesis, batch_id);
+          // If we did provide this transaction, we should've set the block for the batch
+          let Some((provided_block, index)) = TributaryDb::<D>::batch(&txn, genesis, batch_id)
+          else {
+            return Err(ProvidedOrderError::NotProvided);
+          };
+          // Block verification checks provided transactions are identical to the ones we provided
+          debug_assert_eq!(provided_block, block);
+
+          match check_provided_order::<D, _>(&txn, "external", genesis, index) {
+            Ok(()) => {
+              // Because this batch's external block has been finalized, its ID should be authorized
+              TributaryDb::<D>::recognize_id(
+                &mut txn,
+                Zone::Batch.label(),
+                genesis,
+                batch_id,
+                block_number,
+              );
+              TributaryDb::<D>::set_last_provided(&mut txn, "external", genesis, index);
+            }
+            // This was already finalized, so it can't be rejected, yet it also can't authorize
+            // anything which wasn't already authorized
+            Err(e) => log::error!("tributary finalized a batch out of order: {e:?}"),
+          }
         }
 
         Transaction::SubstrateBlock(block) => {
-          let plan_ids = TributaryDb::<D>::plan_ids(&txn, genesis, block).expect(
-            "synced a tributary block finalizing a substrate block in a provided transaction \
-            despite us not providing that transaction",
-          );
-
-          for id in plan_ids {
-            TributaryDb::<D>::recognize_id(&mut txn, Zone::Sign.label(), genesis, id);
+          let Some(plan_ids) = TributaryDb::<D>::plan_ids(&txn, genesis, block) else {
+            return Err(ProvidedOrderError::NotProvided);
+          };
+
+          match check_provided_order::<D, _>(&txn, "serai", genesis, block) {
+            Ok(()) => {
+              for id in plan_ids {
+                TributaryDb::<D>::recognize_id(
+                  &mut txn,
+                  Zone::Sign.label(),
+                  genesis,
+                  id,
+                  block_number,
+                );
+              }
+              TributaryDb::<D>::set_last_provided(&mut txn, "serai", genesis, block);
+            }
+            Err(e) => log::error!("tributary finalized a substrate block out of order: {e:?}"),
           }
         }
 
         Transaction::BatchPreprocess(data) => {
           if let Some(preprocesses) = handle(
             Zone::Batch,
-            b"batch_preprocess",
+            DataLabel::BatchPreprocess,
             spec.t(),
             data.plan,
             data.attempt,
@@ -208,7 +329,11 @@ async fn handle_block<D: Db, Pro: Processor>(
             processor
               .send(CoordinatorMessage::Coordinator(
                 coordinator::CoordinatorMessage::BatchPreprocesses {
-                  id: SignId { key: todo!(), id: data.plan, attempt: data.attempt },
+                  id: SignId {
+                    key: set_keys::<D, _>(&txn, spec).0 .0.to_vec(),
+                    id: data.plan,
+                    attempt: data.attempt,
+                  },
                   preprocesses,
                 },
               ))
@@ -218,7 +343,7 @@ async fn handle_block<D: Db, Pro: Processor>(
         Transaction::BatchShare(data) => {
           if let Some(shares) = handle(
             Zone::Batch,
-            b"batch_share",
+            DataLabel::BatchShare,
             spec.t(),
             data.plan,
             data.attempt,
@@ -227,7 +352,11 @@ async fn handle_block<D: Db, Pro: Processor>(
           ) {
             processor
               .send(CoordinatorMessage::Coordinator(coordinator::CoordinatorMessage::BatchShares {
-                id: SignId { key: todo!(), id: data.plan, attempt: data.attempt },
+                id: SignId {
+                  key: set_keys::<D, _>(&txn, spec).0 .0.to_vec(),
+                  id: data.plan,
+                  attempt: data.attempt,
+                },
                 shares: shares
                   .drain()
                   .map(|(validator, share)| (validator, share.try_into().unwrap()))
@@ -240,7 +369,7 @@ async fn handle_block<D: Db, Pro: Processor>(
         Transaction::SignPreprocess(data) => {
           if let Some(preprocesses) = handle(
             Zone::Sign,
-            b"sign_preprocess",
+            DataLabel::SignPreprocess,
             spec.t(),
             data.plan,
             data.attempt,
@@ -249,7 +378,11 @@ async fn handle_block<D: Db, Pro: Processor>(
           ) {
             processor
               .send(CoordinatorMessage::Sign(sign::CoordinatorMessage::Preprocesses {
-                id: SignId { key: todo!(), id: data.plan, attempt: data.attempt },
+                id: SignId {
+                  key: set_keys::<D, _>(&txn, spec).1.into_inner(),
+                  id: data.plan,
+                  attempt: data.attempt,
+                },
                 preprocesses,
               }))
               .await;
@@ -258,7 +391,7 @@ async fn handle_block<D: Db, Pro: Processor>(
         Transaction::SignShare(data) => {
           if let Some(shares) = handle(
             Zone::Sign,
-            b"sign_share",
+            DataLabel::SignShare,
             spec.t(),
             data.plan,
             data.attempt,
@@ -267,35 +400,116 @@ async fn handle_block<D: Db, Pro: Processor>(
           ) {
             processor
               .send(CoordinatorMessage::Sign(sign::CoordinatorMessage::Shares {
-                id: SignId { key: todo!(), id: data.plan, attempt: data.attempt },
+                id: SignId {
+                  key: set_keys::<D, _>(&txn, spec).1.into_inner(),
+                  id: data.plan,
+                  attempt: data.attempt,
+                },
                 shares,
               }))
               .await;
           }
         }
+
+        Transaction::FeeBump(plan, bumps, signed) => {
+          // A plan's transaction may linger well after its signing protocol completed, so this
+          // solely requires the plan to have been recognized
+          if TributaryDb::<D>::recognized_id(&txn, Zone::Sign.label(), genesis, plan).is_none() {
+            // TODO: Full slash
+            todo!();
+          }
+
+          let curr_bumps = TributaryDb::<D>::fee_bumps(&txn, genesis, plan);
+          if bumps <= curr_bumps {
+            // TODO: Slash for being late
+          } else if bumps > (curr_bumps + 1) {
+            // TODO: Full slash
+            todo!();
+          } else if TributaryDb::<D>::data_hash(
+            DataLabel::FeeBump,
+            &txn,
+            genesis,
+            plan,
+            bumps,
+            signed.signer,
+          )
+          .is_some()
+          {
+            // TODO: Slash
+          } else {
+            // Votes are stored under the amount of bumps voted for, as the attempt is for other
+            // data
+            let received = TributaryDb::<D>::set_data(
+              DataLabel::FeeBump,
+              &mut txn,
+              genesis,
+              plan,
+              bumps,
+              signed.signer,
+              &[],
+            );
+
+            // TODO: This needs to be coded by weight, not by validator count
+            if received == spec.t() {
+              // Re-sign the plan under a new attempt, accepting data for it once again
+              let attempt = TributaryDb::<D>::attempt(&txn, genesis, plan) + 1;
+              TributaryDb::<D>::set_attempt(&mut txn, genesis, plan, attempt);
+              TributaryDb::<D>::set_fee_bumps(&mut txn, genesis, plan, bumps);
+              TributaryDb::<D>::reopen_id(
+                &mut txn,
+                Zone::Sign.label(),
+                genesis,
+                plan,
+                block_number,
+              );
+
+              processor
+                .send(CoordinatorMessage::Sign(sign::CoordinatorMessage::BumpFee {
+                  id: SignId {
+                    key: set_keys::<D, _>(&txn, spec).1.into_inner(),
+                    id: plan,
+                    attempt,
+                  },
+                  bumps,
+                }))
+                .await;
+            }
+          }
+        }
       }
 
-      TributaryDb::<D>::handle_event(&mut txn, hash, event_id);
+      TributaryDb::<D>::handle_transaction(&mut txn, hash, index.try_into().unwrap(), tx_hash);
       txn.commit();
     }
-    event_id += 1;
   }
 
   // TODO: Trigger any necessary re-attempts
+
+  Ok(())
 }
 
+/// Handle all new blocks on a Tributary.
+///
+/// If prune is set, the data sent to the processor is replaced with its hash once consumed,
+/// reclaiming space yet preventing it from being sent again.
 pub async fn handle_new_blocks<D: Db, Pro: Processor>(
   db: &mut TributaryDb<D>,
   key: &Zeroizing<<Ristretto as Ciphersuite>::F>,
   processor: &Pro,
   spec: &TributarySpec,
   tributary: &TributaryReader<D, Transaction>,
+  prune: bool,
 ) {
   let genesis = tributary.genesis();
   let mut last_block = db.last_block(genesis);
   while let Some(next) = tributary.block_after(&last_block) {
     let block = tributary.block(&next).unwrap();
-    handle_block(db, key, processor, spec, block).await;
+    let block_number = db.last_block_number(genesis) + 1;
+    if let Err(e) = handle_block(db, key, processor, spec, block_number, block, prune).await {
+      // Halt until this is resolved, retrying the next time we're called
+      log::warn!("couldn't handle tributary block {block_number}: {e:?}");
+      break;
+    }
     last_block = next;
     db.set_last_block(genesis, next);
   }
@@ -1,13 +1,23 @@
 use std::{
   io,
-  collections::{VecDeque, HashMap},
+  collections::{VecDeque, HashSet, HashMap},
 };
 
 use thiserror::Error;
 
 use blake2::{Digest, Blake2s256};
 
-use ciphersuite::{Ciphersuite, Ristretto};
+use ciphersuite::{
+  group::{
+    ff::{Field, PrimeField},
+    GroupEncoding,
+  },
+  Ciphersuite, Ristretto,
+};
+use schnorr::{
+  SchnorrSignature,
+  aggregate::{SchnorrAggregate, SchnorrAggregator},
+};
 
 #[derive(Clone, PartialEq, Eq, Debug, Error)]
 pub enum BlockError {
@@ -23,22 +33,68 @@ pub enum BlockError {
   /// A provided transaction was placed after a non-provided transaction.
   #[error("a provided transaction was included after a non-provided transaction")]
   ProvidedAfterNonProvided,
+  /// The non-provided transactions weren't in their canonical order.
+  #[error("non-provided transactions weren't in their canonical order")]
+  NonCanonicalOrder,
   /// The block had a provided transaction this validator has yet to be provided.
   #[error("block had a provided transaction not yet locally provided: {0:?}")]
   NonLocalProvided([u8; 32]),
   /// The provided transaction was distinct from the locally provided transaction.
   #[error("block had a distinct provided transaction")]
   DistinctProvided,
+  /// An account's transactions exceeded the weight an account may have included in a block.
+  #[error("account's transactions exceeded the per-block weight limit")]
+  TooHeavyAccount,
   /// An included transaction was invalid.
   #[error("included transaction had an error")]
   TransactionError(TransactionError),
 }
 
 use crate::{
-  BLOCK_SIZE_LIMIT, ReadWrite, TransactionError, Signed, TransactionKind, Transaction, merkle,
-  verify_transaction,
+  BLOCK_SIZE_LIMIT, ACCOUNT_BLOCK_WEIGHT_LIMIT, ReadWrite, TransactionError, Signed,
+  TransactionKind, Transaction, merkle, verify_transaction_with,
 };
 
+const AGGREGATE_DST: &[u8] = b"Tributary Block Signature Aggregate";
+
+// SchnorrAggregate doesn't expose its fields, so the following are done via its serialization,
+// which is the amount of signatures, their nonces, and then the aggregated s
+fn aggregate_s(aggregate: &SchnorrAggregate<Ristretto>) -> <Ristretto as Ciphersuite>::F {
+  let aggregate = aggregate.serialize();
+  Ristretto::read_F::<&[u8]>(&mut &aggregate[(aggregate.len() - 32) ..]).unwrap()
+}
+
+#[allow(non_snake_case)]
+fn aggregate_from_parts(
+  Rs: &[<Ristretto as Ciphersuite>::G],
+  s: <Ristretto as Ciphersuite>::F,
+) -> SchnorrAggregate<Ristretto> {
+  let mut aggregate = u32::try_from(Rs.len()).unwrap().to_le_bytes().to_vec();
+  for R in Rs {
+    aggregate.extend(R.to_bytes());
+  }
+  aggregate.extend(s.to_repr());
+  SchnorrAggregate::read::<&[u8]>(&mut aggregate.as_ref()).unwrap()
+}
+
+// If any of these transactions are signed, requiring the block have an aggregate signature
+fn any_signed<T: Transaction>(txs: &[T]) -> bool {
+  txs.iter().any(|tx| matches!(tx.kind(), TransactionKind::Signed(_)))
+}
+
+// The position of a non-provided transaction within a block.
+//
+// Provided transactions come first, in the order they were provided. They're followed by unsigned
+// transactions, ordered by hash, and then signed transactions, ordered by nonce and then signer.
+// This leaves the block producer no discretion over the order transactions are handled in.
+fn canonical_order<T: Transaction>(tx: &T) -> (u8, u32, [u8; 32]) {
+  match tx.kind() {
+    TransactionKind::Provided(_) => panic!("provided transactions don't have a canonical order"),
+    TransactionKind::Unsigned => (0, 0, tx.hash()),
+    TransactionKind::Signed(Signed { signer, nonce, .. }) => (1, *nonce, signer.to_bytes()),
+  }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct BlockHeader {
   pub parent: [u8; 32],
@@ -68,7 +124,15 @@ impl BlockHeader {
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Block<T: Transaction> {
   pub header: BlockHeader,
+  /// The transactions within this block.
+  ///
+  /// Transactions are serialized without their signature's s, so the signatures of transactions
+  /// read from a serialized block will have a s of zero.
   pub transactions: Vec<T>,
+  /// The s of the half-aggregated signature for this block's signed transactions, if it has any.
+  ///
+  /// The nonces for the aggregated signatures are carried by the transactions themselves.
+  pub signature: Option<<Ristretto as Ciphersuite>::F>,
 }
 
 impl<T: Transaction> ReadWrite for Block<T> {
@@ -81,17 +145,22 @@ impl<T: Transaction> ReadWrite for Block<T> {
 
     let mut transactions = Vec::with_capacity(usize::try_from(txs).unwrap());
     for _ in 0 .. txs {
-      transactions.push(T::read(reader)?);
+      transactions.push(T::read_without_s(reader)?);
     }
 
-    Ok(Block { header, transactions })
+    let signature = if any_signed(&transactions) { Some(Ristretto::read_F(reader)?) } else { None };
+
+    Ok(Block { header, transactions, signature })
   }
 
   fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
     self.header.write(writer)?;
     writer.write_all(&u32::try_from(self.transactions.len()).unwrap().to_le_bytes())?;
     for tx in &self.transactions {
-      tx.write(writer)?;
+      tx.write_without_s(writer)?;
+    }
+    if let Some(signature) = self.signature {
+      writer.write_all(&signature.to_repr())?;
     }
     Ok(())
   }
@@ -101,16 +170,44 @@ impl<T: Transaction> Block<T> {
   /// Create a new block.
   ///
   /// mempool is expected to only have valid, non-conflicting transactions.
-  pub(crate) fn new(parent: [u8; 32], provided: Vec<T>, mempool: Vec<T>) -> Self {
+  pub(crate) fn new(
+    genesis: [u8; 32],
+    parent: [u8; 32],
+    provided: Vec<T>,
+    mempool: Vec<T>,
+  ) -> Self {
+    let provided_len = provided.len();
     let mut txs = provided;
+    let mut weights = HashMap::new();
+    let mut exhausted = HashSet::new();
     for tx in mempool {
       assert!(
         !matches!(tx.kind(), TransactionKind::Provided(_)),
         "provided transaction entered mempool"
       );
+
+      // Don't include more weight from an account than it's allowed in a single block
+      // Once an account's transaction is skipped, every later transaction from it must also be, as
+      // they'd have gaps in their nonces
+      if let TransactionKind::Signed(Signed { signer, .. }) = tx.kind() {
+        if exhausted.contains(signer) {
+          continue;
+        }
+        let weight = weights.entry(*signer).or_insert(0);
+        if (*weight + tx.weight()) > ACCOUNT_BLOCK_WEIGHT_LIMIT {
+          exhausted.insert(*signer);
+          continue;
+        }
+        *weight += tx.weight();
+      }
+
       txs.push(tx);
     }
 
+    // Since every account's transactions are included as a contiguous run of nonces, this can't
+    // create gaps
+    txs[provided_len ..].sort_by_key(canonical_order);
+
     // Check TXs are sorted by nonce.
     let nonce = |tx: &T| {
       if let TransactionKind::Signed(Signed { nonce, .. }) = tx.kind() {
@@ -128,11 +225,28 @@ impl<T: Transaction> Block<T> {
       last = nonce;
     }
 
-    let mut res =
-      Block { header: BlockHeader { parent, transactions: [0; 32] }, transactions: txs };
-    while res.serialize().len() > BLOCK_SIZE_LIMIT {
+    let mut res = Block {
+      header: BlockHeader { parent, transactions: [0; 32] },
+      transactions: txs,
+      signature: None,
+    };
+    // Since the aggregate signature is of a fixed size, a placeholder is used while trimming
+    loop {
+      res.signature = any_signed(&res.transactions).then_some(<Ristretto as Ciphersuite>::F::ZERO);
+      if res.serialize().len() <= BLOCK_SIZE_LIMIT {
+        break;
+      }
       assert!(res.transactions.pop().is_some());
     }
+
+    let mut aggregator = SchnorrAggregator::<Ristretto>::new(AGGREGATE_DST);
+    for tx in &res.transactions {
+      if let TransactionKind::Signed(Signed { signature, .. }) = tx.kind() {
+        aggregator.aggregate(tx.sig_hash(genesis), *signature);
+      }
+    }
+    res.signature = aggregator.complete().as_ref().map(aggregate_s);
+
     let hashes = res.transactions.iter().map(Transaction::hash).collect::<Vec<_>>();
     res.header.transactions = merkle(&hashes);
     res
@@ -162,6 +276,11 @@ impl<T: Transaction> Block<T> {
     }
 
     let mut found_non_provided = false;
+    let mut last_order = None;
+    let mut weights = HashMap::new();
+    // The nonces, signers, and challenges of the aggregated signatures
+    let mut nonces = vec![];
+    let mut keys_and_challenges = vec![];
     let mut txs = Vec::with_capacity(self.transactions.len());
     for tx in self.transactions.iter() {
       txs.push(tx.hash());
@@ -185,16 +304,49 @@ impl<T: Transaction> Block<T> {
       }
 
       found_non_provided = true;
-      match verify_transaction(tx, genesis, &mut next_nonces) {
+      // The signature is verified as part of the aggregate once every transaction is verified
+      let aggregate = |signer, signature: &SchnorrSignature<Ristretto>, challenge| {
+        nonces.push(signature.R);
+        keys_and_challenges.push((signer, challenge));
+        true
+      };
+      match verify_transaction_with(tx, genesis, &mut next_nonces, aggregate) {
         Ok(()) => {}
         Err(e) => Err(BlockError::TransactionError(e))?,
       }
+
+      // This is checked after the transaction is verified so duplicated nonces are reported as
+      // such
+      let order = canonical_order(tx);
+      if last_order.as_ref().map(|last| *last >= order).unwrap_or(false) {
+        Err(BlockError::NonCanonicalOrder)?;
+      }
+      last_order = Some(order);
+
+      if let TransactionKind::Signed(Signed { signer, .. }) = tx.kind() {
+        let weight = weights.entry(*signer).or_insert(0);
+        *weight += tx.weight();
+        if *weight > ACCOUNT_BLOCK_WEIGHT_LIMIT {
+          Err(BlockError::TooHeavyAccount)?;
+        }
+      }
     }
 
     if merkle(&txs) != self.header.transactions {
       Err(BlockError::InvalidTransactions)?;
     }
 
+    let valid_signature = match self.signature {
+      Some(s) => {
+        (!nonces.is_empty()) &&
+          aggregate_from_parts(&nonces, s).verify(AGGREGATE_DST, &keys_and_challenges)
+      }
+      None => nonces.is_empty(),
+    };
+    if !valid_signature {
+      Err(BlockError::TransactionError(TransactionError::InvalidSignature))?;
+    }
+
     Ok(())
   }
 }
@@ -125,6 +125,7 @@ impl<D: Db, T: Transaction> Blockchain<D, T> {
 
   pub(crate) fn build_block(&mut self) -> Block<T> {
     let block = Block::new(
+      self.genesis,
       self.tip,
       self.provided.transactions.values().flatten().cloned().collect(),
       self.mempool.block(&self.next_nonces),
@@ -48,6 +48,12 @@ pub mod tests;
 pub const TRANSACTION_SIZE_LIMIT: usize = 50_000;
 /// Amount of transactions a single account may have in the mempool.
 pub const ACCOUNT_MEMPOOL_LIMIT: u32 = 50;
+/// Total weight of the transactions a single account may have in the mempool.
+pub const ACCOUNT_MEMPOOL_WEIGHT_LIMIT: u64 = 1_000_000;
+/// Total weight of the transactions a single account may have included in a block.
+// This bounds how much of every block, and accordingly every participant's disk, a single
+// malicious participant may consume.
+pub const ACCOUNT_BLOCK_WEIGHT_LIMIT: u64 = 100_000;
 /// Block size limit.
 // This targets a growth limit of roughly 5 GB a day, under load, in order to prevent a malicious
 // participant from flooding disks and causing out of space errors in order processes.
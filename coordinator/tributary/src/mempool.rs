@@ -4,7 +4,10 @@ use ciphersuite::{Ciphersuite, Ristretto};
 
 use serai_db::{DbTxn, Db};
 
-use crate::{ACCOUNT_MEMPOOL_LIMIT, Signed, TransactionKind, Transaction, verify_transaction};
+use crate::{
+  ACCOUNT_MEMPOOL_LIMIT, ACCOUNT_MEMPOOL_WEIGHT_LIMIT, Signed, TransactionKind, Transaction,
+  verify_transaction,
+};
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub(crate) struct Mempool<D: Db, T: Transaction> {
@@ -84,6 +87,14 @@ impl<D: Db, T: Transaction> Mempool<D, T> {
           return false;
         }
 
+        // Apply the same reasoning to the weight of this sender's transactions
+        if !internal &&
+          ((self.weight(signer, blockchain_next_nonce) + tx.weight()) >
+            ACCOUNT_MEMPOOL_WEIGHT_LIMIT)
+        {
+          return false;
+        }
+
         if verify_transaction(&tx, self.genesis, &mut self.next_nonces).is_err() {
           return false;
         }
@@ -109,6 +120,20 @@ impl<D: Db, T: Transaction> Mempool<D, T> {
     }
   }
 
+  // The weight of the non-stale transactions this signer has in the mempool
+  fn weight(&self, signer: &<Ristretto as Ciphersuite>::G, blockchain_next_nonce: u32) -> u64 {
+    self
+      .txs
+      .values()
+      .filter_map(|tx| match tx.kind() {
+        TransactionKind::Signed(Signed { signer: tx_signer, nonce, .. }) => {
+          ((tx_signer == signer) && (*nonce >= blockchain_next_nonce)).then(|| tx.weight())
+        }
+        _ => None,
+      })
+      .sum()
+  }
+
   // Returns None if the mempool doesn't have a nonce tracked.
   pub(crate) fn next_nonce(&self, signer: &<Ristretto as Ciphersuite>::G) -> Option<u32> {
     self.next_nonces.get(signer).cloned()
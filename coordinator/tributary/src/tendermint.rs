@@ -204,13 +204,28 @@ impl Weights for Validators {
   fn proposer(&self, block: BlockNumber, round: RoundNumber) -> Self::ValidatorId {
     let block = usize::try_from(block.0).unwrap();
     let round = usize::try_from(round.0).unwrap();
-    // If multiple rounds are used, a naive block + round would cause the same index to be chosen
-    // in quick succesion.
-    // Accordingly, if we use additional rounds, jump halfway around.
-    // While this is still game-able, it's not explicitly reusing indexes immediately after each
-    // other.
-    self.robin
-      [(block + (if round == 0 { 0 } else { round + (self.robin.len() / 2) })) % self.robin.len()]
+
+    let scheduled = self.robin[block % self.robin.len()];
+    if round == 0 {
+      return scheduled;
+    }
+
+    // Additional rounds occur when the scheduled proposer failed to produce a block in time,
+    // presumably due to being offline
+    // Since the robin has an entry per unit of weight, naively incrementing the index may choose
+    // the same validator again. Instead, walk the robin from halfway around, assigning each round
+    // the next distinct validator, so every other validator gets a chance before any repeats
+    let mut fallbacks = Vec::with_capacity(self.weights.len());
+    for i in 0 .. self.robin.len() {
+      let validator = self.robin[(block + (self.robin.len() / 2) + i) % self.robin.len()];
+      if (validator != scheduled) && (!fallbacks.contains(&validator)) {
+        fallbacks.push(validator);
+      }
+    }
+    if fallbacks.is_empty() {
+      return scheduled;
+    }
+    fallbacks[(round - 1) % fallbacks.len()]
   }
 }
 
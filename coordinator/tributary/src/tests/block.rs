@@ -1,14 +1,21 @@
 use std::{io, collections::HashMap};
 
+use zeroize::Zeroizing;
+use rand::{RngCore, rngs::OsRng};
+
 use blake2::{Digest, Blake2s256};
 
 use ciphersuite::{
-  group::{ff::Field, Group},
+  group::{ff::Field, Group, GroupEncoding},
   Ciphersuite, Ristretto,
 };
 use schnorr::SchnorrSignature;
 
-use crate::{ReadWrite, TransactionError, Signed, TransactionKind, Transaction, BlockError, Block};
+use crate::{
+  ACCOUNT_BLOCK_WEIGHT_LIMIT, ReadWrite, TransactionError, Signed, TransactionKind, Transaction,
+  BlockError, BlockHeader, Block, merkle,
+  tests::{SignedTransaction, signed_transaction},
+};
 
 // A transaction solely defined by its nonce and a distinguisher (to allow creating distinct TXs
 // sharing a nonce).
@@ -59,16 +66,29 @@ impl Transaction for NonceTransaction {
     Blake2s256::digest([self.0.to_le_bytes().as_ref(), &[self.1]].concat()).into()
   }
 
+  fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read(reader)
+  }
+
+  fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write(writer)
+  }
+
   fn verify(&self) -> Result<(), TransactionError> {
     Ok(())
   }
+
+  // Allow a single account to have four of these included per block
+  fn weight(&self) -> u64 {
+    ACCOUNT_BLOCK_WEIGHT_LIMIT / 4
+  }
 }
 
 #[test]
 fn empty_block() {
   const GENESIS: [u8; 32] = [0xff; 32];
   const LAST: [u8; 32] = [0x01; 32];
-  Block::<NonceTransaction>::new(LAST, vec![], vec![])
+  Block::<NonceTransaction>::new(GENESIS, LAST, vec![], vec![])
     .verify(GENESIS, LAST, HashMap::new(), HashMap::new())
     .unwrap();
 }
@@ -86,7 +106,7 @@ fn duplicate_nonces() {
     insert(NonceTransaction::new(0, 0));
     insert(NonceTransaction::new(i, 1));
 
-    let res = Block::new(LAST, vec![], mempool).verify(
+    let res = Block::new(GENESIS, LAST, vec![], mempool).verify(
       GENESIS,
       LAST,
       HashMap::new(),
@@ -99,3 +119,133 @@ fn duplicate_nonces() {
     }
   }
 }
+
+#[test]
+fn account_weight_limit() {
+  const GENESIS: [u8; 32] = [0xff; 32];
+  const LAST: [u8; 32] = [0x01; 32];
+
+  let mempool = (0 .. 6).map(|nonce| NonceTransaction::new(nonce, 0)).collect::<Vec<_>>();
+  let next_nonces = HashMap::from([(<Ristretto as Ciphersuite>::G::identity(), 0)]);
+
+  // Creating a block should only include as many transactions as the account is allowed
+  let block = Block::new(GENESIS, LAST, vec![], mempool.clone());
+  assert_eq!(block.transactions, mempool[.. 4]);
+  block.verify(GENESIS, LAST, HashMap::new(), next_nonces.clone()).unwrap();
+
+  // A block exceeding the limit should be rejected
+  let transactions = mempool[.. 5].to_vec();
+  let hashes = transactions.iter().map(Transaction::hash).collect::<Vec<_>>();
+  let block = Block {
+    header: BlockHeader { parent: LAST, transactions: merkle(&hashes) },
+    transactions,
+    signature: None,
+  };
+  assert_eq!(
+    block.verify(GENESIS, LAST, HashMap::new(), next_nonces),
+    Err(BlockError::TooHeavyAccount)
+  );
+}
+
+#[test]
+fn aggregate_signature() {
+  const LAST: [u8; 32] = [0x01; 32];
+
+  let mut genesis = [0; 32];
+  OsRng.fill_bytes(&mut genesis);
+
+  let mut next_nonces = HashMap::new();
+  let mut mempool = vec![];
+  for _ in 0 .. 3 {
+    let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+    let tx = signed_transaction(&mut OsRng, genesis, &key, 0);
+    next_nonces.insert(tx.1.signer, 0);
+    mempool.push(tx);
+  }
+  // Transactions sharing a nonce are ordered by signer
+  mempool.sort_by_key(|tx| tx.1.signer.to_bytes());
+
+  let block = Block::new(genesis, LAST, vec![], mempool.clone());
+  assert_eq!(block.transactions, mempool);
+  block.verify(genesis, LAST, HashMap::new(), next_nonces.clone()).unwrap();
+
+  // The block should serialize without each transaction's signature's s, carrying just the
+  // aggregate's
+  let serialized = block.serialize();
+  assert_eq!(
+    serialized.len(),
+    64 + 4 + mempool.iter().map(|tx| tx.serialize().len() - 32).sum::<usize>() + 32
+  );
+  let read = Block::<SignedTransaction>::read::<&[u8]>(&mut serialized.as_ref()).unwrap();
+  assert_eq!(read.header, block.header);
+  assert_eq!(read.signature, block.signature);
+  for (read, tx) in read.transactions.iter().zip(&mempool) {
+    assert_eq!(read.1.signature.s, <Ristretto as Ciphersuite>::F::ZERO);
+    assert_eq!(read.hash(), tx.hash());
+  }
+  read.verify(genesis, LAST, HashMap::new(), next_nonces.clone()).unwrap();
+
+  // An invalid aggregate should cause the block to be rejected
+  {
+    let mut block = block.clone();
+    *block.signature.as_mut().unwrap() += <Ristretto as Ciphersuite>::F::ONE;
+    assert_eq!(
+      block.verify(genesis, LAST, HashMap::new(), next_nonces.clone()),
+      Err(BlockError::TransactionError(TransactionError::InvalidSignature))
+    );
+
+    block.signature = None;
+    assert_eq!(
+      block.verify(genesis, LAST, HashMap::new(), next_nonces.clone()),
+      Err(BlockError::TransactionError(TransactionError::InvalidSignature))
+    );
+  }
+
+  // A single invalid signature should cause the entire block to be rejected
+  mempool[1].1.signature.s += <Ristretto as Ciphersuite>::F::ONE;
+  assert_eq!(
+    Block::new(genesis, LAST, vec![], mempool).verify(genesis, LAST, HashMap::new(), next_nonces),
+    Err(BlockError::TransactionError(TransactionError::InvalidSignature))
+  );
+}
+
+#[test]
+fn canonical_order() {
+  const LAST: [u8; 32] = [0x01; 32];
+
+  let mut genesis = [0; 32];
+  OsRng.fill_bytes(&mut genesis);
+
+  let mut next_nonces = HashMap::new();
+  let mut mempool = vec![];
+  for _ in 0 .. 3 {
+    let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+    next_nonces.insert(<Ristretto as Ciphersuite>::generator() * *key, 0);
+    for nonce in 0 .. 3 {
+      mempool.push(signed_transaction(&mut OsRng, genesis, &key, nonce));
+    }
+  }
+
+  // Regardless of the order the producer has the transactions in, the block should order them by
+  // nonce and then signer
+  let block = Block::new(genesis, LAST, vec![], mempool.clone());
+  let mut sorted = mempool.clone();
+  sorted.sort_by_key(|tx| (tx.1.nonce, tx.1.signer.to_bytes()));
+  assert_eq!(block.transactions, sorted);
+  block.verify(genesis, LAST, HashMap::new(), next_nonces.clone()).unwrap();
+
+  // A block whose transactions are validly nonced yet not in the canonical order should be
+  // rejected
+  let transactions = mempool;
+  assert_ne!(transactions, sorted);
+  let hashes = transactions.iter().map(Transaction::hash).collect::<Vec<_>>();
+  let block = Block {
+    header: BlockHeader { parent: LAST, transactions: merkle(&hashes) },
+    transactions,
+    signature: None,
+  };
+  assert_eq!(
+    block.verify(genesis, LAST, HashMap::new(), next_nonces),
+    Err(BlockError::NonCanonicalOrder)
+  );
+}
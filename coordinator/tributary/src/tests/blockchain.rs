@@ -76,7 +76,7 @@ fn invalid_block() {
   // Not a participant
   {
     // Manually create the block to bypass build_block's checks
-    let block = Block::new(blockchain.tip(), vec![], vec![tx.clone()]);
+    let block = Block::new(genesis, blockchain.tip(), vec![], vec![tx.clone()]);
     assert_eq!(block.header.transactions, merkle(&[tx.hash()]));
     assert!(blockchain.verify_block(&block).is_err());
   }
@@ -86,7 +86,7 @@ fn invalid_block() {
 
   // Re-run the not a participant block to make sure it now works
   {
-    let block = Block::new(blockchain.tip(), vec![], vec![tx.clone()]);
+    let block = Block::new(genesis, blockchain.tip(), vec![], vec![tx.clone()]);
     assert_eq!(block.header.transactions, merkle(&[tx.hash()]));
     blockchain.verify_block(&block).unwrap();
   }
@@ -108,7 +108,7 @@ fn invalid_block() {
     // Invalid nonce
     let tx = crate::tests::signed_transaction(&mut OsRng, genesis, &key, 5);
     // Manually create the block to bypass build_block's checks
-    let block = Block::new(blockchain.tip(), vec![], vec![tx]);
+    let block = Block::new(genesis, blockchain.tip(), vec![], vec![tx]);
     assert!(blockchain.verify_block(&block).is_err());
   }
 
@@ -118,12 +118,18 @@ fn invalid_block() {
     assert!(blockchain.add_transaction(true, tx));
     let mut block = blockchain.build_block();
     blockchain.verify_block(&block).unwrap();
-    block.transactions[0].1.signature.s += <Ristretto as Ciphersuite>::F::ONE;
+    block.transactions[0].1.signature.R += Ristretto::generator();
     assert!(blockchain.verify_block(&block).is_err());
 
     // Make sure this isn't because the merkle changed due to the transaction hash including the
     // signature (which it explicitly isn't allowed to anyways)
     assert_eq!(block.header.transactions, merkle(&[block.transactions[0].hash()]));
+
+    // The aggregate signature is what's actually verified
+    block.transactions[0].1.signature.R -= Ristretto::generator();
+    blockchain.verify_block(&block).unwrap();
+    *block.signature.as_mut().unwrap() += <Ristretto as Ciphersuite>::F::ONE;
+    assert!(blockchain.verify_block(&block).is_err());
   }
 }
 
@@ -147,7 +153,7 @@ fn signed_transaction() {
       assert_eq!(next_nonce + 1, blockchain.next_nonce(signer).unwrap());
     }
     let block = blockchain.build_block();
-    assert_eq!(block, Block::new(blockchain.tip(), vec![], mempool.clone()));
+    assert_eq!(block, Block::new(genesis, blockchain.tip(), vec![], mempool.clone()));
     assert_eq!(blockchain.tip(), tip);
     assert_eq!(block.header.parent, tip);
 
@@ -202,7 +208,7 @@ fn provided_transaction() {
     .is_empty());
 
   // Non-provided transactions should fail verification
-  let block = Block::new(blockchain.tip(), vec![tx.clone()], vec![]);
+  let block = Block::new(genesis, blockchain.tip(), vec![tx.clone()], vec![]);
   assert!(blockchain.verify_block(&block).is_err());
 
   // Provided transactions should pass verification
@@ -212,7 +218,7 @@ fn provided_transaction() {
   // add_block should work for verified blocks
   assert!(blockchain.add_block(&block, vec![]).is_ok());
 
-  let block = Block::new(blockchain.tip(), vec![tx], vec![]);
+  let block = Block::new(genesis, blockchain.tip(), vec![tx], vec![]);
   // The provided transaction should no longer considered provided, causing this error
   assert!(blockchain.verify_block(&block).is_err());
   // add_block should fail for unverified provided transactions if told to add them
@@ -10,3 +10,6 @@ mod block;
 mod blockchain;
 #[cfg(test)]
 mod mempool;
+
+#[cfg(test)]
+mod proposer;
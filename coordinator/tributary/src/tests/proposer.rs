@@ -0,0 +1,30 @@
+use std::collections::HashSet;
+
+use rand::rngs::OsRng;
+
+use ciphersuite::{group::Group, Ciphersuite, Ristretto};
+
+use tendermint::ext::{BlockNumber, RoundNumber, Weights};
+
+use crate::Validators;
+
+#[test]
+fn proposer_fallback() {
+  let validators = (0 .. 5)
+    .map(|i| (<Ristretto as Ciphersuite>::G::random(&mut OsRng), 1 + (i % 3)))
+    .collect::<Vec<_>>();
+  let validators = Validators::new([0xff; 32], validators).unwrap();
+
+  for block in 0 .. 10 {
+    let scheduled = validators.proposer(BlockNumber(block), RoundNumber(0));
+
+    // The rounds after the scheduled proposer's should cycle through every other validator
+    // before any repeat
+    let fallbacks = (1 ..= 4)
+      .map(|round| validators.proposer(BlockNumber(block), RoundNumber(round)))
+      .collect::<Vec<_>>();
+    assert!(!fallbacks.contains(&scheduled));
+    assert_eq!(fallbacks.iter().collect::<HashSet<_>>().len(), 4);
+    assert_eq!(validators.proposer(BlockNumber(block), RoundNumber(5)), fallbacks[0]);
+  }
+}
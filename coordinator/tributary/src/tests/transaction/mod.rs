@@ -54,6 +54,14 @@ impl Transaction for ProvidedTransaction {
     Blake2s256::digest(self.serialize()).into()
   }
 
+  fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read(reader)
+  }
+
+  fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write(writer)
+  }
+
   fn verify(&self) -> Result<(), TransactionError> {
     Ok(())
   }
@@ -68,19 +76,28 @@ pub fn random_provided_transaction<R: RngCore + CryptoRng>(rng: &mut R) -> Provi
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct SignedTransaction(pub Vec<u8>, pub Signed);
 
-impl ReadWrite for SignedTransaction {
-  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+impl SignedTransaction {
+  fn read_data<R: io::Read>(reader: &mut R) -> io::Result<Vec<u8>> {
     let mut len = [0; 4];
     reader.read_exact(&mut len)?;
     let mut data = vec![0; usize::try_from(u32::from_le_bytes(len)).unwrap()];
     reader.read_exact(&mut data)?;
+    Ok(data)
+  }
 
-    Ok(SignedTransaction(data, Signed::read(reader)?))
+  fn write_data<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&u32::try_from(self.0.len()).unwrap().to_le_bytes())?;
+    writer.write_all(&self.0)
+  }
+}
+
+impl ReadWrite for SignedTransaction {
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Ok(SignedTransaction(Self::read_data(reader)?, Signed::read(reader)?))
   }
 
   fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
-    writer.write_all(&u32::try_from(self.0.len()).unwrap().to_le_bytes())?;
-    writer.write_all(&self.0)?;
+    self.write_data(writer)?;
     self.1.write(writer)
   }
 }
@@ -95,6 +112,15 @@ impl Transaction for SignedTransaction {
     Blake2s256::digest(&serialized[.. (serialized.len() - 64)]).into()
   }
 
+  fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Ok(SignedTransaction(Self::read_data(reader)?, Signed::read_without_s(reader)?))
+  }
+
+  fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write_data(writer)?;
+    self.1.write_without_s(writer)
+  }
+
   fn verify(&self) -> Result<(), TransactionError> {
     Ok(())
   }
@@ -114,11 +140,10 @@ pub fn signed_transaction<R: RngCore + CryptoRng>(
   let mut tx =
     SignedTransaction(data, Signed { signer, nonce, signature: random_signed(rng).signature });
 
-  tx.1.signature = SchnorrSignature::sign(
-    key,
-    Zeroizing::new(<Ristretto as Ciphersuite>::F::random(rng)),
-    tx.sig_hash(genesis),
-  );
+  // The sig hash binds to the signature's nonce, so it must be set first
+  let signature_nonce = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(rng));
+  tx.1.signature.R = <Ristretto as Ciphersuite>::generator() * *signature_nonce;
+  tx.1.signature = SchnorrSignature::sign(key, signature_nonce, tx.sig_hash(genesis));
 
   let mut nonces = HashMap::from([(signer, nonce)]);
   verify_transaction(&tx, genesis, &mut nonces).unwrap();
@@ -15,6 +15,14 @@ use crate::{
 fn serialize_signed() {
   let signed = random_signed(&mut rand::rngs::OsRng);
   assert_eq!(Signed::read::<&[u8]>(&mut signed.serialize().as_ref()).unwrap(), signed);
+
+  let mut without_s = vec![];
+  signed.write_without_s(&mut without_s).unwrap();
+  assert_eq!(without_s.len(), signed.serialize().len() - 32);
+  let mut read = Signed::read_without_s::<&[u8]>(&mut without_s.as_ref()).unwrap();
+  assert_eq!(read.signature.s, <Ristretto as Ciphersuite>::F::ZERO);
+  read.signature.s = signed.signature.s;
+  assert_eq!(read, signed);
 }
 
 #[test]
@@ -25,6 +33,12 @@ fn sig_hash() {
   let (_, tx2) = random_signed_transaction(&mut OsRng);
   assert!(tx1.hash() != tx2.hash());
   assert!(tx1.sig_hash(genesis) != tx2.sig_hash(genesis));
+
+  // The sig hash should bind to the signature's nonce
+  let mut tx3 = tx1.clone();
+  tx3.1.signature.R += Ristretto::generator();
+  assert_eq!(tx1.hash(), tx3.hash());
+  assert!(tx1.sig_hash(genesis) != tx3.sig_hash(genesis));
 }
 
 #[test]
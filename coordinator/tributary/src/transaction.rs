@@ -5,16 +5,25 @@ use thiserror::Error;
 
 use blake2::{Digest, Blake2b512};
 
-use ciphersuite::{group::GroupEncoding, Ciphersuite, Ristretto};
+use ciphersuite::{
+  group::{
+    ff::{Field, PrimeField},
+    GroupEncoding,
+  },
+  Ciphersuite, Ristretto,
+};
 use schnorr::SchnorrSignature;
 
-use crate::{TRANSACTION_SIZE_LIMIT, ReadWrite};
+use crate::{TRANSACTION_SIZE_LIMIT, ACCOUNT_BLOCK_WEIGHT_LIMIT, ReadWrite};
 
 #[derive(Clone, PartialEq, Eq, Debug, Error)]
 pub enum TransactionError {
   /// Transaction exceeded the size limit.
   #[error("transaction is too large")]
   TooLargeTransaction,
+  /// Transaction's weight exceeded what a single account may have included in a block.
+  #[error("transaction is too heavy")]
+  TooHeavyTransaction,
   /// Transaction's signer isn't a participant.
   #[error("invalid signer")]
   InvalidSigner,
@@ -37,8 +46,8 @@ pub struct Signed {
   pub signature: SchnorrSignature<Ristretto>,
 }
 
-impl ReadWrite for Signed {
-  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+impl Signed {
+  fn read_inner<R: io::Read>(reader: &mut R, with_s: bool) -> io::Result<Self> {
     let signer = Ristretto::read_G(reader)?;
 
     let mut nonce = [0; 4];
@@ -48,15 +57,41 @@ impl ReadWrite for Signed {
       Err(io::Error::new(io::ErrorKind::Other, "nonce exceeded limit"))?;
     }
 
-    let signature = SchnorrSignature::<Ristretto>::read(reader)?;
+    let signature = if with_s {
+      SchnorrSignature::<Ristretto>::read(reader)?
+    } else {
+      SchnorrSignature::<Ristretto> {
+        R: Ristretto::read_G(reader)?,
+        s: <Ristretto as Ciphersuite>::F::ZERO,
+      }
+    };
 
     Ok(Signed { signer, nonce, signature })
   }
 
-  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+  /// Read a Signed written by `write_without_s`.
+  ///
+  /// The signature's s will be zero.
+  pub fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read_inner(reader, false)
+  }
+
+  /// Write a Signed without its signature's s, as done for transactions within blocks.
+  pub fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
     writer.write_all(&self.signer.to_bytes())?;
     writer.write_all(&self.nonce.to_le_bytes())?;
-    self.signature.write(writer)
+    writer.write_all(&self.signature.R.to_bytes())
+  }
+}
+
+impl ReadWrite for Signed {
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Self::read_inner(reader, true)
+  }
+
+  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    self.write_without_s(writer)?;
+    writer.write_all(&self.signature.s.to_repr())
   }
 }
 
@@ -92,15 +127,43 @@ pub trait Transaction: 'static + Send + Sync + Clone + Eq + Debug + ReadWrite {
   /// The hash must NOT commit to the signature.
   fn hash(&self) -> [u8; 32];
 
+  /// Read a transaction written by `write_without_s`.
+  ///
+  /// If this transaction is signed, its signature's s will be zero.
+  fn read_without_s<R: io::Read>(reader: &mut R) -> io::Result<Self>;
+
+  /// Write this transaction without its signature's s, if it has a signature.
+  ///
+  /// Blocks carry a single aggregate signature for all of their transactions, making the s of
+  /// each individual signature redundant.
+  fn write_without_s<W: io::Write>(&self, writer: &mut W) -> io::Result<()>;
+
   /// Perform transaction-specific verification.
   fn verify(&self) -> Result<(), TransactionError>;
 
+  /// Return the weight of this transaction, used to rate limit the accounts signing transactions.
+  ///
+  /// Defaults to the transaction's serialized length. Transactions which are more expensive to
+  /// handle than their length implies should override this.
+  fn weight(&self) -> u64 {
+    u64::try_from(self.serialize().len()).unwrap()
+  }
+
   /// Obtain the challenge for this transaction's signature.
   ///
+  /// This binds to the signature's nonce, as required for signatures to be aggregated, so the
+  /// nonce must be set before this is called. This panics if the transaction isn't signed.
+  ///
   /// Do not override this unless you know what you're doing.
   fn sig_hash(&self, genesis: [u8; 32]) -> <Ristretto as Ciphersuite>::F {
+    let TransactionKind::Signed(Signed { signature, .. }) = self.kind() else {
+      panic!("obtaining the sig hash of a transaction which isn't signed");
+    };
     <Ristretto as Ciphersuite>::F::from_bytes_mod_order_wide(
-      &Blake2b512::digest([genesis, self.hash()].concat()).into(),
+      &Blake2b512::digest(
+        [genesis.as_ref(), &self.hash(), signature.R.to_bytes().as_ref()].concat(),
+      )
+      .into(),
     )
   }
 }
@@ -110,10 +173,33 @@ pub(crate) fn verify_transaction<T: Transaction>(
   tx: &T,
   genesis: [u8; 32],
   next_nonces: &mut HashMap<<Ristretto as Ciphersuite>::G, u32>,
+) -> Result<(), TransactionError> {
+  verify_transaction_with(tx, genesis, next_nonces, |signer, signature, challenge| {
+    signature.verify(signer, challenge)
+  })
+}
+
+// Verify a transaction, passing its signature (if it has one) to the passed closure
+//
+// If the closure doesn't actually verify the signature, the caller is responsible for doing so,
+// and discarding mutations to next_nonces if it's invalid
+pub(crate) fn verify_transaction_with<T: Transaction>(
+  tx: &T,
+  genesis: [u8; 32],
+  next_nonces: &mut HashMap<<Ristretto as Ciphersuite>::G, u32>,
+  verify_signature: impl FnOnce(
+    <Ristretto as Ciphersuite>::G,
+    &SchnorrSignature<Ristretto>,
+    <Ristretto as Ciphersuite>::F,
+  ) -> bool,
 ) -> Result<(), TransactionError> {
   if tx.serialize().len() > TRANSACTION_SIZE_LIMIT {
     Err(TransactionError::TooLargeTransaction)?;
   }
+  // A transaction heavier than an account's per-block allowance could never be included
+  if tx.weight() > ACCOUNT_BLOCK_WEIGHT_LIMIT {
+    Err(TransactionError::TooHeavyTransaction)?;
+  }
 
   tx.verify()?;
 
@@ -130,8 +216,7 @@ pub(crate) fn verify_transaction<T: Transaction>(
         Err(TransactionError::InvalidSigner)?;
       }
 
-      // TODO: Use Schnorr half-aggregation and a batch verification here
-      if !signature.verify(*signer, tx.sig_hash(genesis)) {
+      if !verify_signature(*signer, signature, tx.sig_hash(genesis)) {
         Err(TransactionError::InvalidSignature)?;
       }
 
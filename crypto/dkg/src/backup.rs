@@ -0,0 +1,319 @@
+use core::{ops::Deref, fmt};
+use std::{
+  io::{self, Read, Write},
+  collections::HashMap,
+};
+
+use rand_core::{RngCore, CryptoRng};
+
+use zeroize::{Zeroize, Zeroizing};
+
+use ciphersuite::{
+  group::{
+    ff::{Field, PrimeField},
+    Group, GroupEncoding,
+  },
+  Ciphersuite,
+};
+use multiexp::multiexp_vartime;
+
+use crate::{
+  Participant, DkgError, ThresholdParams, ThresholdCore, lagrange,
+  encryption::{EncryptedMessage, encrypt},
+  frost::{SecretShare, polynomial, share_verification_statements},
+};
+
+fn read_u16<R: Read>(reader: &mut R) -> io::Result<u16> {
+  let mut value = [0; 2];
+  reader.read_exact(&mut value)?;
+  Ok(u16::from_le_bytes(value))
+}
+
+fn read_params<R: Read>(reader: &mut R) -> io::Result<ThresholdParams> {
+  let (t, n, i) = (read_u16(reader)?, read_u16(reader)?, read_u16(reader)?);
+  Participant::new(i)
+    .and_then(|i| ThresholdParams::new(t, n, i).ok())
+    .ok_or(io::Error::new(io::ErrorKind::Other, "invalid parameters"))
+}
+
+fn write_params<W: Write>(writer: &mut W, params: ThresholdParams) -> io::Result<()> {
+  writer.write_all(&params.t.to_le_bytes())?;
+  writer.write_all(&params.n.to_le_bytes())?;
+  writer.write_all(&params.i.to_bytes())
+}
+
+// The public portion of the keys being backed up, and the commitments to the polynomial their
+// secret share was split with
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Backup<C: Ciphersuite> {
+  keys: ThresholdParams,
+  verification_shares: Vec<C::G>,
+  commitments: Vec<C::G>,
+}
+
+impl<C: Ciphersuite> Backup<C> {
+  fn read<R: Read>(reader: &mut R, m: u16) -> io::Result<Self> {
+    let keys = read_params(reader)?;
+    let verification_shares = (0 .. keys.n).map(|_| C::read_G(reader)).collect::<io::Result<_>>()?;
+    let commitments = (0 .. m).map(|_| C::read_G(reader)).collect::<io::Result<_>>()?;
+    Ok(Backup { keys, verification_shares, commitments })
+  }
+
+  fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    write_params(writer, self.keys)?;
+    for share in &self.verification_shares {
+      writer.write_all(share.to_bytes().as_ref())?;
+    }
+    for commitment in &self.commitments {
+      writer.write_all(commitment.to_bytes().as_ref())?;
+    }
+    Ok(())
+  }
+
+  // Verify a custodian's share against this backup's commitments
+  fn verify(&self, custodian: Participant, share: &Zeroizing<C::F>) -> bool {
+    multiexp_vartime(&share_verification_statements::<C>(
+      custodian,
+      &self.commitments,
+      share.clone(),
+    ))
+    .is_identity()
+    .into()
+  }
+}
+
+/// A piece of a backup of a participant's keys, encrypted to a custodian.
+///
+/// Every piece carries the public portion of the keys and the commitments the secret share was
+/// split with, so each custodian only needs to store their own piece.
+#[derive(Clone)]
+pub struct BackupPiece<C: Ciphersuite> {
+  // The backup's parameters, where i is the custodian this piece is for
+  params: ThresholdParams,
+  backup: Backup<C>,
+  share: EncryptedMessage<C, SecretShare<C::F>>,
+}
+
+impl<C: Ciphersuite> fmt::Debug for BackupPiece<C> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("BackupPiece")
+      .field("params", &self.params)
+      .field("backup", &self.backup)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<C: Ciphersuite> BackupPiece<C> {
+  /// The custodian this piece is encrypted to.
+  pub fn custodian(&self) -> Participant {
+    self.params.i
+  }
+
+  /// Decrypt this piece with the custodian's private key, verifying it against the backup's
+  /// commitments.
+  ///
+  /// The context must be the one the backup was created with.
+  pub fn open(self, context: &str, key: &Zeroizing<C::F>) -> Result<OpenedPiece<C>, DkgError<()>> {
+    let custodian = self.custodian();
+    let invalid = DkgError::InvalidBackupPiece(custodian);
+
+    let share = self
+      .share
+      .decrypt_with_key(context, self.backup.keys.i, custodian, key)
+      .ok_or(invalid.clone())?;
+    let mut repr = share.0;
+    let share = Option::<C::F>::from(C::F::from_repr(repr)).map(Zeroizing::new);
+    repr.as_mut().zeroize();
+    let share = share.ok_or(invalid.clone())?;
+
+    if !self.backup.verify(custodian, &share) {
+      Err(invalid)?;
+    }
+    Ok(OpenedPiece { params: self.params, backup: self.backup, share })
+  }
+
+  /// Write a BackupPiece to something implementing std::io::Write.
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    write_params(writer, self.params)?;
+    self.backup.write(writer)?;
+    self.share.write(writer)
+  }
+
+  /// Read a BackupPiece from something implementing std::io::Read.
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+    let params = read_params(reader)?;
+    let backup = Backup::read(reader, params.t)?;
+    let share = EncryptedMessage::read(reader, params)?;
+    Ok(BackupPiece { params, backup, share })
+  }
+
+  /// Serialize a BackupPiece to a `Vec<u8>`.
+  pub fn serialize(&self) -> Vec<u8> {
+    let mut buf = vec![];
+    self.write(&mut buf).unwrap();
+    buf
+  }
+}
+
+/// A decrypted piece of a backup, to be combined with other pieces in order to recover the
+/// backed up keys.
+///
+/// This contains a custodian's share of a secret share and must be kept as confidential as the
+/// keys themselves.
+#[derive(Clone)]
+pub struct OpenedPiece<C: Ciphersuite> {
+  params: ThresholdParams,
+  backup: Backup<C>,
+  share: Zeroizing<C::F>,
+}
+
+impl<C: Ciphersuite> fmt::Debug for OpenedPiece<C> {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt
+      .debug_struct("OpenedPiece")
+      .field("params", &self.params)
+      .field("backup", &self.backup)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<C: Ciphersuite> OpenedPiece<C> {
+  /// The custodian this piece was opened by.
+  pub fn custodian(&self) -> Participant {
+    self.params.i
+  }
+
+  /// Write an OpenedPiece to something implementing std::io::Write.
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    write_params(writer, self.params)?;
+    self.backup.write(writer)?;
+    let mut share = self.share.to_repr();
+    writer.write_all(share.as_ref())?;
+    share.as_mut().zeroize();
+    Ok(())
+  }
+
+  /// Read an OpenedPiece from something implementing std::io::Read.
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+    let params = read_params(reader)?;
+    let backup = Backup::read(reader, params.t)?;
+    let share = Zeroizing::new(C::read_F(reader)?);
+    Ok(OpenedPiece { params, backup, share })
+  }
+
+  /// Serialize an OpenedPiece to a `Vec<u8>`.
+  pub fn serialize(&self) -> Zeroizing<Vec<u8>> {
+    let mut serialized = Zeroizing::new(vec![]);
+    self.write::<Vec<u8>>(serialized.as_mut()).unwrap();
+    serialized
+  }
+}
+
+/// Back up a participant's keys by splitting their secret share into a m-of-k sharing, where the
+/// k custodians are specified by their keys.
+///
+/// The group's private key is never reconstructed, solely this participant's secret share. Each
+/// returned piece is encrypted to the custodian at the same position within `custodians`. The
+/// context should be unique to these keys, as it must be provided again to open the pieces.
+pub fn backup<R: RngCore + CryptoRng, C: Ciphersuite>(
+  rng: &mut R,
+  context: &str,
+  keys: &ThresholdCore<C>,
+  m: u16,
+  custodians: &[C::G],
+) -> Result<Vec<BackupPiece<C>>, DkgError<()>> {
+  let k = u16::try_from(custodians.len())
+    .map_err(|_| DkgError::InvalidParticipantQuantity(u16::MAX.into(), custodians.len()))?;
+  // Validate the parameters before doing any work
+  ThresholdParams::new(m, k, Participant(1))?;
+
+  let mut coefficients = Vec::with_capacity(usize::from(m));
+  coefficients.push(keys.secret_share().clone());
+  for _ in 1 .. m {
+    coefficients.push(Zeroizing::new(C::random_nonzero_F(&mut *rng)));
+  }
+
+  let verification_shares = keys.verification_shares();
+  let backup = Backup {
+    keys: keys.params(),
+    verification_shares: (1 ..= keys.params().n)
+      .map(|l| verification_shares[&Participant(l)])
+      .collect(),
+    commitments: coefficients
+      .iter()
+      .map(|coefficient| C::generator() * coefficient.deref())
+      .collect(),
+  };
+
+  let mut pieces = Vec::with_capacity(custodians.len());
+  for (custodian, key) in (1 ..= k).map(Participant).zip(custodians) {
+    let mut share = polynomial(&coefficients, custodian);
+    let share_bytes = Zeroizing::new(SecretShare::<C::F>(share.to_repr()));
+    share.zeroize();
+
+    pieces.push(BackupPiece {
+      params: ThresholdParams::new(m, k, custodian)?,
+      backup: backup.clone(),
+      share: encrypt(&mut *rng, context, keys.params().i, custodian, *key, share_bytes),
+    });
+  }
+
+  for mut coefficient in coefficients {
+    coefficient.zeroize();
+  }
+
+  Ok(pieces)
+}
+
+/// Recover backed up keys from the opened pieces of at least m custodians.
+pub fn recover<C: Ciphersuite>(
+  pieces: &[OpenedPiece<C>],
+) -> Result<ThresholdCore<C>, DkgError<()>> {
+  let first = pieces.first().ok_or(DkgError::InvalidParticipantQuantity(1, 0))?;
+  let m = first.params.t;
+  if pieces.len() < usize::from(m) {
+    Err(DkgError::InvalidParticipantQuantity(m.into(), pieces.len()))?;
+  }
+
+  let mut included = Vec::with_capacity(pieces.len());
+  for piece in pieces {
+    let custodian = piece.custodian();
+    if included.contains(&custodian) {
+      Err(DkgError::DuplicatedParticipant(custodian))?;
+    }
+    // Every piece must be of the same backup, and individually valid
+    if (piece.params.t != m) ||
+      (piece.params.n != first.params.n) ||
+      (piece.backup != first.backup) ||
+      (!piece.backup.verify(custodian, &piece.share))
+    {
+      Err(DkgError::InvalidBackupPiece(custodian))?;
+    }
+    included.push(custodian);
+  }
+
+  let mut secret_share = Zeroizing::new(C::F::ZERO);
+  for piece in pieces {
+    *secret_share += lagrange::<C::F>(piece.custodian(), &included) * piece.share.deref();
+  }
+
+  // The constant term of the backup's polynomial is the secret share, so this should always pass
+  // for valid pieces. Checking it also binds the backup to the keys it claims to be of.
+  let backup = &first.backup;
+  let i = backup.keys.i;
+  let verification_share = backup.verification_shares[usize::from(u16::from(i)) - 1];
+  if (backup.commitments[0] != verification_share) ||
+    ((C::generator() * secret_share.deref()) != verification_share)
+  {
+    Err(DkgError::InvalidBackupPiece(first.custodian()))?;
+  }
+
+  let verification_shares = backup
+    .verification_shares
+    .iter()
+    .enumerate()
+    .map(|(l, share)| (Participant(u16::try_from(l + 1).unwrap()), *share))
+    .collect::<HashMap<_, _>>();
+  Ok(ThresholdCore::new(backup.keys, secret_share, verification_shares))
+}
@@ -132,11 +132,12 @@ fn cipher<C: Ciphersuite>(context: &str, ecdh: &Zeroizing<C::G>) -> ChaCha20 {
   res
 }
 
-fn encrypt<R: RngCore + CryptoRng, C: Ciphersuite, E: Encryptable>(
+pub(crate) fn encrypt<R: RngCore + CryptoRng, C: Ciphersuite, E: Encryptable>(
   rng: &mut R,
   context: &str,
   from: Participant,
-  to: C::G,
+  to: Participant,
+  to_key: C::G,
   mut msg: Zeroizing<E>,
 ) -> EncryptedMessage<C, E> {
   /*
@@ -151,7 +152,7 @@ fn encrypt<R: RngCore + CryptoRng, C: Ciphersuite, E: Encryptable>(
   // Generate a new key for this message, satisfying cipher's requirement of distinct keys per
   // message, and enabling revealing this message without revealing any others
   let key = Zeroizing::new(C::random_nonzero_F(rng));
-  cipher::<C>(context, &ecdh::<C>(&key, to)).apply_keystream(msg.as_mut().as_mut());
+  cipher::<C>(context, &ecdh::<C>(&key, to_key)).apply_keystream(msg.as_mut().as_mut());
 
   let pub_key = C::generator() * key.deref();
   let nonce = Zeroizing::new(C::random_nonzero_F(rng));
@@ -161,7 +162,7 @@ fn encrypt<R: RngCore + CryptoRng, C: Ciphersuite, E: Encryptable>(
     pop: SchnorrSignature::sign(
       &key,
       nonce,
-      pop_challenge::<C>(context, pub_nonce, pub_key, from, msg.deref().as_ref()),
+      pop_challenge::<C>(context, pub_nonce, pub_key, from, to, msg.deref().as_ref()),
     ),
     msg,
   }
@@ -188,6 +189,26 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     buf
   }
 
+  // Decrypt a message with a key held outside of any Encryption, such as a backup custodian's
+  // Returns None if the proof of possession is invalid
+  pub(crate) fn decrypt_with_key(
+    mut self,
+    context: &str,
+    from: Participant,
+    to: Participant,
+    key: &Zeroizing<C::F>,
+  ) -> Option<Zeroizing<E>> {
+    if !self.pop.verify(
+      self.key,
+      pop_challenge::<C>(context, self.pop.R, self.key, from, to, self.msg.deref().as_ref()),
+    ) {
+      None?;
+    }
+
+    cipher::<C>(context, &ecdh::<C>(key, self.key)).apply_keystream(self.msg.as_mut().as_mut());
+    Some(self.msg)
+  }
+
   #[cfg(test)]
   pub(crate) fn invalidate_pop(&mut self) {
     self.pop.s += C::F::ONE;
@@ -199,6 +220,7 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     rng: &mut R,
     context: &str,
     from: Participant,
+    to: Participant,
   ) {
     // Invalidate the message by specifying a new key/Schnorr PoP
     // This will cause all initial checks to pass, yet a decrypt to gibberish
@@ -210,7 +232,7 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     self.pop = SchnorrSignature::sign(
       &key,
       nonce,
-      pop_challenge::<C>(context, pub_nonce, pub_key, from, self.msg.deref().as_ref()),
+      pop_challenge::<C>(context, pub_nonce, pub_key, from, to, self.msg.deref().as_ref()),
     );
   }
 
@@ -221,7 +243,8 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     rng: &mut R,
     context: &str,
     from: Participant,
-    to: C::G,
+    to: Participant,
+    to_key: C::G,
   ) {
     use ciphersuite::group::ff::PrimeField;
 
@@ -235,7 +258,7 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     assert!(!bool::from(C::F::from_repr(repr).is_some()));
 
     self.msg.as_mut().as_mut().copy_from_slice(repr.as_ref());
-    *self = encrypt(rng, context, from, to, self.msg.clone());
+    *self = encrypt(rng, context, from, to, to_key, self.msg.clone());
   }
 
   // Assumes the encrypted message is a secret share.
@@ -245,14 +268,15 @@ impl<C: Ciphersuite, E: Encryptable> EncryptedMessage<C, E> {
     rng: &mut R,
     context: &str,
     from: Participant,
-    to: C::G,
+    to: Participant,
+    to_key: C::G,
   ) {
     use ciphersuite::group::ff::PrimeField;
 
     // Assumes the share isn't randomly 1
     let repr = C::F::ONE.to_repr();
     self.msg.as_mut().as_mut().copy_from_slice(repr.as_ref());
-    *self = encrypt(rng, context, from, to, self.msg.clone());
+    *self = encrypt(rng, context, from, to, to_key, self.msg.clone());
   }
 }
 
@@ -304,9 +328,10 @@ fn pop_challenge<C: Ciphersuite>(
   nonce: C::G,
   key: C::G,
   sender: Participant,
+  recipient: Participant,
   msg: &[u8],
 ) -> C::F {
-  let mut transcript = RecommendedTranscript::new(b"DKG Encryption Key Proof of Possession v0.2");
+  let mut transcript = RecommendedTranscript::new(b"DKG Encryption Key Proof of Possession v0.3");
   transcript.append_message(b"context", context.as_bytes());
 
   transcript.domain_separate(b"proof_of_possession");
@@ -315,6 +340,8 @@ fn pop_challenge<C: Ciphersuite>(
   transcript.append_message(b"key", key.to_bytes());
   // This is sufficient to prevent the attack this is meant to stop
   transcript.append_message(b"sender", sender.to_bytes());
+  // Binding the recipient lets a recipient identify a message which was addressed to someone else
+  transcript.append_message(b"recipient", recipient.to_bytes());
   // This, as written above, doesn't hurt
   transcript.append_message(b"message", msg);
   // While this is a PoK and a PoP, it's called a PoP here since the important part is its owner
@@ -403,7 +430,30 @@ impl<C: Ciphersuite> Encryption<C> {
     participant: Participant,
     msg: Zeroizing<E>,
   ) -> EncryptedMessage<C, E> {
-    encrypt(rng, &self.context, self.i, self.enc_keys[&participant], msg)
+    encrypt(rng, &self.context, self.i, participant, self.enc_keys[&participant], msg)
+  }
+
+  // Find who a message from the specified sender was addressed to, if it was well-formed for any
+  // participant.
+  // This is solely intended to explain a message whose proof of possession failed to verify.
+  pub(crate) fn recipient<E: Encryptable>(
+    &self,
+    from: Participant,
+    msg: &EncryptedMessage<C, E>,
+  ) -> Option<Participant> {
+    core::iter::once(self.i).chain(self.enc_keys.keys().copied()).find(|recipient| {
+      msg.pop.verify(
+        msg.key,
+        pop_challenge::<C>(
+          &self.context,
+          msg.pop.R,
+          msg.key,
+          from,
+          *recipient,
+          msg.msg.deref().as_ref(),
+        ),
+      )
+    })
   }
 
   pub(crate) fn decrypt<R: RngCore + CryptoRng, I: Copy + Zeroize, E: Encryptable>(
@@ -414,20 +464,21 @@ impl<C: Ciphersuite> Encryption<C> {
     // which failed, and therefore to use None for the blame
     batch_id: I,
     from: Participant,
-    mut msg: EncryptedMessage<C, E>,
+    msg: &EncryptedMessage<C, E>,
   ) -> (Zeroizing<E>, EncryptionKeyProof<C>) {
     msg.pop.batch_verify(
       rng,
       batch,
       batch_id,
       msg.key,
-      pop_challenge::<C>(&self.context, msg.pop.R, msg.key, from, msg.msg.deref().as_ref()),
+      pop_challenge::<C>(&self.context, msg.pop.R, msg.key, from, self.i, msg.msg.deref().as_ref()),
     );
 
     let key = ecdh::<C>(&self.enc_key, msg.key);
-    cipher::<C>(&self.context, &key).apply_keystream(msg.msg.as_mut().as_mut());
+    let mut decrypted = msg.msg.clone();
+    cipher::<C>(&self.context, &key).apply_keystream(decrypted.as_mut().as_mut());
     (
-      msg.msg,
+      decrypted,
       EncryptionKeyProof {
         key,
         dleq: DLEqProof::prove(
@@ -452,7 +503,14 @@ impl<C: Ciphersuite> Encryption<C> {
   ) -> Result<Zeroizing<E>, DecryptionError> {
     if !msg.pop.verify(
       msg.key,
-      pop_challenge::<C>(&self.context, msg.pop.R, msg.key, from, msg.msg.deref().as_ref()),
+      pop_challenge::<C>(
+        &self.context,
+        msg.pop.R,
+        msg.key,
+        from,
+        decryptor,
+        msg.msg.deref().as_ref(),
+      ),
     ) {
       Err(DecryptionError::InvalidSignature)?;
     }
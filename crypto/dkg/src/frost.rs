@@ -150,7 +150,7 @@ impl<C: Ciphersuite> KeyGenMachine<C> {
   }
 }
 
-fn polynomial<F: PrimeField + Zeroize>(
+pub(crate) fn polynomial<F: PrimeField + Zeroize>(
   coefficients: &[Zeroizing<F>],
   l: Participant,
 ) -> Zeroizing<F> {
@@ -177,7 +177,7 @@ fn polynomial<F: PrimeField + Zeroize>(
 // The encryption system also explicitly uses Zeroizing<M> so it can ensure anything being
 // encrypted is within Zeroizing. Accordingly, internally having Zeroizing would be redundant.
 #[derive(Clone, PartialEq, Eq)]
-pub struct SecretShare<F: PrimeField>(F::Repr);
+pub struct SecretShare<F: PrimeField>(pub(crate) F::Repr);
 impl<F: PrimeField> AsRef<[u8]> for SecretShare<F> {
   fn as_ref(&self) -> &[u8] {
     self.0.as_ref()
@@ -373,7 +373,7 @@ fn exponential<C: Ciphersuite>(i: Participant, values: &[C::G]) -> Vec<(C::F, C:
   res
 }
 
-fn share_verification_statements<C: Ciphersuite>(
+pub(crate) fn share_verification_statements<C: Ciphersuite>(
   target: Participant,
   commitments: &[C::G],
   mut share: Zeroizing<C::F>,
@@ -409,7 +409,7 @@ impl<C: Ciphersuite> KeyMachine<C> {
   pub fn calculate_share<R: RngCore + CryptoRng>(
     mut self,
     rng: &mut R,
-    mut shares: HashMap<Participant, EncryptedMessage<C, SecretShare<C::F>>>,
+    shares: HashMap<Participant, EncryptedMessage<C, SecretShare<C::F>>>,
   ) -> Result<BlameMachine<C>, FrostError<C>> {
     validate_map(
       &shares,
@@ -419,12 +419,19 @@ impl<C: Ciphersuite> KeyMachine<C> {
 
     let mut batch = BatchVerifier::new(shares.len());
     let mut blames = HashMap::new();
-    for (l, share_bytes) in shares.drain() {
+    for (l, share_bytes) in &shares {
+      let l = *l;
       let (mut share_bytes, blame) =
         self.encryption.decrypt(rng, &mut batch, BatchId::Decryption(l), l, share_bytes);
       let share =
         Zeroizing::new(Option::<C::F>::from(C::F::from_repr(share_bytes.0)).ok_or_else(|| {
-          FrostError::InvalidShare { participant: l, blame: Some(blame.clone()) }
+          // A share addressed to someone else will decrypt to garbage
+          match self.encryption.recipient(l, &shares[&l]) {
+            Some(recipient) if recipient != self.params.i() => {
+              FrostError::MisdirectedShare { participant: l, recipient }
+            }
+            _ => FrostError::InvalidShare { participant: l, blame: Some(blame.clone()) },
+          }
         })?);
       share_bytes.zeroize();
       *self.secret += share.deref();
@@ -438,7 +445,12 @@ impl<C: Ciphersuite> KeyMachine<C> {
     }
     batch.verify_with_vartime_blame().map_err(|id| {
       let (l, blame) = match id {
-        BatchId::Decryption(l) => (l, None),
+        BatchId::Decryption(l) => match self.encryption.recipient(l, &shares[&l]) {
+          Some(recipient) if recipient != self.params.i() => {
+            return FrostError::MisdirectedShare { participant: l, recipient };
+          }
+          _ => (l, None),
+        },
         BatchId::Share(l) => (l, Some(blames.remove(&l).unwrap())),
       };
       FrostError::InvalidShare { participant: l, blame }
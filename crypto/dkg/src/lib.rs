@@ -29,6 +29,12 @@ pub mod frost;
 /// Promote keys between ciphersuites.
 pub mod promote;
 
+/// Backups of a participant's keys, split among custodians.
+pub mod backup;
+
+/// Ordered sets of participants, mapping keys to participant indexes.
+pub mod participants;
+
 /// Tests for application-provided curves and algorithms.
 #[cfg(any(test, feature = "tests"))]
 pub mod tests;
@@ -98,6 +104,12 @@ pub enum DkgError<B: Clone + PartialEq + Eq + Debug> {
   /// An invalid DKG share was provided.
   #[error("invalid share (participant {participant}, blame {blame})")]
   InvalidShare { participant: Participant, blame: Option<B> },
+  /// A DKG share was validly encrypted, yet addressed to a different participant.
+  #[error("misdirected share (participant {participant}, addressed to {recipient})")]
+  MisdirectedShare { participant: Participant, recipient: Participant },
+  /// A backup piece failed to decrypt or didn't verify against its backup.
+  #[error("invalid backup piece (custodian {0})")]
+  InvalidBackupPiece(Participant),
 }
 
 // Validate a map of values to have the expected included participants
@@ -0,0 +1,108 @@
+use std::io::{self, Read, Write};
+
+use ciphersuite::{group::GroupEncoding, Ciphersuite};
+
+use crate::{Participant, DkgError};
+
+/// An ordered set of participants' keys, mapping each key to its participant index.
+///
+/// Indexes are assigned in order, starting from 1. All parties must construct the set with the
+/// same order of keys, or else their indexes will disagree. `ParticipantSet::sorted` provides a
+/// canonical order for when no order is otherwise defined.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParticipantSet<C: Ciphersuite>(Vec<C::G>);
+
+impl<C: Ciphersuite> ParticipantSet<C> {
+  /// Create a new set of participants, in the order provided.
+  pub fn new(keys: Vec<C::G>) -> Result<ParticipantSet<C>, DkgError<()>> {
+    if keys.is_empty() {
+      Err(DkgError::InvalidParticipantQuantity(1, 0))?;
+    }
+    if u16::try_from(keys.len()).is_err() {
+      Err(DkgError::InvalidParticipantQuantity(u16::MAX.into(), keys.len()))?;
+    }
+
+    for (i, key) in keys.iter().enumerate() {
+      if keys[.. i].contains(key) {
+        Err(DkgError::DuplicatedParticipant(Participant(u16::try_from(i + 1).unwrap())))?;
+      }
+    }
+
+    Ok(ParticipantSet(keys))
+  }
+
+  /// Create a new set of participants, canonically ordered by their keys' encodings.
+  pub fn sorted(mut keys: Vec<C::G>) -> Result<ParticipantSet<C>, DkgError<()>> {
+    keys.sort_by(|a, b| a.to_bytes().as_ref().cmp(b.to_bytes().as_ref()));
+    ParticipantSet::new(keys)
+  }
+
+  /// The amount of participants in this set.
+  pub fn n(&self) -> u16 {
+    u16::try_from(self.0.len()).unwrap()
+  }
+
+  /// The index of the participant with the specified key.
+  pub fn i(&self, key: C::G) -> Option<Participant> {
+    self
+      .0
+      .iter()
+      .position(|existing| *existing == key)
+      .map(|i| Participant(u16::try_from(i + 1).unwrap()))
+  }
+
+  /// The key of the specified participant.
+  pub fn key(&self, i: Participant) -> Option<C::G> {
+    self.0.get(usize::from(i.0).checked_sub(1)?).copied()
+  }
+
+  /// Iterate over every participant, alongside their key.
+  pub fn iter(&self) -> impl Iterator<Item = (Participant, C::G)> + '_ {
+    self.0.iter().enumerate().map(|(i, key)| (Participant(u16::try_from(i + 1).unwrap()), *key))
+  }
+
+  /// Deterministically select a signing set of `t` participants from those available.
+  ///
+  /// The participants with the lowest indexes are selected, so any parties with the same view of
+  /// who's available will select the same signing set. Returns None if less than `t` of the
+  /// available participants are in this set.
+  pub fn signing_set(&self, available: &[Participant], t: u16) -> Option<Vec<Participant>> {
+    let mut available =
+      available.iter().copied().filter(|i| (i.0 != 0) && (i.0 <= self.n())).collect::<Vec<_>>();
+    available.sort();
+    available.dedup();
+    if available.len() < usize::from(t) {
+      None?;
+    }
+    available.truncate(t.into());
+    Some(available)
+  }
+
+  /// Write the set of participants.
+  pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&self.n().to_le_bytes())?;
+    for key in &self.0 {
+      writer.write_all(key.to_bytes().as_ref())?;
+    }
+    Ok(())
+  }
+
+  /// Serialize the set of participants to a `Vec<u8>`.
+  pub fn serialize(&self) -> Vec<u8> {
+    let mut buf = vec![];
+    self.write(&mut buf).unwrap();
+    buf
+  }
+
+  /// Read a set of participants.
+  pub fn read<R: Read>(reader: &mut R) -> io::Result<ParticipantSet<C>> {
+    let mut n = [0; 2];
+    reader.read_exact(&mut n)?;
+    let mut keys = vec![];
+    for _ in 0 .. u16::from_le_bytes(n) {
+      keys.push(C::read_G(reader)?);
+    }
+    ParticipantSet::new(keys)
+      .map_err(|_| io::Error::new(io::ErrorKind::Other, "invalid participant set"))
+  }
+}
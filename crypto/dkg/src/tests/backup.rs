@@ -0,0 +1,71 @@
+use core::ops::Deref;
+
+use rand_core::{RngCore, CryptoRng};
+
+use zeroize::Zeroizing;
+
+use ciphersuite::Ciphersuite;
+
+use crate::{
+  Participant, DkgError,
+  backup::{BackupPiece, OpenedPiece, backup, recover},
+  tests::key_gen,
+};
+
+pub(crate) fn test_backup<R: RngCore + CryptoRng, C: Ciphersuite>(rng: &mut R) {
+  const CONTEXT: &str = "DKG Backup Test";
+
+  let keys = key_gen::<_, C>(&mut *rng);
+  let keys = &keys[&Participant::new(1).unwrap()];
+
+  let custodian_keys =
+    (0 .. 5).map(|_| Zeroizing::new(C::random_nonzero_F(&mut *rng))).collect::<Vec<_>>();
+  let custodians =
+    custodian_keys.iter().map(|key| C::generator() * key.deref()).collect::<Vec<_>>();
+
+  let pieces = backup(&mut *rng, CONTEXT, &keys.core, 3, &custodians).unwrap();
+  assert_eq!(pieces.len(), 5);
+
+  let mut opened = vec![];
+  for (piece, key) in pieces.iter().zip(&custodian_keys) {
+    let piece = BackupPiece::<C>::read::<&[u8]>(&mut piece.serialize().as_ref()).unwrap();
+    let piece = piece.open(CONTEXT, key).unwrap();
+    opened.push(OpenedPiece::<C>::read::<&[u8]>(&mut piece.serialize().as_ref()).unwrap());
+  }
+
+  // Any m pieces should recover the keys
+  for pieces in [&opened[.. 3], &opened[2 ..], &opened[..]] {
+    let recovered = recover(pieces).unwrap();
+    assert_eq!(recovered.params(), keys.params());
+    assert_eq!(recovered.secret_share(), keys.secret_share());
+    assert_eq!(recovered.group_key(), keys.group_key());
+    assert_eq!(recovered.verification_shares(), keys.verification_shares());
+  }
+
+  // Less than m pieces shouldn't
+  assert_eq!(recover(&opened[.. 2]).unwrap_err(), DkgError::InvalidParticipantQuantity(3, 2));
+  // Nor should duplicated pieces
+  assert_eq!(
+    recover(&[opened[0].clone(), opened[1].clone(), opened[0].clone()]).unwrap_err(),
+    DkgError::DuplicatedParticipant(Participant::new(1).unwrap())
+  );
+
+  // A piece shouldn't open with the wrong context or key
+  assert_eq!(
+    pieces[0].clone().open("Another Context", &custodian_keys[0]).unwrap_err(),
+    DkgError::InvalidBackupPiece(Participant::new(1).unwrap())
+  );
+  assert_eq!(
+    pieces[0].clone().open(CONTEXT, &custodian_keys[1]).unwrap_err(),
+    DkgError::InvalidBackupPiece(Participant::new(1).unwrap())
+  );
+
+  // Pieces from distinct backups shouldn't be combinable
+  let other = backup(&mut *rng, CONTEXT, &keys.core, 3, &custodians).unwrap();
+  let other = other.into_iter().zip(&custodian_keys).next().unwrap();
+  let other = other.0.open(CONTEXT, other.1).unwrap();
+  assert_eq!(
+    recover(&[opened[1].clone(), opened[2].clone(), other]).unwrap_err(),
+    DkgError::InvalidBackupPiece(Participant::new(1).unwrap())
+  );
+}
@@ -140,6 +140,31 @@ mod literal {
     }
   }
 
+  #[test]
+  fn invalid_proof_of_knowledge() {
+    let mut machines = HashMap::new();
+    let mut commitments = HashMap::new();
+    for i in (1 ..= PARTICIPANTS).map(Participant) {
+      let params = ThresholdParams::new(THRESHOLD, PARTICIPANTS, i).unwrap();
+      // Have 1 use a distinct context, which its proof of knowledge is bound to
+      let context = if i == ONE { "Distinct Context" } else { CONTEXT };
+      let machine = KeyGenMachine::<Ristretto>::new(params, context.to_string());
+      let (machine, these_commitments) = machine.generate_coefficients(&mut OsRng);
+      machines.insert(i, machine);
+      commitments.insert(i, these_commitments);
+    }
+
+    for (i, machine) in machines.drain() {
+      let res = machine.generate_secret_shares(&mut OsRng, clone_without(&commitments, &i));
+      if i == ONE {
+        // 1 will also consider everyone else's proofs invalid, and blame one of them
+        assert!(matches!(res.err(), Some(DkgError::InvalidProofOfKnowledge(_))));
+      } else {
+        assert_eq!(res.err(), Some(DkgError::InvalidProofOfKnowledge(ONE)));
+      }
+    }
+  }
+
   // TODO: Write a macro which expands to the following
   #[test]
   fn invalid_encryption_pop_blame() {
@@ -170,6 +195,29 @@ mod literal {
     test_blame(machines, secret_shares[&ONE][&TWO].clone(), blame.unwrap());
   }
 
+  #[test]
+  fn misdirected_share() {
+    let (mut machines, _, mut secret_shares) =
+      commit_enc_keys_and_shares::<_, Ristretto>(&mut OsRng);
+
+    // Have 1 send 2 the share it encrypted for 3
+    let three = Participant(3);
+    let misdirected = secret_shares[&ONE][&three].clone();
+    secret_shares.get_mut(&ONE).unwrap().insert(TWO, misdirected);
+
+    for (i, machine) in machines.drain() {
+      let machine = machine.calculate_share(&mut OsRng, generate_secret_shares(&secret_shares, i));
+      if i == TWO {
+        assert_eq!(
+          machine.err(),
+          Some(DkgError::MisdirectedShare { participant: ONE, recipient: three })
+        );
+      } else {
+        assert!(machine.is_ok());
+      }
+    }
+  }
+
   #[test]
   fn invalid_ecdh_blame() {
     let (mut machines, _, mut secret_shares) =
@@ -186,7 +234,7 @@ mod literal {
       .unwrap()
       .get_mut(&ONE)
       .unwrap()
-      .invalidate_msg(&mut OsRng, CONTEXT, TWO);
+      .invalidate_msg(&mut OsRng, CONTEXT, TWO, ONE);
 
     let mut blame = None;
     let machines = machines
@@ -221,7 +269,7 @@ mod literal {
       .unwrap()
       .get_mut(&ONE)
       .unwrap()
-      .invalidate_msg(&mut OsRng, CONTEXT, TWO);
+      .invalidate_msg(&mut OsRng, CONTEXT, TWO, ONE);
 
     let mut blame = None;
     let machines = machines
@@ -254,6 +302,7 @@ mod literal {
       &mut OsRng,
       CONTEXT,
       ONE,
+      TWO,
       enc_keys[&TWO],
     );
 
@@ -287,6 +336,7 @@ mod literal {
       &mut OsRng,
       CONTEXT,
       ONE,
+      TWO,
       enc_keys[&TWO],
     );
 
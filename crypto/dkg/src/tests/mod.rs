@@ -15,6 +15,22 @@ use frost::frost_gen;
 mod promote;
 use promote::test_generator_promotion;
 
+// Backup test.
+mod backup;
+use backup::test_backup;
+
+// Participant set test.
+mod participants;
+use participants::test_participant_set;
+
+// Offset test.
+mod offset;
+use offset::test_offset;
+
+// Randomized property tests.
+mod properties;
+use properties::test_properties;
+
 /// Constant amount of participants to use when testing.
 pub const PARTICIPANTS: u16 = 5;
 /// Constant threshold of participants to use when testing.
@@ -67,6 +83,10 @@ pub fn key_gen<R: RngCore + CryptoRng, C: Ciphersuite>(
 pub fn test_ciphersuite<R: RngCore + CryptoRng, C: Ciphersuite>(rng: &mut R) {
   key_gen::<_, C>(rng);
   test_generator_promotion::<_, C>(rng);
+  test_backup::<_, C>(rng);
+  test_participant_set::<_, C>(rng);
+  test_offset::<_, C>(rng);
+  test_properties::<_, C>(rng);
 }
 
 #[test]
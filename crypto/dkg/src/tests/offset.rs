@@ -0,0 +1,40 @@
+use core::ops::Deref;
+
+use rand_core::{RngCore, CryptoRng};
+
+use ciphersuite::{group::ff::Field, Ciphersuite};
+
+use crate::{
+  Participant,
+  tests::{THRESHOLD, key_gen},
+};
+
+/// Test offsets compose and that views of offset keys remain consistent.
+pub(crate) fn test_offset<R: RngCore + CryptoRng, C: Ciphersuite>(rng: &mut R) {
+  let keys = key_gen::<_, C>(&mut *rng);
+  let group_key = keys[&Participant(1)].group_key();
+
+  let first = C::F::random(&mut *rng);
+  let second = C::F::random(&mut *rng);
+  let included = (1 ..= THRESHOLD).map(Participant).collect::<Vec<_>>();
+
+  let mut secret = C::F::ZERO;
+  for i in &included {
+    let offset = keys[i].offset(first).offset(second);
+    // Offsets should accumulate
+    assert_eq!(offset.current_offset(), Some(first + second));
+    assert_eq!(offset.group_key(), group_key + (C::generator() * (first + second)));
+    // Verification shares are never offset
+    assert_eq!(offset.verification_shares(), keys[i].verification_shares());
+
+    let view = offset.view(included.clone()).unwrap();
+    assert_eq!(view.offset(), first + second);
+    assert_eq!(view.group_key(), offset.group_key());
+    // The view's shares should be consistent with each other
+    assert_eq!(C::generator() * view.secret_share().deref(), view.verification_share(*i));
+    secret += view.secret_share().deref();
+  }
+
+  // And should sum to the offset group key
+  assert_eq!(C::generator() * secret, group_key + (C::generator() * (first + second)));
+}
@@ -0,0 +1,52 @@
+use rand_core::{RngCore, CryptoRng};
+
+use ciphersuite::{
+  group::{ff::Field, GroupEncoding},
+  Ciphersuite,
+};
+
+use crate::{Participant, DkgError, participants::ParticipantSet};
+
+pub(crate) fn test_participant_set<R: RngCore + CryptoRng, C: Ciphersuite>(rng: &mut R) {
+  let keys = (0 .. 5).map(|_| C::generator() * C::F::random(&mut *rng)).collect::<Vec<_>>();
+
+  // Indexes should be assigned in order, starting from 1
+  let set = ParticipantSet::<C>::new(keys.clone()).unwrap();
+  assert_eq!(set.n(), 5);
+  for (i, key) in keys.iter().enumerate() {
+    let i = Participant::new(u16::try_from(i + 1).unwrap()).unwrap();
+    assert_eq!(set.i(*key), Some(i));
+    assert_eq!(set.key(i), Some(*key));
+  }
+  assert_eq!(set.i(C::generator()), None);
+  assert_eq!(set.key(Participant::new(6).unwrap()), None);
+  assert_eq!(set.iter().map(|(_, key)| key).collect::<Vec<_>>(), keys);
+
+  // Sorting should be independent of the order provided
+  let mut reversed = keys.clone();
+  reversed.reverse();
+  let sorted = ParticipantSet::<C>::sorted(keys.clone()).unwrap();
+  assert_eq!(sorted, ParticipantSet::<C>::sorted(reversed).unwrap());
+  let sorted_keys =
+    sorted.iter().map(|(_, key)| key.to_bytes().as_ref().to_vec()).collect::<Vec<_>>();
+  assert!(sorted_keys.windows(2).all(|keys| keys[0] < keys[1]));
+
+  // Duplicates should be rejected
+  let mut duplicated = keys.clone();
+  duplicated.push(keys[1]);
+  assert_eq!(
+    ParticipantSet::<C>::new(duplicated),
+    Err(DkgError::DuplicatedParticipant(Participant::new(6).unwrap()))
+  );
+  assert!(ParticipantSet::<C>::new(vec![]).is_err());
+
+  // The lowest available indexes should be selected
+  let participant = |i| Participant::new(i).unwrap();
+  assert_eq!(
+    set.signing_set(&[participant(4), participant(2), participant(5), participant(2)], 2),
+    Some(vec![participant(2), participant(4)])
+  );
+  assert_eq!(set.signing_set(&[participant(1), participant(6)], 2), None);
+
+  assert_eq!(ParticipantSet::<C>::read::<&[u8]>(&mut set.serialize().as_ref()).unwrap(), set);
+}
@@ -0,0 +1,128 @@
+use core::ops::Deref;
+use std::collections::HashMap;
+
+use zeroize::Zeroizing;
+use rand_core::{RngCore, CryptoRng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use ciphersuite::{group::ff::Field, Ciphersuite};
+
+use crate::{
+  Participant, DkgError, ThresholdParams, ThresholdCore, ThresholdKeys, lagrange,
+  frost::polynomial, tests::recover_key,
+};
+
+// How many random parameterizations to test
+const ITERATIONS: usize = 32;
+// The largest amount of participants to test with
+const MAX_PARTICIPANTS: u16 = 10;
+
+// Seed the RNG used for these tests, printing the seed so a failure can be reproduced by setting
+// DKG_PROPERTIES_SEED
+fn seeded_rng<R: RngCore>(rng: &mut R) -> ChaCha20Rng {
+  let seed = std::env::var("DKG_PROPERTIES_SEED")
+    .map(|seed| seed.parse().expect("DKG_PROPERTIES_SEED wasn't a u64"))
+    .unwrap_or_else(|_| rng.next_u64());
+  println!("testing DKG properties with seed {seed}");
+  ChaCha20Rng::seed_from_u64(seed)
+}
+
+// A value in the inclusive range [min, max]
+fn range<R: RngCore>(rng: &mut R, min: u16, max: u16) -> u16 {
+  min + u16::try_from(rng.next_u64() % (u64::from(max - min) + 1)).unwrap()
+}
+
+// A random subset of the participants, of the specified size
+fn subset<R: RngCore>(rng: &mut R, n: u16, size: u16) -> Vec<Participant> {
+  let mut participants = (1 ..= n).map(Participant).collect::<Vec<_>>();
+  // Fisher-Yates shuffle
+  for i in (1 .. participants.len()).rev() {
+    let j = usize::try_from(rng.next_u64() % u64::try_from(i + 1).unwrap()).unwrap();
+    participants.swap(i, j);
+  }
+  participants.truncate(usize::from(size));
+  participants
+}
+
+// Deal t-of-n keys for a random secret, returning the secret alongside the keys
+fn deal<R: RngCore + CryptoRng, C: Ciphersuite>(
+  rng: &mut R,
+  t: u16,
+  n: u16,
+) -> (C::F, HashMap<Participant, ThresholdKeys<C>>) {
+  let coefficients = (0 .. t).map(|_| Zeroizing::new(C::F::random(&mut *rng))).collect::<Vec<_>>();
+  let shares = (1 ..= n)
+    .map(|i| (Participant(i), polynomial(&coefficients, Participant(i))))
+    .collect::<HashMap<_, _>>();
+  let verification_shares =
+    shares.iter().map(|(i, share)| (*i, C::generator() * share.deref())).collect::<HashMap<_, _>>();
+
+  let keys = shares
+    .into_iter()
+    .map(|(i, share)| {
+      let params = ThresholdParams::new(t, n, i).unwrap();
+      (i, ThresholdKeys::new(ThresholdCore::new(params, share, verification_shares.clone())))
+    })
+    .collect();
+  (*coefficients[0], keys)
+}
+
+/// Test interpolation, verification shares, and offsets over random parameters and signing sets.
+pub(crate) fn test_properties<R: RngCore + CryptoRng, C: Ciphersuite>(rng: &mut R) {
+  let rng = &mut seeded_rng(rng);
+  for _ in 0 .. ITERATIONS {
+    let n = range(rng, 1, MAX_PARTICIPANTS);
+    let t = range(rng, 1, n);
+    let (secret, keys) = deal::<_, C>(rng, t, n);
+    let group_key = C::generator() * secret;
+
+    // Every participant should agree on the group key and the verification shares
+    for keys_i in keys.values() {
+      assert_eq!(keys_i.group_key(), group_key);
+      assert_eq!(keys_i.verification_shares(), keys[&Participant(1)].verification_shares());
+      assert_eq!(
+        keys_i.verification_shares()[&keys_i.params().i()],
+        C::generator() * keys_i.secret_share().deref()
+      );
+    }
+
+    // Any set of at least t participants should interpolate to the secret, including in the
+    // exponent via their verification shares
+    let size = range(rng, t, n);
+    let included = subset(rng, n, size);
+    let included_keys = included.iter().map(|i| (*i, keys[i].clone())).collect::<HashMap<_, _>>();
+    assert_eq!(recover_key(&included_keys), secret);
+    let verification_shares = keys[&Participant(1)].verification_shares();
+    assert_eq!(
+      included
+        .iter()
+        .map(|i| verification_shares[i] * lagrange::<C::F>(*i, &included))
+        .sum::<C::G>(),
+      group_key
+    );
+
+    // Views of offset keys should be consistent with each other and sum to the offset secret
+    let offset = C::F::random(&mut *rng);
+    let mut view_secret = C::F::ZERO;
+    for i in &included {
+      let view = keys[i].offset(offset).view(included.clone()).unwrap();
+      assert_eq!(view.group_key(), group_key + (C::generator() * offset));
+      for l in &included {
+        assert_eq!(view.original_verification_share(*l), verification_shares[l]);
+      }
+      assert_eq!(C::generator() * view.secret_share().deref(), view.verification_share(*i));
+      view_secret += view.secret_share().deref();
+    }
+    assert_eq!(view_secret, secret + offset);
+
+    // Less than t participants should be unable to form a view
+    if t > 1 {
+      let size = range(rng, 1, t - 1);
+      let insufficient = subset(rng, n, size);
+      assert!(matches!(
+        keys[&insufficient[0]].view(insufficient.clone()),
+        Err(DkgError::InvalidSigningSet)
+      ));
+    }
+  }
+}
@@ -65,3 +65,9 @@ field!(
 fn test_field() {
   ff_group_tests::prime_field::test_prime_field_bits::<_, FieldElement>(&mut rand_core::OsRng);
 }
+
+#[cfg(feature = "timing")]
+#[test]
+fn test_field_timing() {
+  ff_group_tests::timing::test_field::<_, FieldElement>(&mut rand_core::OsRng);
+}
@@ -324,6 +324,12 @@ fn test_group() {
   ff_group_tests::group::test_prime_group_bits::<_, Point>(&mut rand_core::OsRng);
 }
 
+#[cfg(feature = "timing")]
+#[test]
+fn test_group_timing() {
+  ff_group_tests::timing::test_group::<_, Point>(&mut rand_core::OsRng);
+}
+
 #[test]
 fn generator() {
   assert!(G.x == G_X);
@@ -8,3 +8,7 @@ pub mod prime_field;
 
 /// Tests for the Group and GroupEncoding traits.
 pub mod group;
+
+/// dudect-style tests for operations expected to be constant time.
+#[cfg(feature = "timing")]
+pub mod timing;
@@ -0,0 +1,112 @@
+use core::hint::black_box;
+use std::time::Instant;
+
+use rand_core::RngCore;
+use group::{ff::Field, Group, GroupEncoding};
+
+/// The amount of measurements taken per operation.
+pub const SAMPLES: usize = 10_000;
+
+/// The t-statistic at which an operation is considered to not be constant time.
+///
+/// dudect considers anything above 4.5 as likely leaking. This is set higher as wall clock
+/// measurements are far noisier than cycle counts.
+pub const THRESHOLD: f64 = 10.0;
+
+// Welch's t-test between the measurements of the fixed input and the random inputs
+fn t_statistic(measurements: &[(bool, u128)]) -> f64 {
+  // Crop the slowest measurements, which are likely due to the operation being interrupted
+  let mut sorted = measurements.iter().map(|(_, time)| *time).collect::<Vec<_>>();
+  sorted.sort_unstable();
+  let cutoff = sorted[(sorted.len() * 9) / 10];
+
+  let mut n = [0f64; 2];
+  let mut mean = [0f64; 2];
+  let mut m2 = [0f64; 2];
+  for (class, time) in measurements.iter().filter(|(_, time)| *time <= cutoff) {
+    let class = usize::from(*class);
+    let time = *time as f64;
+    n[class] += 1.0;
+    let delta = time - mean[class];
+    mean[class] += delta / n[class];
+    m2[class] += delta * (time - mean[class]);
+  }
+
+  let variance = |class: usize| m2[class] / (n[class] - 1.0);
+  ((mean[0] - mean[1]) / ((variance(0) / n[0]) + (variance(1) / n[1])).sqrt()).abs()
+}
+
+/// Measure an operation over a fixed input and over random inputs, returning the t-statistic for
+/// whether the two are distinguishable by their timings. This is the methodology of dudect.
+///
+/// All inputs are generated before any measurements are taken, and the fixed and random inputs
+/// are randomly interleaved so any change in the environment affects both equally.
+pub fn measure<R: RngCore, I: Clone, O>(
+  rng: &mut R,
+  mut random: impl FnMut(&mut R) -> I,
+  mut op: impl FnMut(I) -> O,
+) -> f64 {
+  let fixed = random(rng);
+  let inputs = (0 .. SAMPLES)
+    .map(|_| {
+      let class = (rng.next_u64() % 2) == 1;
+      (class, if class { random(rng) } else { fixed.clone() })
+    })
+    .collect::<Vec<_>>();
+
+  let mut measurements = Vec::with_capacity(SAMPLES);
+  for (class, input) in inputs {
+    let start = Instant::now();
+    black_box(op(black_box(input)));
+    measurements.push((class, start.elapsed().as_nanos()));
+  }
+  t_statistic(&measurements)
+}
+
+fn assert_constant_time(op: &str, t: f64) {
+  assert!(t < THRESHOLD, "{op} may not be constant time (t = {t})");
+}
+
+/// Test multiplication is constant time with regards to the elements multiplied.
+pub fn test_mul<R: RngCore, F: Field>(rng: &mut R) {
+  let t = measure(rng, |rng| (F::random(&mut *rng), F::random(&mut *rng)), |(a, b)| a * b);
+  assert_constant_time("multiplication", t);
+}
+
+/// Test inversion is constant time with regards to the element inverted.
+pub fn test_invert<R: RngCore, F: Field>(rng: &mut R) {
+  let t = measure(rng, |rng| F::random(rng), |a| a.invert());
+  assert_constant_time("inversion", t);
+}
+
+/// Test square roots are constant time with regards to the square the root is of.
+pub fn test_sqrt<R: RngCore, F: Field>(rng: &mut R) {
+  let t = measure(rng, |rng| F::random(rng).square(), |a| a.sqrt());
+  assert_constant_time("square root", t);
+}
+
+/// Run all timing tests on fields.
+pub fn test_field<R: RngCore, F: Field>(rng: &mut R) {
+  test_mul::<_, F>(rng);
+  test_invert::<_, F>(rng);
+  test_sqrt::<_, F>(rng);
+}
+
+/// Test scalar multiplication is constant time with regards to the scalar.
+pub fn test_scalar_mul<R: RngCore, G: Group>(rng: &mut R) {
+  let t = measure(rng, |rng| G::Scalar::random(rng), |scalar| G::generator() * scalar);
+  assert_constant_time("scalar multiplication", t);
+}
+
+/// Test decoding is constant time with regards to the element decoded.
+pub fn test_from_bytes<R: RngCore, G: Group + GroupEncoding>(rng: &mut R) {
+  let t = measure(rng, |rng| G::random(rng).to_bytes(), |bytes| G::from_bytes(&bytes));
+  assert_constant_time("decoding", t);
+}
+
+/// Run all timing tests on groups.
+pub fn test_group<R: RngCore, G: Group + GroupEncoding>(rng: &mut R) {
+  test_field::<_, G::Scalar>(rng);
+  test_scalar_mul::<_, G>(rng);
+  test_from_bytes::<_, G>(rng);
+}
@@ -2,8 +2,9 @@ use std::collections::HashMap;
 
 use zeroize::Zeroize;
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
 
+use ciphersuite::{group::GroupEncoding, Ciphersuite, Ristretto};
 use dkg::{Participant, ThresholdParams};
 
 use serai_primitives::{BlockHash, NetworkId};
@@ -11,6 +12,61 @@ use in_instructions_primitives::SignedBatch;
 use tokens_primitives::OutInstructionWithBalance;
 use validator_sets_primitives::{ValidatorSet, KeyPair};
 
+/// The version of the protocol messages are serialized under.
+///
+/// This must be incremented whenever a message is added, removed, or modified.
+pub const PROTOCOL_VERSION: u16 = 6;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageError {
+  /// The other party is running an incompatible version of the protocol.
+  IncompatibleVersion { ours: u16, theirs: u16 },
+  /// The message was malformed or of a variant unknown to this version.
+  InvalidMessage,
+}
+
+/// Sent by both the coordinator and processor when they connect, before any other message, to
+/// detect incompatible revisions before any protocol messages are exchanged.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Handshake {
+  pub version: u16,
+  pub network: NetworkId,
+}
+
+impl Handshake {
+  pub fn new(network: NetworkId) -> Handshake {
+    Handshake { version: PROTOCOL_VERSION, network }
+  }
+
+  pub fn serialize(&self) -> Vec<u8> {
+    serialize(self)
+  }
+
+  /// Deserialize a handshake, checking it's compatible with our own.
+  pub fn deserialize(bytes: &[u8]) -> Result<Handshake, MessageError> {
+    deserialize(bytes)
+  }
+}
+
+// Messages are prefixed with the protocol version so a mismatch is detected as such, instead of
+// bincode failing to deserialize or, worse, deserializing into a distinct message
+fn serialize<M: Serialize>(msg: &M) -> Vec<u8> {
+  let mut res = PROTOCOL_VERSION.to_le_bytes().to_vec();
+  res.extend(bincode::serialize(msg).unwrap());
+  res
+}
+
+fn deserialize<M: DeserializeOwned>(bytes: &[u8]) -> Result<M, MessageError> {
+  if bytes.len() < 2 {
+    Err(MessageError::InvalidMessage)?;
+  }
+  let theirs = u16::from_le_bytes([bytes[0], bytes[1]]);
+  if theirs != PROTOCOL_VERSION {
+    Err(MessageError::IncompatibleVersion { ours: PROTOCOL_VERSION, theirs })?;
+  }
+  bincode::deserialize(&bytes[2 ..]).map_err(|_| MessageError::InvalidMessage)
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Zeroize, Serialize, Deserialize)]
 pub struct SubstrateContext {
   pub serai_time: u64,
@@ -30,11 +86,26 @@ pub mod key_gen {
   pub enum CoordinatorMessage {
     // Instructs the Processor to begin the key generation process.
     // TODO: Should this be moved under Substrate?
-    GenerateKey { id: KeyGenId, params: ThresholdParams },
+    // The Serai genesis is included in the DKG's context so keys generated for one Serai network
+    // (such as a testnet) can't be used with another.
+    GenerateKey { id: KeyGenId, params: ThresholdParams, serai_genesis: [u8; 32] },
     // Received commitments for the specified key generation protocol.
     Commitments { id: KeyGenId, commitments: HashMap<Participant, Vec<u8>> },
     // Received shares for the specified key generation protocol.
     Shares { id: KeyGenId, shares: HashMap<Participant, Vec<u8>> },
+    // Received a single participant's commitments for the specified key generation protocol.
+    // These are accumulated until every other participant's commitments have been received and
+    // CommitmentsComplete has been, at which point they're handled as Commitments, without
+    // requiring them all in one message.
+    Commitment { id: KeyGenId, participant: Participant, commitments: Vec<u8> },
+    // Every other participant's commitments have been sent, as individual Commitment messages.
+    CommitmentsComplete { id: KeyGenId },
+    // Received a single participant's share for the specified key generation protocol.
+    // These are accumulated until every other participant's share has been received and
+    // SharesComplete has been.
+    Share { id: KeyGenId, participant: Participant, share: Vec<u8> },
+    // Every other participant's share has been sent, as individual Share messages.
+    SharesComplete { id: KeyGenId },
   }
 
   impl CoordinatorMessage {
@@ -74,6 +145,9 @@ pub mod sign {
     Reattempt { id: SignId },
     // Completed a signing protocol already.
     Completed { key: Vec<u8>, id: [u8; 32], tx: Vec<u8> },
+    // Re-sign an already published transaction, with its fee bumped the specified amount of times,
+    // under a new attempt.
+    BumpFee { id: SignId, bumps: u32 },
   }
 
   impl CoordinatorMessage {
@@ -87,6 +161,7 @@ pub mod sign {
         CoordinatorMessage::Shares { id, .. } => &id.key,
         CoordinatorMessage::Reattempt { id } => &id.key,
         CoordinatorMessage::Completed { key, .. } => key,
+        CoordinatorMessage::BumpFee { id, .. } => &id.key,
       }
     }
   }
@@ -100,12 +175,25 @@ pub mod sign {
     // Completed a signing protocol already.
     // TODO: Move this to SignId
     Completed { key: Vec<u8>, id: [u8; 32], tx: Vec<u8> },
+    // The transaction published for this plan has lingered without confirming. Request its fee be
+    // bumped the specified amount of times.
+    RequestFeeBump { key: Vec<u8>, id: [u8; 32], bumps: u32 },
   }
 }
 
 pub mod coordinator {
   use super::{sign::SignId, *};
 
+  /// The ID a batch is signed under, within its SignId.
+  ///
+  /// A block may have multiple batches, so this is derived from the batch's ID, not its block.
+  pub fn batch_sign_id(network: NetworkId, id: u32) -> [u8; 32] {
+    let mut res = [0; 32];
+    let encoded = bincode::serialize(&(network, id)).unwrap();
+    res[.. encoded.len()].copy_from_slice(&encoded);
+    res
+  }
+
   #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
   pub enum CoordinatorMessage {
     // Uses Vec<u8> instead of [u8; 64] since serde Deserialize isn't implemented for [u8; 64]
@@ -113,15 +201,23 @@ pub mod coordinator {
     BatchShares { id: SignId, shares: HashMap<Participant, [u8; 32]> },
     // Re-attempt a batch signing protocol.
     BatchReattempt { id: SignId },
+
+    // The following acknowledge the processor's completion messages, which the processor will
+    // resend on boot until acknowledged.
+    // Acknowledge the coordinator has the batch with the specified ID.
+    BatchSignedAck { key: Vec<u8>, id: u32 },
+    // Acknowledge the coordinator has the transaction for the specified plan.
+    TransactionSignedAck { key: Vec<u8>, id: [u8; 32] },
+    // Acknowledge the coordinator has saved the scanner's cursor.
+    ScannerCursorAck { key: Vec<u8>, block: BlockHash },
   }
 
   impl CoordinatorMessage {
     pub fn required_block(&self) -> Option<BlockHash> {
-      Some(match self {
-        CoordinatorMessage::BatchPreprocesses { id, .. } => BlockHash(id.id),
-        CoordinatorMessage::BatchShares { id, .. } => BlockHash(id.id),
-        CoordinatorMessage::BatchReattempt { id } => BlockHash(id.id),
-      })
+      // The coordinator only sends batch signing messages once the batch is recognized on the
+      // Tributary, which requires our processor provided it, which it only does after scanning
+      // the batch's block
+      None
     }
 
     pub fn key(&self) -> &[u8] {
@@ -129,6 +225,9 @@ pub mod coordinator {
         CoordinatorMessage::BatchPreprocesses { id, .. } => &id.key,
         CoordinatorMessage::BatchShares { id, .. } => &id.key,
         CoordinatorMessage::BatchReattempt { id } => &id.key,
+        CoordinatorMessage::BatchSignedAck { key, .. } => key,
+        CoordinatorMessage::TransactionSignedAck { key, .. } => key,
+        CoordinatorMessage::ScannerCursorAck { key, .. } => key,
       }
     }
   }
@@ -136,8 +235,35 @@ pub mod coordinator {
   #[derive(Clone, PartialEq, Eq, Debug, Zeroize, Serialize, Deserialize)]
   pub enum ProcessorMessage {
     SubstrateBlockAck { network: NetworkId, block: u64, plans: Vec<[u8; 32]> },
-    BatchPreprocess { id: SignId, preprocess: Vec<u8> },
+    BatchPreprocess { id: SignId, block: BlockHash, preprocess: Vec<u8> },
     BatchShare { id: SignId, share: [u8; 32] },
+
+    // Signed the batch with the specified ID.
+    BatchSigned { key: Vec<u8>, id: u32 },
+    // Signed the transaction for the specified plan.
+    TransactionSigned { key: Vec<u8>, id: [u8; 32] },
+    // Handled every block up to and including the specified block.
+    ScannerCursor { key: Vec<u8>, block: BlockHash },
+  }
+
+  impl ProcessorMessage {
+    /// The acknowledgement the coordinator should reply with, if this message expects one.
+    pub fn ack(&self) -> Option<CoordinatorMessage> {
+      match self {
+        ProcessorMessage::SubstrateBlockAck { .. } |
+        ProcessorMessage::BatchPreprocess { .. } |
+        ProcessorMessage::BatchShare { .. } => None,
+        ProcessorMessage::BatchSigned { key, id } => {
+          Some(CoordinatorMessage::BatchSignedAck { key: key.clone(), id: *id })
+        }
+        ProcessorMessage::TransactionSigned { key, id } => {
+          Some(CoordinatorMessage::TransactionSignedAck { key: key.clone(), id: *id })
+        }
+        ProcessorMessage::ScannerCursor { key, block } => {
+          Some(CoordinatorMessage::ScannerCursorAck { key: key.clone(), block: *block })
+        }
+      }
+    }
   }
 }
 
@@ -185,6 +311,13 @@ pub enum CoordinatorMessage {
 }
 
 impl CoordinatorMessage {
+  pub fn serialize(&self) -> Vec<u8> {
+    serialize(self)
+  }
+  pub fn deserialize(bytes: &[u8]) -> Result<CoordinatorMessage, MessageError> {
+    deserialize(bytes)
+  }
+
   pub fn required_block(&self) -> Option<BlockHash> {
     let required = match self {
       CoordinatorMessage::KeyGen(msg) => msg.required_block(),
@@ -200,6 +333,60 @@ impl CoordinatorMessage {
     }
     required
   }
+
+  /// The validator set this message is for.
+  ///
+  /// `key_set` returns the validator set a key is for, if it's known. Returns None if this message
+  /// is for a key whose set isn't known.
+  pub fn validator_set(
+    &self,
+    key_set: impl Fn(&[u8]) -> Option<ValidatorSet>,
+  ) -> Option<ValidatorSet> {
+    match self {
+      CoordinatorMessage::KeyGen(msg) => Some(match msg {
+        key_gen::CoordinatorMessage::GenerateKey { id, .. } |
+        key_gen::CoordinatorMessage::Commitments { id, .. } |
+        key_gen::CoordinatorMessage::Shares { id, .. } |
+        key_gen::CoordinatorMessage::Commitment { id, .. } |
+        key_gen::CoordinatorMessage::CommitmentsComplete { id } |
+        key_gen::CoordinatorMessage::Share { id, .. } |
+        key_gen::CoordinatorMessage::SharesComplete { id } => id.set,
+      }),
+      CoordinatorMessage::Sign(msg) => key_set(msg.key()),
+      CoordinatorMessage::Coordinator(msg) => key_set(msg.key()),
+      CoordinatorMessage::Substrate(msg) => match msg {
+        substrate::CoordinatorMessage::ConfirmKeyPair { set, .. } => Some(*set),
+        substrate::CoordinatorMessage::SubstrateBlock { network, key, .. } => {
+          key_set(key).filter(|set| set.network == *network)
+        }
+      },
+    }
+  }
+
+  /// The challenge the coordinator signs when sending this message to a processor.
+  ///
+  /// This binds the validator set the message is for, and accordingly both the network of the
+  /// processor it's for and the session, along with the message's ID, which is never reused. The
+  /// signature can't be replayed to another processor, for another session, or as another message.
+  pub fn challenge(
+    &self,
+    coordinator: <Ristretto as Ciphersuite>::G,
+    nonce: <Ristretto as Ciphersuite>::G,
+    set: ValidatorSet,
+    id: u64,
+  ) -> <Ristretto as Ciphersuite>::F {
+    Ristretto::hash_to_F(
+      b"Serai Coordinator Message",
+      &[
+        coordinator.to_bytes().as_ref(),
+        nonce.to_bytes().as_ref(),
+        &bincode::serialize(&set).unwrap(),
+        &id.to_le_bytes(),
+        &self.serialize(),
+      ]
+      .concat(),
+    )
+  }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -210,6 +397,15 @@ pub enum ProcessorMessage {
   Substrate(substrate::ProcessorMessage),
 }
 
+impl ProcessorMessage {
+  pub fn serialize(&self) -> Vec<u8> {
+    serialize(self)
+  }
+  pub fn deserialize(bytes: &[u8]) -> Result<ProcessorMessage, MessageError> {
+    deserialize(bytes)
+  }
+}
+
 const COORDINATOR_UID: u8 = 0;
 const PROCESSSOR_UID: u8 = 1;
 
@@ -230,14 +426,24 @@ impl CoordinatorMessage {
     match self {
       CoordinatorMessage::KeyGen(msg) => {
         // Unique since key gen ID embeds the validator set and attempt
-        let (sub, id) = match msg {
-          key_gen::CoordinatorMessage::GenerateKey { id, .. } => (0, id),
-          key_gen::CoordinatorMessage::Commitments { id, .. } => (1, id),
-          key_gen::CoordinatorMessage::Shares { id, .. } => (2, id),
+        let (sub, id, participant) = match msg {
+          key_gen::CoordinatorMessage::GenerateKey { id, .. } => (0, id, None),
+          key_gen::CoordinatorMessage::Commitments { id, .. } => (1, id, None),
+          key_gen::CoordinatorMessage::Shares { id, .. } => (2, id, None),
+          // Also unique to the participant, as each participant has one message per attempt
+          key_gen::CoordinatorMessage::Commitment { id, participant, .. } => {
+            (3, id, Some(participant))
+          }
+          key_gen::CoordinatorMessage::Share { id, participant, .. } => (4, id, Some(participant)),
+          key_gen::CoordinatorMessage::CommitmentsComplete { id } => (5, id, None),
+          key_gen::CoordinatorMessage::SharesComplete { id } => (6, id, None),
         };
 
         let mut res = vec![COORDINATOR_UID, TYPE_KEY_GEN_UID, sub];
         res.extend(&bincode::serialize(id).unwrap());
+        if let Some(participant) = participant {
+          res.extend(u16::from(*participant).to_le_bytes());
+        }
         res
       }
       CoordinatorMessage::Sign(msg) => {
@@ -249,6 +455,8 @@ impl CoordinatorMessage {
           // TODO: This doesn't embed the attempt. Accordingly, multiple distinct completions will
           // be flattened. This isn't acceptable.
           sign::CoordinatorMessage::Completed { id, .. } => (3, id.to_vec()),
+          // Unique since SignId, as every fee bump is signed under a new attempt
+          sign::CoordinatorMessage::BumpFee { id, .. } => (4, bincode::serialize(id).unwrap()),
         };
 
         let mut res = vec![COORDINATOR_UID, TYPE_SIGN_UID, sub];
@@ -257,7 +465,7 @@ impl CoordinatorMessage {
       }
       CoordinatorMessage::Coordinator(msg) => {
         let (sub, id) = match msg {
-          // Unique since this embeds the batch ID (hash of it, including its network) and attempt
+          // Unique since this embeds the batch ID (which embeds its network) and attempt
           coordinator::CoordinatorMessage::BatchPreprocesses { id, .. } => {
             (0, bincode::serialize(id).unwrap())
           }
@@ -267,6 +475,17 @@ impl CoordinatorMessage {
           coordinator::CoordinatorMessage::BatchReattempt { id, .. } => {
             (2, bincode::serialize(id).unwrap())
           }
+          // Unique since each completion is only acknowledged once
+          coordinator::CoordinatorMessage::BatchSignedAck { key, id } => {
+            (3, bincode::serialize(&(key, id)).unwrap())
+          }
+          coordinator::CoordinatorMessage::TransactionSignedAck { key, id } => {
+            (4, bincode::serialize(&(key, id)).unwrap())
+          }
+          // Unique since the scanner's cursor only moves forward
+          coordinator::CoordinatorMessage::ScannerCursorAck { key, block } => {
+            (5, bincode::serialize(&(key, block)).unwrap())
+          }
         };
 
         let mut res = vec![COORDINATOR_UID, TYPE_COORDINATOR_UID, sub];
@@ -318,8 +537,15 @@ impl ProcessorMessage {
           // Unique since SignId
           sign::ProcessorMessage::Preprocess { id, .. } => (0, bincode::serialize(id).unwrap()),
           sign::ProcessorMessage::Share { id, .. } => (1, bincode::serialize(id).unwrap()),
-          // Unique since a processor will only sign a TX once
-          sign::ProcessorMessage::Completed { id, .. } => (2, id.to_vec()),
+          // Unique since a processor will only sign a TX once, and a plan only has multiple TXs
+          // when their fees differ
+          sign::ProcessorMessage::Completed { id, tx, .. } => {
+            (2, bincode::serialize(&(id, tx)).unwrap())
+          }
+          // Unique since each fee bump is only requested once
+          sign::ProcessorMessage::RequestFeeBump { key, id, bumps } => {
+            (3, bincode::serialize(&(key, id, bumps)).unwrap())
+          }
         };
 
         let mut res = vec![PROCESSSOR_UID, TYPE_SIGN_UID, sub];
@@ -338,6 +564,17 @@ impl ProcessorMessage {
           coordinator::ProcessorMessage::BatchShare { id, .. } => {
             (2, bincode::serialize(id).unwrap())
           }
+          // Unique since a batch/transaction is only signed once per key
+          coordinator::ProcessorMessage::BatchSigned { key, id } => {
+            (3, bincode::serialize(&(key, id)).unwrap())
+          }
+          coordinator::ProcessorMessage::TransactionSigned { key, id } => {
+            (4, bincode::serialize(&(key, id)).unwrap())
+          }
+          // Unique since the scanner's cursor only moves forward
+          coordinator::ProcessorMessage::ScannerCursor { key, block } => {
+            (5, bincode::serialize(&(key, block)).unwrap())
+          }
         };
 
         let mut res = vec![PROCESSSOR_UID, TYPE_COORDINATOR_UID, sub];
@@ -359,3 +596,6 @@ impl ProcessorMessage {
     }
   }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,69 @@
+use dkg::{Participant, ThresholdParams};
+
+use serai_primitives::NetworkId;
+use validator_sets_primitives::{Session, ValidatorSet};
+
+use crate::*;
+
+fn key_gen_id() -> key_gen::KeyGenId {
+  key_gen::KeyGenId {
+    set: ValidatorSet { session: Session(0), network: NetworkId::Bitcoin },
+    attempt: 0,
+  }
+}
+
+#[test]
+fn round_trip() {
+  let handshake = Handshake::new(NetworkId::Bitcoin);
+  assert_eq!(Handshake::deserialize(&handshake.serialize()), Ok(handshake));
+
+  let msg = CoordinatorMessage::KeyGen(key_gen::CoordinatorMessage::GenerateKey {
+    id: key_gen_id(),
+    params: ThresholdParams::new(2, 3, Participant::new(1).unwrap()).unwrap(),
+    serai_genesis: [0xff; 32],
+  });
+  assert_eq!(CoordinatorMessage::deserialize(&msg.serialize()), Ok(msg));
+
+  let msg = ProcessorMessage::KeyGen(key_gen::ProcessorMessage::GeneratedKeyPair {
+    id: key_gen_id(),
+    substrate_key: [0xaa; 32],
+    coin_key: vec![0xbb; 33],
+  });
+  assert_eq!(ProcessorMessage::deserialize(&msg.serialize()), Ok(msg));
+}
+
+#[test]
+fn version_mismatch() {
+  let mut handshake = Handshake::new(NetworkId::Bitcoin).serialize();
+  handshake[.. 2].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+  assert_eq!(
+    Handshake::deserialize(&handshake),
+    Err(MessageError::IncompatibleVersion { ours: PROTOCOL_VERSION, theirs: PROTOCOL_VERSION + 1 })
+  );
+
+  // Messages are versioned as well, so a message from another version is never misinterpreted
+  let mut msg = ProcessorMessage::Coordinator(coordinator::ProcessorMessage::BatchSigned {
+    key: vec![0xaa; 32],
+    id: 0,
+  })
+  .serialize();
+  msg[.. 2].copy_from_slice(&(PROTOCOL_VERSION - 1).to_le_bytes());
+  assert_eq!(
+    ProcessorMessage::deserialize(&msg),
+    Err(MessageError::IncompatibleVersion { ours: PROTOCOL_VERSION, theirs: PROTOCOL_VERSION - 1 })
+  );
+}
+
+#[test]
+fn invalid_message() {
+  assert_eq!(Handshake::deserialize(&[]), Err(MessageError::InvalidMessage));
+  assert_eq!(
+    CoordinatorMessage::deserialize(&PROTOCOL_VERSION.to_le_bytes()),
+    Err(MessageError::InvalidMessage)
+  );
+
+  // A variant unknown to this version
+  let mut msg = PROTOCOL_VERSION.to_le_bytes().to_vec();
+  msg.extend(u32::MAX.to_le_bytes());
+  assert_eq!(CoordinatorMessage::deserialize(&msg), Err(MessageError::InvalidMessage));
+}
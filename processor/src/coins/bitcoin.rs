@@ -43,7 +43,7 @@ use serai_client::{
 
 use crate::{
   coins::{
-    CoinError, Block as BlockTrait, OutputType, Output as OutputTrait,
+    CoinError, RpcPool, Block as BlockTrait, OutputType, Output as OutputTrait,
     Transaction as TransactionTrait, Eventuality, EventualitiesTracker, PostFeeBranch, Coin,
     drop_branches, amortize_fee,
   },
@@ -138,6 +138,10 @@ impl TransactionTrait<Bitcoin> for Transaction {
     hash.reverse();
     hash
   }
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Transaction::consensus_decode(reader)
+      .map_err(|_| io::Error::new(io::ErrorKind::Other, "couldn't decode transaction"))
+  }
   fn serialize(&self) -> Vec<u8> {
     let mut buf = vec![];
     self.consensus_encode(&mut buf).unwrap();
@@ -150,7 +154,7 @@ impl TransactionTrait<Bitcoin> for Transaction {
       let output = input.previous_output;
       let mut hash = *output.txid.as_raw_hash().as_byte_array();
       hash.reverse();
-      value += coin.rpc.get_transaction(&hash).await.unwrap().output
+      value += coin.get_transaction(&hash).await.unwrap().output
         [usize::try_from(output.vout).unwrap()]
       .value;
     }
@@ -248,7 +252,7 @@ fn scanner(
 
 #[derive(Clone, Debug)]
 pub struct Bitcoin {
-  pub(crate) rpc: Rpc,
+  pub(crate) rpc: RpcPool<Rpc>,
 }
 // Shim required for testing/debugging purposes due to generic arguments also necessitating trait
 // bounds
@@ -260,19 +264,26 @@ impl PartialEq for Bitcoin {
 impl Eq for Bitcoin {}
 
 impl Bitcoin {
-  pub async fn new(url: String) -> Bitcoin {
-    Bitcoin { rpc: Rpc::new(url).await.expect("couldn't create a Bitcoin RPC") }
+  /// Connect to multiple nodes, requiring `agreement` of them to agree on blocks.
+  pub async fn with_endpoints(urls: Vec<String>, agreement: usize) -> Bitcoin {
+    let mut rpcs = vec![];
+    for url in urls {
+      rpcs.push(Rpc::new(url).await.expect("couldn't create a Bitcoin RPC"));
+    }
+    let rpc = RpcPool::new(rpcs, agreement);
+    rpc.monitor(|rpc| async move { rpc.get_latest_block_number().await });
+    Bitcoin { rpc }
   }
 
   #[cfg(test)]
   pub async fn fresh_chain(&self) {
-    if self.rpc.get_latest_block_number().await.unwrap() > 0 {
+    if self.get_latest_block_number().await.unwrap() > 0 {
+      let hash = self.rpc.agreed(|rpc| async move { rpc.get_block_hash(1).await }).await.unwrap();
       self
         .rpc
-        .rpc_call(
-          "invalidateblock",
-          serde_json::json!([hex::encode(self.rpc.get_block_hash(1).await.unwrap())]),
-        )
+        .request(|rpc| async move {
+          rpc.rpc_call::<()>("invalidateblock", serde_json::json!([hex::encode(hash)])).await
+        })
         .await
         .unwrap()
     }
@@ -305,6 +316,9 @@ impl Coin for Bitcoin {
   #[allow(clippy::inconsistent_digit_grouping)]
   const DUST: u64 = 1_00_000_000 / 10_000;
 
+  // Transactions signal replaceability (BIP-125)
+  const FEE_BUMPS: bool = true;
+
   // Bitcoin has a max weight of 400,000 (MAX_STANDARD_TX_WEIGHT)
   // A non-SegWit TX will have 4 weight units per byte, leaving a max size of 100,000 bytes
   // While our inputs are entirely SegWit, such fine tuning is not necessary and could create
@@ -319,6 +333,11 @@ impl Coin for Bitcoin {
   const MAX_INPUTS: usize = 520;
   const MAX_OUTPUTS: usize = 520;
 
+  // Doubling the fee rate raises it by at least the incremental relay fee a replacement must pay
+  fn bump_fee(fee: Fee, bumps: u32) -> Fee {
+    Fee(fee.0.saturating_mul(2u64.saturating_pow(bumps)))
+  }
+
   fn tweak_keys(keys: &mut ThresholdKeys<Self::Curve>) {
     *keys = tweak_keys(keys);
     // Also create a scanner to assert these keys, and all expected paths, are usable
@@ -335,13 +354,13 @@ impl Coin for Bitcoin {
   }
 
   async fn get_latest_block_number(&self) -> Result<usize, CoinError> {
-    self.rpc.get_latest_block_number().await.map_err(|_| CoinError::ConnectionError)
+    self.rpc.request(|rpc| async move { rpc.get_latest_block_number().await }).await
   }
 
   async fn get_block(&self, number: usize) -> Result<Self::Block, CoinError> {
-    let block_hash =
-      self.rpc.get_block_hash(number).await.map_err(|_| CoinError::ConnectionError)?;
-    self.rpc.get_block(&block_hash).await.map_err(|_| CoinError::ConnectionError)
+    let block_hash = self.rpc.agreed(|rpc| async move { rpc.get_block_hash(number).await }).await?;
+    // The RPC verifies the block has the requested hash, so this doesn't need to be cross-checked
+    self.rpc.request(|rpc| async move { rpc.get_block(&block_hash).await }).await
   }
 
   async fn get_outputs(
@@ -412,7 +431,11 @@ impl Coin for Bitcoin {
     let this_block_hash = block.id();
     let this_block_num = (|| async {
       loop {
-        match self.rpc.get_block_number(&this_block_hash).await {
+        match self
+          .rpc
+          .request(|rpc| async move { rpc.get_block_number(&this_block_hash).await })
+          .await
+        {
           Ok(number) => return number,
           Err(e) => {
             log::error!("couldn't get the block number for {}: {}", hex::encode(this_block_hash), e)
@@ -532,9 +555,19 @@ impl Coin for Bitcoin {
   }
 
   async fn publish_transaction(&self, tx: &Self::Transaction) -> Result<(), CoinError> {
-    match self.rpc.send_raw_transaction(tx).await {
+    // Only fail over on connection errors, as any other error is from the transaction itself
+    let res = self
+      .rpc
+      .request(|rpc| async move {
+        match rpc.send_raw_transaction(tx).await {
+          Err(RpcError::ConnectionError) => Err(RpcError::ConnectionError),
+          res => Ok(res),
+        }
+      })
+      .await?;
+    match res {
       Ok(_) => (),
-      Err(RpcError::ConnectionError) => Err(CoinError::ConnectionError)?,
+      Err(RpcError::ConnectionError) => unreachable!("connection errors caused a failover"),
       // TODO: Distinguish already in pool vs double spend (other signing attempt succeeded) vs
       // invalid transaction
       Err(e) => panic!("failed to publish TX {}: {e}", tx.txid()),
@@ -543,7 +576,7 @@ impl Coin for Bitcoin {
   }
 
   async fn get_transaction(&self, id: &[u8; 32]) -> Result<Transaction, CoinError> {
-    self.rpc.get_transaction(id).await.map_err(|_| CoinError::ConnectionError)
+    self.rpc.request(|rpc| async move { rpc.get_transaction(id).await }).await
   }
 
   fn confirm_completion(&self, eventuality: &OutPoint, tx: &Transaction) -> bool {
@@ -552,7 +585,7 @@ impl Coin for Bitcoin {
 
   #[cfg(test)]
   async fn get_block_number(&self, id: &[u8; 32]) -> usize {
-    self.rpc.get_block_number(id).await.unwrap()
+    self.rpc.request(|rpc| async move { rpc.get_block_number(id).await }).await.unwrap()
   }
 
   #[cfg(test)]
@@ -564,10 +597,14 @@ impl Coin for Bitcoin {
   async fn mine_block(&self) {
     self
       .rpc
-      .rpc_call::<Vec<String>>(
-        "generatetoaddress",
-        serde_json::json!([1, BAddress::p2sh(Script::empty(), Network::Regtest).unwrap()]),
-      )
+      .request(|rpc| async move {
+        rpc
+          .rpc_call::<Vec<String>>(
+            "generatetoaddress",
+            serde_json::json!([1, BAddress::p2sh(Script::empty(), Network::Regtest).unwrap()]),
+          )
+          .await
+      })
       .await
       .unwrap();
   }
@@ -580,9 +617,14 @@ impl Coin for Bitcoin {
     let main_addr = BAddress::p2pkh(&public_key, Network::Regtest);
 
     let new_block = self.get_latest_block_number().await.unwrap() + 1;
+    let main_addr_ref = &main_addr;
     self
       .rpc
-      .rpc_call::<Vec<String>>("generatetoaddress", serde_json::json!([1, main_addr]))
+      .request(|rpc| async move {
+        rpc
+          .rpc_call::<Vec<String>>("generatetoaddress", serde_json::json!([1, main_addr_ref]))
+          .await
+      })
       .await
       .unwrap();
 
@@ -625,7 +667,8 @@ impl Coin for Bitcoin {
       .into_script();
 
     let block = self.get_latest_block_number().await.unwrap() + 1;
-    self.rpc.send_raw_transaction(&tx).await.unwrap();
+    let tx_ref = &tx;
+    self.rpc.request(|rpc| async move { rpc.send_raw_transaction(tx_ref).await }).await.unwrap();
     for _ in 0 .. Self::CONFIRMATIONS {
       self.mine_block().await;
     }
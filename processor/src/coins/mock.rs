@@ -0,0 +1,878 @@
+use core::fmt;
+use std::{
+  io,
+  sync::{Arc, Mutex},
+  collections::{HashSet, HashMap},
+};
+
+use async_trait::async_trait;
+
+use rand_core::{RngCore, CryptoRng};
+
+use transcript::{Transcript, RecommendedTranscript};
+use group::GroupEncoding;
+use frost::{
+  curve::{Ciphersuite, Ristretto, IetfRistrettoHram},
+  FrostError, Participant, ThresholdKeys,
+  algorithm::{Hram, Schnorr, SchnorrSignature},
+  sign::{
+    CachedPreprocess, Preprocess, SignatureShare, PreprocessMachine, SignMachine, SignatureMachine,
+    AlgorithmMachine, AlgorithmSignMachine, AlgorithmSignatureMachine,
+  },
+};
+
+use serai_client::primitives::{MAX_DATA_LEN, Coin as SeraiCoin, NetworkId, Amount, Balance};
+
+use crate::{
+  coins::{
+    CoinError, Block as BlockTrait, OutputType, Output as OutputTrait,
+    Transaction as TransactionTrait, Eventuality as EventualityTrait, EventualitiesTracker,
+    PostFeeBranch, Coin, drop_branches, amortize_fee,
+  },
+  Plan,
+};
+
+type Point = <Ristretto as Ciphersuite>::G;
+type Scalar = <Ristretto as Ciphersuite>::F;
+
+type MockSchnorr = Schnorr<Ristretto, RecommendedTranscript, IetfRistrettoHram>;
+
+fn hash(label: &'static [u8], data: &[u8]) -> [u8; 32] {
+  let mut transcript = RecommendedTranscript::new(b"Serai Processor Mock Coin");
+  transcript.append_message(label, data);
+  transcript.challenge(b"hash")[.. 32].try_into().unwrap()
+}
+
+fn read_u64<R: io::Read>(reader: &mut R) -> io::Result<u64> {
+  let mut buf = [0; 8];
+  reader.read_exact(&mut buf)?;
+  Ok(u64::from_le_bytes(buf))
+}
+
+fn read_vec<R: io::Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+  let mut len = [0; 2];
+  reader.read_exact(&mut len)?;
+  let mut data = vec![0; usize::from(u16::from_le_bytes(len))];
+  reader.read_exact(&mut data)?;
+  Ok(data)
+}
+
+fn write_vec<W: io::Write>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+  writer.write_all(&u16::try_from(data.len()).unwrap().to_le_bytes())?;
+  writer.write_all(data)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Address(pub Point);
+
+impl fmt::Display for Address {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", hex::encode(self.0.to_bytes()))
+  }
+}
+
+impl From<Address> for Vec<u8> {
+  fn from(address: Address) -> Vec<u8> {
+    address.0.to_bytes().to_vec()
+  }
+}
+
+impl TryFrom<Vec<u8>> for Address {
+  type Error = ();
+  fn try_from(bytes: Vec<u8>) -> Result<Address, ()> {
+    let mut bytes_ref = bytes.as_slice();
+    let key = Ristretto::read_G(&mut bytes_ref).map_err(|_| ())?;
+    if !bytes_ref.is_empty() {
+      Err(())?;
+    }
+    Ok(Address(key))
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Output {
+  id: [u8; 32],
+  kind: OutputType,
+  amount: u64,
+  data: Vec<u8>,
+}
+
+impl OutputTrait for Output {
+  type Id = [u8; 32];
+
+  fn kind(&self) -> OutputType {
+    self.kind
+  }
+
+  fn id(&self) -> Self::Id {
+    self.id
+  }
+
+  fn balance(&self) -> Balance {
+    Balance { coin: SeraiCoin::Bitcoin, amount: Amount(self.amount) }
+  }
+
+  fn data(&self) -> &[u8] {
+    &self.data
+  }
+
+  fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+    writer.write_all(&self.id)?;
+    self.kind.write(writer)?;
+    writer.write_all(&self.amount.to_le_bytes())?;
+    write_vec(writer, &self.data)
+  }
+
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    let mut id = [0; 32];
+    reader.read_exact(&mut id)?;
+    Ok(Output {
+      id,
+      kind: OutputType::read(reader)?,
+      amount: read_u64(reader)?,
+      data: read_vec(reader)?,
+    })
+  }
+}
+
+/// An output as it appears on the mock chain.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TransactionOutput {
+  pub address: Address,
+  pub amount: u64,
+  pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Transaction {
+  // The key which authorized this transaction's spends, or None for deposits
+  key: Option<Point>,
+  inputs: Vec<[u8; 32]>,
+  outputs: Vec<TransactionOutput>,
+  // Distinguishes otherwise identical deposits
+  nonce: u64,
+  signature: Option<SchnorrSignature<Ristretto>>,
+}
+
+impl Transaction {
+  // The serialization of everything but the signature, which is what's signed
+  fn body(&self) -> Vec<u8> {
+    let mut buf = vec![];
+    buf.push(u8::from(self.key.is_some()));
+    if let Some(key) = self.key {
+      buf.extend(key.to_bytes().as_ref());
+    }
+    buf.extend(&u16::try_from(self.inputs.len()).unwrap().to_le_bytes());
+    for input in &self.inputs {
+      buf.extend(input);
+    }
+    buf.extend(&u16::try_from(self.outputs.len()).unwrap().to_le_bytes());
+    for output in &self.outputs {
+      buf.extend(output.address.0.to_bytes().as_ref());
+      buf.extend(&output.amount.to_le_bytes());
+      write_vec(&mut buf, &output.data).unwrap();
+    }
+    buf.extend(&self.nonce.to_le_bytes());
+    buf
+  }
+
+  fn output_id(&self, i: usize) -> [u8; 32] {
+    hash(b"output", &[self.id().as_ref(), &u16::try_from(i).unwrap().to_le_bytes()].concat())
+  }
+}
+
+#[async_trait]
+impl TransactionTrait<Mock> for Transaction {
+  type Id = [u8; 32];
+  fn id(&self) -> Self::Id {
+    hash(b"transaction", &self.body())
+  }
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    let mut flag = [0; 1];
+    reader.read_exact(&mut flag)?;
+    let key = if flag[0] == 1 { Some(Ristretto::read_G(reader)?) } else { None };
+
+    let mut len = [0; 2];
+    reader.read_exact(&mut len)?;
+    let mut inputs = vec![];
+    for _ in 0 .. u16::from_le_bytes(len) {
+      let mut input = [0; 32];
+      reader.read_exact(&mut input)?;
+      inputs.push(input);
+    }
+
+    reader.read_exact(&mut len)?;
+    let mut outputs = vec![];
+    for _ in 0 .. u16::from_le_bytes(len) {
+      outputs.push(TransactionOutput {
+        address: Address(Ristretto::read_G(reader)?),
+        amount: read_u64(reader)?,
+        data: read_vec(reader)?,
+      });
+    }
+
+    let nonce = read_u64(reader)?;
+
+    reader.read_exact(&mut flag)?;
+    let signature = if flag[0] == 1 { Some(SchnorrSignature::read(reader)?) } else { None };
+
+    Ok(Transaction { key, inputs, outputs, nonce, signature })
+  }
+  fn serialize(&self) -> Vec<u8> {
+    let mut buf = self.body();
+    buf.push(u8::from(self.signature.is_some()));
+    if let Some(signature) = self.signature {
+      signature.write(&mut buf).unwrap();
+    }
+    buf
+  }
+  #[cfg(test)]
+  async fn fee(&self, coin: &Mock) -> u64 {
+    coin.chain.lock().unwrap().fee(self)
+  }
+}
+
+/// The first input spent by a plan.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Eventuality([u8; 32]);
+
+impl EventualityTrait for Eventuality {
+  fn lookup(&self) -> Vec<u8> {
+    self.0.to_vec()
+  }
+
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    let mut input = [0; 32];
+    reader.read_exact(&mut input)?;
+    Ok(Eventuality(input))
+  }
+  fn serialize(&self) -> Vec<u8> {
+    self.0.to_vec()
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Block {
+  parent: [u8; 32],
+  number: usize,
+  time: u64,
+  fee: u64,
+  // Distinguishes blocks mined at the same height, such as after a reorganization
+  salt: u64,
+  transactions: Vec<Transaction>,
+}
+
+impl BlockTrait<Mock> for Block {
+  type Id = [u8; 32];
+  fn id(&self) -> Self::Id {
+    let mut buf = self.parent.to_vec();
+    buf.extend(&u64::try_from(self.number).unwrap().to_le_bytes());
+    buf.extend(&self.time.to_le_bytes());
+    buf.extend(&self.fee.to_le_bytes());
+    buf.extend(&self.salt.to_le_bytes());
+    for tx in &self.transactions {
+      buf.extend(&tx.id());
+    }
+    hash(b"block", &buf)
+  }
+
+  fn parent(&self) -> Self::Id {
+    self.parent
+  }
+
+  fn time(&self) -> u64 {
+    self.time
+  }
+
+  fn median_fee(&self) -> Fee {
+    Fee(self.fee)
+  }
+}
+
+/// The fee per input and output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Fee(u64);
+
+#[derive(Clone, Debug)]
+pub struct SignableTransaction {
+  keys: ThresholdKeys<Ristretto>,
+  transcript: RecommendedTranscript,
+  tx: Transaction,
+}
+
+pub struct TransactionMachine {
+  tx: Transaction,
+  machine: AlgorithmMachine<Ristretto, MockSchnorr>,
+}
+
+impl PreprocessMachine for TransactionMachine {
+  type Preprocess = Preprocess<Ristretto, ()>;
+  type Signature = Transaction;
+  type SignMachine = TransactionSignMachine;
+
+  fn preprocess<R: RngCore + CryptoRng>(
+    self,
+    rng: &mut R,
+  ) -> (TransactionSignMachine, Self::Preprocess) {
+    let (machine, preprocess) = self.machine.preprocess(rng);
+    (TransactionSignMachine { tx: self.tx, machine }, preprocess)
+  }
+}
+
+pub struct TransactionSignMachine {
+  tx: Transaction,
+  machine: AlgorithmSignMachine<Ristretto, MockSchnorr>,
+}
+
+impl SignMachine<Transaction> for TransactionSignMachine {
+  type Params = (MockSchnorr, Transaction);
+  type Keys = ThresholdKeys<Ristretto>;
+  type Preprocess = Preprocess<Ristretto, ()>;
+  type SignatureShare = SignatureShare<Ristretto>;
+  type SignatureMachine = TransactionSignatureMachine;
+
+  fn cache(self) -> CachedPreprocess {
+    self.machine.cache()
+  }
+
+  fn from_cache(
+    (algorithm, tx): (MockSchnorr, Transaction),
+    keys: ThresholdKeys<Ristretto>,
+    cache: CachedPreprocess,
+  ) -> Result<Self, FrostError> {
+    let machine = AlgorithmSignMachine::from_cache(algorithm, keys, cache)?;
+    Ok(TransactionSignMachine { tx, machine })
+  }
+
+  fn read_preprocess<R: io::Read>(&self, reader: &mut R) -> io::Result<Self::Preprocess> {
+    self.machine.read_preprocess(reader)
+  }
+
+  fn sign(
+    self,
+    commitments: HashMap<Participant, Self::Preprocess>,
+    msg: &[u8],
+  ) -> Result<(TransactionSignatureMachine, SignatureShare<Ristretto>), FrostError> {
+    if !msg.is_empty() {
+      panic!("message was passed to the TransactionSignMachine when it generates its own");
+    }
+    let (machine, share) = self.machine.sign(commitments, &self.tx.body())?;
+    Ok((TransactionSignatureMachine { tx: self.tx, machine }, share))
+  }
+}
+
+pub struct TransactionSignatureMachine {
+  tx: Transaction,
+  machine: AlgorithmSignatureMachine<Ristretto, MockSchnorr>,
+}
+
+impl SignatureMachine<Transaction> for TransactionSignatureMachine {
+  type SignatureShare = SignatureShare<Ristretto>;
+
+  fn read_share<R: io::Read>(&self, reader: &mut R) -> io::Result<Self::SignatureShare> {
+    self.machine.read_share(reader)
+  }
+
+  fn complete(
+    self,
+    shares: HashMap<Participant, SignatureShare<Ristretto>>,
+  ) -> Result<Transaction, FrostError> {
+    let mut tx = self.tx;
+    tx.signature = Some(self.machine.complete(shares)?);
+    Ok(tx)
+  }
+}
+
+const KEY_DST: &[u8] = b"Mock Key";
+lazy_static::lazy_static! {
+  static ref BRANCH_OFFSET: Scalar = Ristretto::hash_to_F(KEY_DST, b"branch");
+  static ref CHANGE_OFFSET: Scalar = Ristretto::hash_to_F(KEY_DST, b"change");
+}
+
+// How many seconds pass between blocks
+const BLOCK_TIME: u64 = 60;
+
+#[derive(Debug)]
+struct Chain {
+  blocks: Vec<Block>,
+  mempool: Vec<Transaction>,
+  // The fee rate for blocks mined from now on
+  fee: u64,
+  // How many blocks have ever been mined, used to salt block IDs
+  mined: u64,
+  // How many deposits have ever been made, used to make their IDs unique
+  deposits: u64,
+}
+
+impl Chain {
+  fn new() -> Chain {
+    let genesis =
+      Block { parent: [0; 32], number: 0, time: 0, fee: 1, salt: 0, transactions: vec![] };
+    Chain { blocks: vec![genesis], mempool: vec![], fee: 1, mined: 0, deposits: 0 }
+  }
+
+  fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+    self.blocks.iter().flat_map(|block| block.transactions.iter()).chain(self.mempool.iter())
+  }
+
+  fn transaction(&self, id: &[u8; 32]) -> Option<&Transaction> {
+    self.transactions().find(|tx| &tx.id() == id)
+  }
+
+  // Find an output, whether or not it's been spent
+  fn output(&self, id: &[u8; 32]) -> Option<&TransactionOutput> {
+    for tx in self.transactions() {
+      for (i, output) in tx.outputs.iter().enumerate() {
+        if &tx.output_id(i) == id {
+          return Some(output);
+        }
+      }
+    }
+    None
+  }
+
+  // The unspent outputs, including those created and spent within the mempool
+  fn utxos(&self) -> HashMap<[u8; 32], TransactionOutput> {
+    let mut utxos = HashMap::new();
+    for tx in self.transactions() {
+      for input in &tx.inputs {
+        utxos.remove(input);
+      }
+      for (i, output) in tx.outputs.iter().enumerate() {
+        utxos.insert(tx.output_id(i), output.clone());
+      }
+    }
+    utxos
+  }
+
+  fn verify(&self, tx: &Transaction) -> bool {
+    let (Some(key), Some(signature)) = (tx.key, tx.signature) else { return false };
+    if !signature.verify(key, IetfRistrettoHram::hram(&signature.R, &key, &tx.body())) {
+      return false;
+    }
+
+    // Every input must be an unspent output owned by this key
+    let owned = [Mock::address(key), Mock::branch_address(key), Mock::change_address(key)];
+    let utxos = self.utxos();
+    let mut inputs = 0;
+    for input in &tx.inputs {
+      let Some(output) = utxos.get(input) else { return false };
+      if !owned.contains(&output.address) {
+        return false;
+      }
+      inputs += output.amount;
+    }
+    if tx.inputs.iter().collect::<HashSet<_>>().len() != tx.inputs.len() {
+      return false;
+    }
+
+    inputs >= tx.outputs.iter().map(|output| output.amount).sum::<u64>()
+  }
+
+  // Remove a transaction from the mempool, along with any transactions spending its outputs
+  fn remove_transaction(&mut self, id: [u8; 32]) -> bool {
+    let Some(i) = self.mempool.iter().position(|tx| tx.id() == id) else { return false };
+    let tx = self.mempool.remove(i);
+    for output in (0 .. tx.outputs.len()).map(|i| tx.output_id(i)) {
+      let spenders = self
+        .mempool
+        .iter()
+        .filter(|spender| spender.inputs.contains(&output))
+        .map(Transaction::id)
+        .collect::<Vec<_>>();
+      for spender in spenders {
+        self.remove_transaction(spender);
+      }
+    }
+    true
+  }
+
+  fn fee(&self, tx: &Transaction) -> u64 {
+    let inputs = tx.inputs.iter().map(|input| self.output(input).unwrap().amount).sum::<u64>();
+    inputs - tx.outputs.iter().map(|output| output.amount).sum::<u64>()
+  }
+
+  // If a transaction pays enough of a fee to be mined at the current fee rate
+  fn pays_fee(&self, tx: &Transaction) -> bool {
+    // Deposits are always mined
+    tx.key.is_none() ||
+      (self.fee(tx) >= (self.fee * u64::try_from(tx.inputs.len() + tx.outputs.len()).unwrap()))
+  }
+
+  fn mine(&mut self) {
+    // Transactions which don't pay enough of a fee, along with any transactions spending their
+    // outputs, linger in the mempool
+    let mut lingering = HashSet::new();
+    let mut include = vec![];
+    for tx in &self.mempool {
+      let lingers = tx.inputs.iter().any(|input| lingering.contains(input)) || (!self.pays_fee(tx));
+      if lingers {
+        lingering.extend((0 .. tx.outputs.len()).map(|i| tx.output_id(i)));
+      }
+      include.push(!lingers);
+    }
+    let mut include = include.into_iter();
+    let (transactions, mempool) = self.mempool.drain(..).partition(|_| include.next().unwrap());
+    self.mempool = mempool;
+
+    let parent = self.blocks.last().unwrap();
+    let block = Block {
+      parent: parent.id(),
+      number: parent.number + 1,
+      time: parent.time + BLOCK_TIME,
+      fee: self.fee,
+      salt: self.mined,
+      transactions,
+    };
+    self.mined += 1;
+    self.blocks.push(block);
+  }
+}
+
+/// An in-memory coin, for testing the processor without any daemons.
+///
+/// Its chain can be scripted, with deposits, reorganizations, and fee changes all triggerable on
+/// demand. Clones share the same chain.
+#[derive(Clone, Debug)]
+pub struct Mock {
+  chain: Arc<Mutex<Chain>>,
+}
+// Shim required for testing/debugging purposes due to generic arguments also necessitating trait
+// bounds
+impl PartialEq for Mock {
+  fn eq(&self, _: &Self) -> bool {
+    true
+  }
+}
+impl Eq for Mock {}
+
+impl Mock {
+  pub fn new() -> Mock {
+    Mock { chain: Arc::new(Mutex::new(Chain::new())) }
+  }
+
+  fn change_address(key: Point) -> Address {
+    Address(key + (Ristretto::generator() * *CHANGE_OFFSET))
+  }
+
+  /// Deposit coins to an address, returning the ID of the depositing transaction.
+  ///
+  /// The deposit is placed in the mempool, and will be included in the next mined block.
+  pub fn deposit(&self, address: Address, amount: u64, data: Vec<u8>) -> [u8; 32] {
+    let mut chain = self.chain.lock().unwrap();
+    let tx = Transaction {
+      key: None,
+      inputs: vec![],
+      outputs: vec![TransactionOutput { address, amount, data }],
+      nonce: chain.deposits,
+      signature: None,
+    };
+    chain.deposits += 1;
+    let id = tx.id();
+    chain.mempool.push(tx);
+    id
+  }
+
+  /// Remove the latest `depth` blocks, returning their transactions to the mempool.
+  pub fn reorg(&self, depth: usize) {
+    let mut chain = self.chain.lock().unwrap();
+    assert!(depth < chain.blocks.len(), "reorganizing out the genesis block");
+    let mut orphaned = vec![];
+    for _ in 0 .. depth {
+      orphaned = [chain.blocks.pop().unwrap().transactions, orphaned].concat();
+    }
+    orphaned.append(&mut chain.mempool);
+    chain.mempool = orphaned;
+  }
+
+  /// Drop a transaction from the mempool, as if it was evicted, returning if it was present.
+  pub fn drop_transaction(&self, id: [u8; 32]) -> bool {
+    self.chain.lock().unwrap().remove_transaction(id)
+  }
+
+  /// Set the fee rate for blocks mined from now on.
+  pub fn set_fee(&self, fee: u64) {
+    self.chain.lock().unwrap().fee = fee;
+  }
+}
+
+impl Default for Mock {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[async_trait]
+impl Coin for Mock {
+  type Curve = Ristretto;
+
+  type Fee = Fee;
+  type Transaction = Transaction;
+  type Block = Block;
+
+  type Output = Output;
+  type SignableTransaction = SignableTransaction;
+  type Eventuality = Eventuality;
+  type TransactionMachine = TransactionMachine;
+
+  type Address = Address;
+
+  // The mock coin has to claim to be some network, and its amounts are denominated in Bitcoin
+  const NETWORK: NetworkId = NetworkId::Bitcoin;
+  const ID: &'static str = "Mock";
+  const CONFIRMATIONS: usize = 3;
+
+  const DUST: u64 = 10_000;
+
+  // Transactions paying too low a fee linger in the mempool until replaced
+  const FEE_BUMPS: bool = true;
+
+  // Intentionally small, so the scheduler's aggregation of inputs can be exercised
+  const MAX_INPUTS: usize = 16;
+  const MAX_OUTPUTS: usize = 16;
+
+  fn bump_fee(fee: Fee, bumps: u32) -> Fee {
+    Fee(fee.0 * 2u64.pow(bumps))
+  }
+
+  fn tweak_keys(_: &mut ThresholdKeys<Self::Curve>) {}
+
+  fn address(key: Point) -> Address {
+    Address(key)
+  }
+
+  fn branch_address(key: Point) -> Address {
+    Address(key + (Ristretto::generator() * *BRANCH_OFFSET))
+  }
+
+  async fn get_latest_block_number(&self) -> Result<usize, CoinError> {
+    Ok(self.chain.lock().unwrap().blocks.len() - 1)
+  }
+
+  async fn get_block(&self, number: usize) -> Result<Self::Block, CoinError> {
+    self.chain.lock().unwrap().blocks.get(number).cloned().ok_or(CoinError::ConnectionError)
+  }
+
+  async fn get_outputs(&self, block: &Block, key: Point) -> Result<Vec<Output>, CoinError> {
+    let kinds = [
+      (Self::address(key), OutputType::External),
+      (Self::branch_address(key), OutputType::Branch),
+      (Self::change_address(key), OutputType::Change),
+    ];
+
+    let mut outputs = vec![];
+    for tx in &block.transactions {
+      for (i, output) in tx.outputs.iter().enumerate() {
+        let Some((_, kind)) = kinds.iter().find(|(address, _)| address == &output.address) else {
+          continue;
+        };
+
+        let mut data = if *kind == OutputType::External { output.data.clone() } else { vec![] };
+        data.truncate(MAX_DATA_LEN.try_into().unwrap());
+
+        outputs.push(Output { id: tx.output_id(i), kind: *kind, amount: output.amount, data });
+      }
+    }
+    Ok(outputs)
+  }
+
+  async fn get_eventuality_completions(
+    &self,
+    eventualities: &mut EventualitiesTracker<Eventuality>,
+    block: &Block,
+  ) -> HashMap<[u8; 32], [u8; 32]> {
+    let mut res = HashMap::new();
+    // The scanner re-scans blocks which didn't have any outputs, which we may have already checked
+    if eventualities.map.is_empty() || (block.number <= eventualities.block_number) {
+      return res;
+    }
+
+    fn check_block(
+      eventualities: &mut EventualitiesTracker<Eventuality>,
+      block: &Block,
+      res: &mut HashMap<[u8; 32], [u8; 32]>,
+    ) {
+      for tx in &block.transactions {
+        let Some(input) = tx.inputs.first() else { continue };
+        if let Some((plan, eventuality)) = eventualities.map.remove(input.as_slice()) {
+          assert_eq!(input, &eventuality.0);
+          res.insert(plan, tx.id());
+        }
+      }
+
+      eventualities.block_number += 1;
+    }
+
+    let blocks = self.chain.lock().unwrap().blocks.clone();
+    for prior in &blocks[(eventualities.block_number + 1) .. block.number] {
+      check_block(eventualities, prior, &mut res);
+    }
+
+    // Also check the current block
+    check_block(eventualities, block, &mut res);
+    assert_eq!(eventualities.block_number, block.number);
+
+    res
+  }
+
+  async fn prepare_send(
+    &self,
+    keys: ThresholdKeys<Ristretto>,
+    _: usize,
+    mut plan: Plan<Self>,
+    fee: Fee,
+  ) -> Result<(Option<(SignableTransaction, Eventuality)>, Vec<PostFeeBranch>), CoinError> {
+    // Returns the transaction and the fee it pays, or None if there isn't enough value
+    let signable = |plan: &Plan<Self>, tx_fee: Option<u64>| {
+      assert!(!plan.inputs.is_empty(), "trying to create a mock transaction without inputs");
+
+      let mut outputs = vec![];
+      for payment in &plan.payments {
+        // If we're solely estimating the fee, don't specify the actual amount
+        // This won't affect the fee calculation yet will ensure we don't hit a not enough funds
+        // error
+        outputs.push(TransactionOutput {
+          address: payment.address,
+          amount: if tx_fee.is_none() { Self::DUST } else { payment.amount },
+          data: payment.data.clone().unwrap_or(vec![]),
+        });
+      }
+
+      let needed_fee = |outputs: usize| fee.0 * u64::try_from(plan.inputs.len() + outputs).unwrap();
+
+      let inputs = plan.inputs.iter().map(|input| input.amount).sum::<u64>();
+      let Some(available) =
+        inputs.checked_sub(outputs.iter().map(|output| output.amount).sum::<u64>())
+      else {
+        assert!(tx_fee.is_none(), "not enough funds for mock TX despite amortizing the fee");
+        return None;
+      };
+
+      let mut tx_fee = needed_fee(outputs.len());
+      if available < tx_fee {
+        return None;
+      }
+      // Only add a change output if it's worth more than dust after paying for itself
+      if let Some(change) = plan.change {
+        let with_change = needed_fee(outputs.len() + 1);
+        if available.saturating_sub(with_change) >= Self::DUST {
+          outputs.push(TransactionOutput {
+            address: Self::change_address(change),
+            amount: available - with_change,
+            data: vec![],
+          });
+          tx_fee = with_change;
+        }
+      }
+
+      // No outputs left and the change isn't worth enough
+      if outputs.is_empty() {
+        return None;
+      }
+
+      Some((
+        Transaction {
+          key: Some(plan.key),
+          inputs: plan.inputs.iter().map(|input| input.id).collect(),
+          outputs,
+          nonce: 0,
+          signature: None,
+        },
+        tx_fee,
+      ))
+    };
+
+    let tx_fee = match signable(&plan, None) {
+      Some((_, tx_fee)) => tx_fee,
+      None => return Ok((None, drop_branches(&plan))),
+    };
+
+    let branch_outputs = amortize_fee(&mut plan, tx_fee);
+
+    let Some((tx, _)) = signable(&plan, Some(tx_fee)) else {
+      return Ok((None, branch_outputs));
+    };
+    Ok((
+      Some((
+        SignableTransaction { keys, transcript: plan.transcript(), tx },
+        Eventuality(plan.inputs[0].id),
+      )),
+      branch_outputs,
+    ))
+  }
+
+  async fn attempt_send(
+    &self,
+    transaction: SignableTransaction,
+  ) -> Result<TransactionMachine, CoinError> {
+    Ok(TransactionMachine {
+      tx: transaction.tx,
+      machine: AlgorithmMachine::new(MockSchnorr::new(transaction.transcript), transaction.keys),
+    })
+  }
+
+  async fn publish_transaction(&self, tx: &Transaction) -> Result<(), CoinError> {
+    let mut chain = self.chain.lock().unwrap();
+    // Multiple signers will publish the same transaction
+    if chain.transaction(&tx.id()).is_some() {
+      return Ok(());
+    }
+
+    // A transaction spending the same outputs as transactions in the mempool replaces them if it
+    // pays a higher fee, as a bumped transaction would
+    let conflicts = chain
+      .mempool
+      .iter()
+      .filter(|existing| existing.inputs.iter().any(|input| tx.inputs.contains(input)))
+      .map(|existing| (existing.id(), chain.fee(existing)))
+      .collect::<Vec<_>>();
+    if !conflicts.is_empty() {
+      let fee = chain.fee(tx);
+      if conflicts.iter().any(|(_, existing)| *existing >= fee) {
+        Err(CoinError::ConnectionError)?;
+      }
+      for (conflict, _) in conflicts {
+        chain.remove_transaction(conflict);
+      }
+    }
+
+    if !chain.verify(tx) {
+      panic!("failed to publish TX {}", hex::encode(tx.id()));
+    }
+    chain.mempool.push(tx.clone());
+    Ok(())
+  }
+
+  async fn get_transaction(&self, id: &[u8; 32]) -> Result<Transaction, CoinError> {
+    self.chain.lock().unwrap().transaction(id).cloned().ok_or(CoinError::ConnectionError)
+  }
+
+  fn confirm_completion(&self, eventuality: &Eventuality, tx: &Transaction) -> bool {
+    tx.inputs.first() == Some(&eventuality.0)
+  }
+
+  #[cfg(test)]
+  async fn get_block_number(&self, id: &[u8; 32]) -> usize {
+    self.chain.lock().unwrap().blocks.iter().position(|block| &block.id() == id).unwrap()
+  }
+
+  #[cfg(test)]
+  async fn get_fee(&self) -> Self::Fee {
+    Fee(self.chain.lock().unwrap().fee)
+  }
+
+  #[cfg(test)]
+  async fn mine_block(&self) {
+    self.chain.lock().unwrap().mine();
+  }
+
+  #[cfg(test)]
+  async fn test_send(&self, address: Self::Address) -> Block {
+    self.deposit(address, 100_000_000, vec![]);
+    let block = self.get_latest_block_number().await.unwrap() + 1;
+    for _ in 0 .. Self::CONFIRMATIONS {
+      self.mine_block().await;
+    }
+    self.get_block(block).await.unwrap()
+  }
+}
@@ -12,6 +12,9 @@ use frost::{
 
 use serai_client::primitives::{NetworkId, Balance};
 
+mod pool;
+pub use pool::RpcPool;
+
 #[cfg(feature = "bitcoin")]
 pub mod bitcoin;
 #[cfg(feature = "bitcoin")]
@@ -22,6 +25,11 @@ pub mod monero;
 #[cfg(feature = "monero")]
 pub use monero::Monero;
 
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+pub use mock::Mock;
+
 use crate::Plan;
 
 #[derive(Clone, Copy, Error, Debug)]
@@ -111,6 +119,7 @@ pub trait Output: Send + Sync + Sized + Clone + PartialEq + Eq + Debug {
 pub trait Transaction<C: Coin>: Send + Sync + Sized + Clone + Debug {
   type Id: 'static + Id;
   fn id(&self) -> Self::Id;
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self>;
   fn serialize(&self) -> Vec<u8>;
 
   #[cfg(test)]
@@ -148,21 +157,6 @@ impl<E: Eventuality> EventualitiesTracker<E> {
     // If our self tracker already went past this block number, set it back
     self.block_number = self.block_number.min(block_number);
   }
-
-  pub fn drop(&mut self, id: [u8; 32]) {
-    // O(n) due to the lack of a reverse lookup
-    let mut found_key = None;
-    for (key, value) in &self.map {
-      if value.0 == id {
-        found_key = Some(key.clone());
-        break;
-      }
-    }
-
-    if let Some(key) = found_key {
-      self.map.remove(&key);
-    }
-  }
 }
 
 impl<E: Eventuality> Default for EventualitiesTracker<E> {
@@ -285,9 +279,19 @@ pub trait Coin: 'static + Send + Sync + Clone + PartialEq + Eq + Debug {
   /// Minimum output value which will be handled.
   const DUST: u64;
 
+  /// If a published transaction can be replaced by one paying a higher fee.
+  const FEE_BUMPS: bool;
+
   /// Tweak keys for this coin.
   fn tweak_keys(key: &mut ThresholdKeys<Self::Curve>);
 
+  /// The fee to re-sign a lingering transaction with, after bumping it the specified amount of
+  /// times.
+  ///
+  /// This is only called if FEE_BUMPS is set, and must increase the fee enough for the
+  /// re-signed transaction to replace the prior one.
+  fn bump_fee(fee: Self::Fee, bumps: u32) -> Self::Fee;
+
   /// Address for the given group key to receive external coins to.
   fn address(key: <Self::Curve as Ciphersuite>::G) -> Self::Address;
   /// Address for the given group key to use for scheduled branches.
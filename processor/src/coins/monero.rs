@@ -33,7 +33,7 @@ pub use serai_client::{
 use crate::{
   Payment, Plan, additional_key,
   coins::{
-    CoinError, Block as BlockTrait, OutputType, Output as OutputTrait,
+    CoinError, RpcPool, Block as BlockTrait, OutputType, Output as OutputTrait,
     Transaction as TransactionTrait, Eventuality as EventualityTrait, EventualitiesTracker,
     PostFeeBranch, Coin, drop_branches, amortize_fee,
   },
@@ -99,6 +99,9 @@ impl TransactionTrait<Monero> for Transaction {
   fn id(&self) -> Self::Id {
     self.hash()
   }
+  fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+    Transaction::read(reader)
+  }
   fn serialize(&self) -> Vec<u8> {
     self.serialize()
   }
@@ -158,7 +161,7 @@ impl BlockTrait<Monero> for Block {
 
 #[derive(Clone, Debug)]
 pub struct Monero {
-  rpc: Rpc,
+  rpc: RpcPool<Rpc>,
 }
 // Shim required for testing/debugging purposes due to generic arguments also necessitating trait
 // bounds
@@ -170,8 +173,11 @@ impl PartialEq for Monero {
 impl Eq for Monero {}
 
 impl Monero {
-  pub fn new(url: String) -> Monero {
-    Monero { rpc: Rpc::new(url).unwrap() }
+  /// Connect to multiple nodes, requiring `agreement` of them to agree on blocks.
+  pub fn with_endpoints(urls: Vec<String>, agreement: usize) -> Monero {
+    let rpc = RpcPool::new(urls.into_iter().map(|url| Rpc::new(url).unwrap()).collect(), agreement);
+    rpc.monitor(|rpc| async move { rpc.get_height().await });
+    Monero { rpc }
   }
 
   fn view_pair(spend: EdwardsPoint) -> ViewPair {
@@ -239,6 +245,14 @@ impl Coin for Monero {
   // 0.01 XMR
   const DUST: u64 = 10000000000;
 
+  // Monero's mempool rejects any transaction spending an already spent key image, regardless of
+  // its fee
+  const FEE_BUMPS: bool = false;
+
+  fn bump_fee(_: Fee, _: u32) -> Fee {
+    unreachable!("bumping the fee of a Monero transaction")
+  }
+
   // Monero doesn't require/benefit from tweaking
   fn tweak_keys(_: &mut ThresholdKeys<Self::Curve>) {}
 
@@ -252,12 +266,13 @@ impl Coin for Monero {
 
   async fn get_latest_block_number(&self) -> Result<usize, CoinError> {
     // Monero defines height as chain length, so subtract 1 for block number
-    Ok(self.rpc.get_height().await.map_err(|_| CoinError::ConnectionError)? - 1)
+    Ok(self.rpc.request(|rpc| async move { rpc.get_height().await }).await? - 1)
   }
 
   async fn get_block(&self, number: usize) -> Result<Self::Block, CoinError> {
-    let hash = self.rpc.get_block_hash(number).await.map_err(|_| CoinError::ConnectionError)?;
-    let block = self.rpc.get_block(hash).await.map_err(|_| CoinError::ConnectionError)?;
+    let hash = self.rpc.agreed(|rpc| async move { rpc.get_block_hash(number).await }).await?;
+    // The block isn't checked to have the requested hash, so its contents are also cross-checked
+    let block = self.rpc.agreed(|rpc| async move { rpc.get_block(hash).await }).await?;
     Ok(Block(hash, block))
   }
 
@@ -266,10 +281,10 @@ impl Coin for Monero {
     block: &Self::Block,
     key: EdwardsPoint,
   ) -> Result<Vec<Self::Output>, CoinError> {
-    let mut txs = Self::scanner(key)
-      .scan(&self.rpc, &block.1)
-      .await
-      .map_err(|_| CoinError::ConnectionError)?
+    let mut txs = self
+      .rpc
+      .request(|rpc| async move { Self::scanner(key).scan(&rpc, &block.1).await })
+      .await?
       .iter()
       .filter_map(|outputs| Some(outputs.not_locked()).filter(|outputs| !outputs.is_empty()))
       .collect::<Vec<_>>();
@@ -388,7 +403,7 @@ impl Coin for Monero {
     // determination
     // Just use whatever the node expects
     #[cfg(test)]
-    let protocol = self.rpc.get_protocol().await.unwrap();
+    let protocol = self.rpc.request(|rpc| async move { rpc.get_protocol().await }).await.unwrap();
 
     // Hedge against the above codegen failing by having an always included runtime check
     if !cfg!(test) {
@@ -396,7 +411,7 @@ impl Coin for Monero {
     }
 
     // Check a fork hasn't occurred which this processor hasn't been updated for
-    assert_eq!(protocol, self.rpc.get_protocol().await.map_err(|_| CoinError::ConnectionError)?);
+    assert_eq!(protocol, self.rpc.request(|rpc| async move { rpc.get_protocol().await }).await?);
 
     let signable = |plan: &mut Plan<Self>, tx_fee: Option<_>| {
       // Monero requires at least two outputs
@@ -498,23 +513,38 @@ impl Coin for Monero {
     &self,
     transaction: SignableTransaction,
   ) -> Result<Self::TransactionMachine, CoinError> {
-    transaction
-      .actual
-      .clone()
-      .multisig(
-        &self.rpc,
-        transaction.keys.clone(),
-        transaction.transcript.clone(),
-        transaction.height,
-      )
+    let transaction = &transaction;
+    self
+      .rpc
+      .request(|rpc| async move {
+        transaction
+          .actual
+          .clone()
+          .multisig(
+            &rpc,
+            transaction.keys.clone(),
+            transaction.transcript.clone(),
+            transaction.height,
+          )
+          .await
+      })
       .await
-      .map_err(|_| CoinError::ConnectionError)
   }
 
   async fn publish_transaction(&self, tx: &Self::Transaction) -> Result<(), CoinError> {
-    match self.rpc.publish_transaction(tx).await {
+    // Only fail over on connection errors, as any other error is from the transaction itself
+    let res = self
+      .rpc
+      .request(|rpc| async move {
+        match rpc.publish_transaction(tx).await {
+          Err(RpcError::ConnectionError) => Err(RpcError::ConnectionError),
+          res => Ok(res),
+        }
+      })
+      .await?;
+    match res {
       Ok(_) => Ok(()),
-      Err(RpcError::ConnectionError) => Err(CoinError::ConnectionError)?,
+      Err(RpcError::ConnectionError) => unreachable!("connection errors caused a failover"),
       // TODO: Distinguish already in pool vs double spend (other signing attempt succeeded) vs
       // invalid transaction
       Err(e) => panic!("failed to publish TX {}: {e}", hex::encode(tx.hash())),
@@ -522,7 +552,7 @@ impl Coin for Monero {
   }
 
   async fn get_transaction(&self, id: &[u8; 32]) -> Result<Transaction, CoinError> {
-    self.rpc.get_transaction(*id).await.map_err(|_| CoinError::ConnectionError)
+    self.rpc.request(|rpc| async move { rpc.get_transaction(*id).await }).await
   }
 
   fn confirm_completion(&self, eventuality: &Eventuality, tx: &Transaction) -> bool {
@@ -531,12 +561,12 @@ impl Coin for Monero {
 
   #[cfg(test)]
   async fn get_block_number(&self, id: &[u8; 32]) -> usize {
-    self.rpc.get_block(*id).await.unwrap().number()
+    self.rpc.request(|rpc| async move { rpc.get_block(*id).await }).await.unwrap().number()
   }
 
   #[cfg(test)]
   async fn get_fee(&self) -> Self::Fee {
-    self.rpc.get_fee().await.unwrap()
+    self.rpc.request(|rpc| async move { rpc.get_fee().await }).await.unwrap()
   }
 
   #[cfg(test)]
@@ -546,18 +576,22 @@ impl Coin for Monero {
 
     #[derive(serde::Deserialize, Debug)]
     struct EmptyResponse {}
-    let _: EmptyResponse = self
+    self
       .rpc
-      .rpc_call(
-        "json_rpc",
-        Some(serde_json::json!({
-          "method": "generateblocks",
-          "params": {
-            "wallet_address": Self::test_address().to_string(),
-            "amount_of_blocks": 1
-          },
-        })),
-      )
+      .request(|rpc| async move {
+        rpc
+          .rpc_call::<_, EmptyResponse>(
+            "json_rpc",
+            Some(serde_json::json!({
+              "method": "generateblocks",
+              "params": {
+                "wallet_address": Self::test_address().to_string(),
+                "amount_of_blocks": 1
+              },
+            })),
+          )
+          .await
+      })
       .await
       .unwrap();
   }
@@ -572,8 +606,11 @@ impl Coin for Monero {
       self.mine_block().await;
     }
 
-    let outputs = Self::test_scanner()
-      .scan(&self.rpc, &self.rpc.get_block_by_number(new_block).await.unwrap())
+    let outputs = self
+      .rpc
+      .request(|rpc| async move {
+        Self::test_scanner().scan(&rpc, &rpc.get_block_by_number(new_block).await?).await
+      })
       .await
       .unwrap()
       .swap_remove(0)
@@ -583,22 +620,28 @@ impl Coin for Monero {
     // The dust should always be sufficient for the fee
     let fee = Monero::DUST;
 
-    let tx = MSignableTransaction::new(
-      self.rpc.get_protocol().await.unwrap(),
+    let signable = MSignableTransaction::new(
+      self.rpc.request(|rpc| async move { rpc.get_protocol().await }).await.unwrap(),
       None,
       outputs,
       vec![(address.into(), amount - fee)],
       Some(Change::fingerprintable(Self::test_address().into())),
       vec![],
-      self.rpc.get_fee().await.unwrap(),
+      self.get_fee().await,
     )
-    .unwrap()
-    .sign(&mut OsRng, &self.rpc, &Zeroizing::new(Scalar::ONE.0))
-    .await
     .unwrap();
+    let signable = &signable;
+    let tx = self
+      .rpc
+      .request(|rpc| async move {
+        signable.clone().sign(&mut OsRng, &rpc, &Zeroizing::new(Scalar::ONE.0)).await
+      })
+      .await
+      .unwrap();
 
     let block = self.get_latest_block_number().await.unwrap() + 1;
-    self.rpc.publish_transaction(&tx).await.unwrap();
+    let tx_ref = &tx;
+    self.rpc.request(|rpc| async move { rpc.publish_transaction(tx_ref).await }).await.unwrap();
     for _ in 0 .. 10 {
       self.mine_block().await;
     }
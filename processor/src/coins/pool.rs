@@ -0,0 +1,145 @@
+use core::{fmt::Debug, future::Future, time::Duration};
+use std::sync::{
+  Arc,
+  atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use log::warn;
+
+use tokio::time::sleep;
+
+use crate::coins::CoinError;
+
+// How often to check the health of every endpoint
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A pool of RPC connections to distinct endpoints for the same coin.
+///
+/// Requests fail over to the next endpoint when the current one errors. Reads which the scanner
+/// relies upon can instead require multiple endpoints to agree on their result, preventing a single
+/// faulty node from misleading the scanner.
+#[derive(Clone, Debug)]
+pub struct RpcPool<R: Clone + Debug> {
+  rpcs: Vec<R>,
+  // How many endpoints must agree on a value for it to be accepted
+  agreement: usize,
+  // The endpoint requests are currently made to, which is the last endpoint to succeed
+  current: Arc<AtomicUsize>,
+  // Whether each endpoint succeeded on its last request
+  healthy: Arc<Vec<AtomicBool>>,
+}
+
+impl<R: Clone + Debug> RpcPool<R> {
+  pub fn new(rpcs: Vec<R>, agreement: usize) -> RpcPool<R> {
+    assert!(!rpcs.is_empty(), "creating an RPC pool without any endpoints");
+    assert!(
+      (agreement != 0) && (agreement <= rpcs.len()),
+      "RPC pool with {} endpoints required {agreement} to agree",
+      rpcs.len(),
+    );
+    let healthy = Arc::new(rpcs.iter().map(|_| AtomicBool::new(true)).collect());
+    RpcPool { rpcs, agreement, current: Arc::new(AtomicUsize::new(0)), healthy }
+  }
+
+  fn record<T, E: Debug>(&self, i: usize, res: &Result<T, E>) {
+    self.healthy[i].store(res.is_ok(), Ordering::Relaxed);
+    if let Err(e) = res {
+      warn!("RPC endpoint #{i} errored: {e:?}");
+    }
+  }
+
+  /// Whether an endpoint succeeded on its last request.
+  pub fn healthy(&self, i: usize) -> bool {
+    self.healthy[i].load(Ordering::Relaxed)
+  }
+
+  /// Perform a request, failing over to the next endpoint whenever one errors.
+  ///
+  /// Healthy endpoints are tried before unhealthy ones. Only errors if every endpoint errored.
+  pub async fn request<T, E: Debug, F: Future<Output = Result<T, E>>>(
+    &self,
+    request: impl Fn(R) -> F,
+  ) -> Result<T, CoinError> {
+    let start = self.current.load(Ordering::Relaxed);
+    let (healthy, unhealthy): (Vec<_>, Vec<_>) = (0 .. self.rpcs.len())
+      .map(|offset| (start + offset) % self.rpcs.len())
+      .partition(|i| self.healthy(*i));
+    for i in healthy.into_iter().chain(unhealthy) {
+      let res = request(self.rpcs[i].clone()).await;
+      self.record(i, &res);
+      if let Ok(res) = res {
+        self.current.store(i, Ordering::Relaxed);
+        return Ok(res);
+      }
+    }
+    Err(CoinError::ConnectionError)
+  }
+
+  /// Perform a request against every endpoint, only returning a result if enough endpoints
+  /// agreed upon it.
+  pub async fn agreed<T: PartialEq, E: Debug, F: Future<Output = Result<T, E>>>(
+    &self,
+    request: impl Fn(R) -> F,
+  ) -> Result<T, CoinError> {
+    let mut results: Vec<(T, usize)> = vec![];
+    for (i, rpc) in self.rpcs.iter().enumerate() {
+      let res = request(rpc.clone()).await;
+      self.record(i, &res);
+      if let Ok(res) = res {
+        if let Some((_, count)) = results.iter_mut().find(|(existing, _)| existing == &res) {
+          *count += 1;
+        } else {
+          results.push((res, 1));
+        }
+      }
+    }
+
+    if results.len() > 1 {
+      warn!("RPC endpoints returned {} distinct results for the same request", results.len());
+    }
+    results
+      .into_iter()
+      .find(|(_, count)| *count >= self.agreement)
+      .map(|(res, _)| res)
+      .ok_or(CoinError::ConnectionError)
+  }
+
+  /// Check the health of every endpoint, returning how many are healthy.
+  pub async fn check_health<T, E: Debug, F: Future<Output = Result<T, E>>>(
+    &self,
+    check: impl Fn(R) -> F,
+  ) -> usize {
+    let mut healthy = 0;
+    for (i, rpc) in self.rpcs.iter().enumerate() {
+      let res = check(rpc.clone()).await;
+      self.record(i, &res);
+      healthy += usize::from(res.is_ok());
+    }
+    if healthy < self.agreement {
+      warn!(
+        "only {healthy} of {} RPC endpoints are healthy, yet {} must agree",
+        self.rpcs.len(),
+        self.agreement
+      );
+    }
+    healthy
+  }
+
+  /// Spawn a task which periodically checks the health of every endpoint, so unhealthy endpoints
+  /// which recover are preferred again.
+  pub fn monitor<T, E, F>(&self, check: impl 'static + Send + Sync + Fn(R) -> F)
+  where
+    R: 'static + Send + Sync,
+    T: Send,
+    E: Debug + Send,
+    F: Send + Future<Output = Result<T, E>>,
+  {
+    let pool = self.clone();
+    tokio::spawn(async move {
+      loop {
+        pool.check_health(&check).await;
+        sleep(HEALTH_CHECK_INTERVAL).await;
+      }
+    });
+  }
+}
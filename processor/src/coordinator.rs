@@ -1,14 +1,98 @@
 use std::{
   sync::{Arc, RwLock},
-  collections::VecDeque,
+  collections::{VecDeque, HashSet},
 };
 
+use thiserror::Error;
+
+use frost::curve::{Ciphersuite, Ristretto};
+use schnorr::SchnorrSignature;
+
+use serai_client::{primitives::NetworkId, validator_sets::primitives::ValidatorSet};
+
 use messages::{ProcessorMessage, CoordinatorMessage};
 
+/// An error from authenticating a message from the coordinator.
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum AuthenticationError {
+  #[error("message was for a key we don't have")]
+  UnrecognizedKey,
+  #[error("message wasn't signed by the coordinator")]
+  InvalidSignature,
+  #[error("message was for a validator set on another network ({0:?})")]
+  WrongNetwork(NetworkId),
+}
+
+/// A message from the coordinator, signed by the coordinator's key.
+///
+/// The signature is over the message's challenge (see `CoordinatorMessage::challenge`), binding
+/// the validator set the message is for and the message's ID.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Message {
   pub id: u64,
   pub msg: CoordinatorMessage,
+  pub signature: SchnorrSignature<Ristretto>,
+}
+
+impl Message {
+  /// Authenticate this message was sent by the coordinator to the processor for this network.
+  ///
+  /// `key_set` returns the validator set a key is for, if it's one of the keys this processor has
+  /// confirmed.
+  pub fn authenticate(
+    &self,
+    coordinator: <Ristretto as Ciphersuite>::G,
+    network: NetworkId,
+    key_set: impl Fn(&[u8]) -> Option<ValidatorSet>,
+  ) -> Result<(), AuthenticationError> {
+    let set = self.msg.validator_set(key_set).ok_or(AuthenticationError::UnrecognizedKey)?;
+    let challenge = self.msg.challenge(coordinator, self.signature.R, set, self.id);
+    if !self.signature.verify(coordinator, challenge) {
+      Err(AuthenticationError::InvalidSignature)?;
+    }
+    if set.network != network {
+      Err(AuthenticationError::WrongNetwork(set.network))?;
+    }
+    Ok(())
+  }
+}
+
+/// The IDs of the messages received from the coordinator, which are expected to be sequential.
+///
+/// Rejected messages are noted so the messages after them are still considered in order. Since a
+/// rejected message may not have been authenticated, its ID is only used for this check.
+#[derive(Default, Debug)]
+pub struct MessageIds {
+  last: Option<u64>,
+  rejected: HashSet<u64>,
+}
+
+impl MessageIds {
+  /// Note a message was rejected.
+  pub fn reject(&mut self, id: u64) {
+    if self.last.map(|last| id > last).unwrap_or(true) {
+      self.rejected.insert(id);
+    }
+  }
+
+  /// Note an authenticated message was received, returning false if it was already received.
+  ///
+  /// Panics if a message between the last message and this one was neither received nor rejected,
+  /// as the coordinator is expected to deliver every message in order.
+  pub fn receive(&mut self, id: u64) -> bool {
+    if let Some(last) = self.last {
+      if id <= last {
+        return false;
+      }
+      assert!(
+        ((last + 1) .. id).all(|skipped| self.rejected.contains(&skipped)),
+        "coordinator skipped messages between {last} and {id}"
+      );
+    }
+    self.rejected.retain(|rejected| *rejected > id);
+    self.last = Some(id);
+    true
+  }
 }
 
 #[async_trait::async_trait]
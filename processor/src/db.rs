@@ -2,6 +2,8 @@ use core::marker::PhantomData;
 
 pub use serai_db::*;
 
+use messages::coordinator::{CoordinatorMessage, ProcessorMessage};
+
 use crate::{Plan, coins::Coin};
 
 #[derive(Debug)]
@@ -25,6 +27,68 @@ impl<C: Coin, D: Db> MainDb<C, D> {
     txn.put(Self::handled_key(id), [])
   }
 
+  fn substrate_block_key(key: &[u8]) -> Vec<u8> {
+    Self::main_key(b"substrate_block", key)
+  }
+  // The number of the last Substrate block whose burns were scheduled with this key
+  pub fn last_substrate_block(&self, key: &[u8]) -> Option<u64> {
+    self
+      .0
+      .get(Self::substrate_block_key(key))
+      .map(|block| u64::from_le_bytes(block.try_into().unwrap()))
+  }
+  pub fn save_substrate_block(txn: &mut D::Transaction<'_>, key: &[u8], block: u64) {
+    txn.put(Self::substrate_block_key(key), block.to_le_bytes());
+  }
+
+  // Where an unacknowledged message is saved
+  // Only the latest cursor for a key is kept, as it supersedes any prior cursors
+  fn unacknowledged_key(msg: &ProcessorMessage) -> Vec<u8> {
+    match msg {
+      ProcessorMessage::ScannerCursor { key, .. } => {
+        Self::main_key(b"unacknowledged", [b"cursor".as_ref(), key].concat())
+      }
+      _ => Self::main_key(b"unacknowledged", bincode::serialize(msg).unwrap()),
+    }
+  }
+  // Save a message which will be resent on boot until the coordinator acknowledges it
+  pub fn save_unacknowledged(txn: &mut D::Transaction<'_>, msg: &ProcessorMessage) {
+    assert!(msg.ack().is_some(), "saving a message which won't be acknowledged");
+    txn.put(Self::unacknowledged_key(msg), bincode::serialize(msg).unwrap());
+  }
+  pub fn unacknowledged(&self) -> Vec<ProcessorMessage> {
+    self
+      .0
+      .get_prefix(Self::main_key(b"unacknowledged", []))
+      .into_iter()
+      .map(|(_, msg)| bincode::deserialize(&msg).unwrap())
+      .collect()
+  }
+  // Handle an acknowledgement from the coordinator, returning if it was expected
+  pub fn acknowledge(txn: &mut D::Transaction<'_>, ack: &CoordinatorMessage) -> bool {
+    let msg = match ack.clone() {
+      CoordinatorMessage::BatchSignedAck { key, id } => ProcessorMessage::BatchSigned { key, id },
+      CoordinatorMessage::TransactionSignedAck { key, id } => {
+        ProcessorMessage::TransactionSigned { key, id }
+      }
+      CoordinatorMessage::ScannerCursorAck { key, block } => {
+        ProcessorMessage::ScannerCursor { key, block }
+      }
+      _ => panic!("acknowledging a message which isn't an acknowledgement"),
+    };
+
+    // If the saved message differs (an older cursor being acknowledged after a newer cursor was
+    // sent), leave it to be resent
+    let key = Self::unacknowledged_key(&msg);
+    if txn.get(&key).map(|saved| bincode::deserialize::<ProcessorMessage>(&saved).unwrap()) !=
+      Some(msg)
+    {
+      return false;
+    }
+    txn.del(key);
+    true
+  }
+
   fn plan_key(id: &[u8]) -> Vec<u8> {
     Self::main_key(b"plan", id)
   }
@@ -46,7 +110,7 @@ impl<C: Coin, D: Db> MainDb<C, D> {
       }
 
       signing.extend(&id);
-      txn.put(Self::signing_key(key), id);
+      txn.put(Self::signing_key(key), signing);
     }
 
     {
@@ -56,19 +120,23 @@ impl<C: Coin, D: Db> MainDb<C, D> {
     }
   }
 
+  // The block number a plan was prepared as of, along with the plan itself
+  pub fn plan(&self, id: &[u8]) -> Option<(u64, Plan<C>)> {
+    let buf = self.0.get(Self::plan_key(id))?;
+
+    let block_number = u64::from_le_bytes(buf[.. 8].try_into().unwrap());
+    let plan = Plan::<C>::read::<&[u8]>(&mut &buf[8 ..]).unwrap();
+    assert_eq!(id, &plan.id());
+    Some((block_number, plan))
+  }
+
   pub fn signing(&self, key: &[u8]) -> Vec<(u64, Plan<C>)> {
     let signing = self.0.get(Self::signing_key(key)).unwrap_or(vec![]);
     let mut res = vec![];
 
     assert_eq!(signing.len() % 32, 0);
     for i in 0 .. (signing.len() / 32) {
-      let id = &signing[(i * 32) .. ((i + 1) * 32)];
-      let buf = self.0.get(Self::plan_key(id)).unwrap();
-
-      let block_number = u64::from_le_bytes(buf[.. 8].try_into().unwrap());
-      let plan = Plan::<C>::read::<&[u8]>(&mut &buf[16 ..]).unwrap();
-      assert_eq!(id, &plan.id());
-      res.push((block_number, plan));
+      res.push(self.plan(&signing[(i * 32) .. ((i + 1) * 32)]).unwrap());
     }
 
     res
@@ -81,7 +149,7 @@ impl<C: Coin, D: Db> MainDb<C, D> {
     let mut found = false;
     for i in 0 .. (signing.len() / 32) {
       let start = i * 32;
-      let end = i + 32;
+      let end = start + 32;
       if signing[start .. end] == id {
         found = true;
         signing = [&signing[.. start], &signing[end ..]].concat().to_vec();
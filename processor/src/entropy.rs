@@ -0,0 +1,69 @@
+use std::{env, fs, path::PathBuf};
+
+use zeroize::{Zeroize, Zeroizing};
+
+use transcript::{Transcript, RecommendedTranscript};
+
+// Parse a hex-encoded 32-byte seed
+fn parse_seed(hex: &Zeroizing<String>) -> Zeroizing<[u8; 32]> {
+  let hex = hex.trim();
+  if hex.len() != 64 {
+    panic!("entropy isn't the right length");
+  }
+  let bytes = Zeroizing::new(hex::decode(hex).expect("entropy wasn't hex-formatted"));
+  let mut seed = Zeroizing::new([0; 32]);
+  let seed_mut: &mut [u8] = seed.as_mut();
+  seed_mut.copy_from_slice(bytes.as_ref());
+  seed
+}
+
+/// A provider of the processor's root secret, which all of the processor's entropy is derived
+/// from.
+pub trait SeedProvider {
+  /// The root secret.
+  fn seed(&self) -> Zeroizing<[u8; 32]>;
+
+  /// Entropy for a specific subsystem, domain separated by the label.
+  fn entropy(&self, label: &'static [u8]) -> Zeroizing<[u8; 32]> {
+    let mut transcript = RecommendedTranscript::new(b"Serai Processor Entropy");
+    transcript.append_message(b"entropy", self.seed());
+
+    let mut challenge = transcript.challenge(label);
+    let mut res = Zeroizing::new([0; 32]);
+    let res_mut: &mut [u8] = res.as_mut();
+    res_mut.copy_from_slice(&challenge[.. 32]);
+    challenge.zeroize();
+    res
+  }
+}
+
+/// Read the seed, hex-encoded, from the ENTROPY environment variable.
+#[derive(Clone, Copy, Debug)]
+pub struct EnvSeedProvider;
+impl SeedProvider for EnvSeedProvider {
+  fn seed(&self) -> Zeroizing<[u8; 32]> {
+    parse_seed(&Zeroizing::new(env::var("ENTROPY").expect("entropy wasn't provided as an env var")))
+  }
+}
+
+/// Read the seed, hex-encoded, from a file.
+#[derive(Clone, Debug)]
+pub struct FileSeedProvider(pub PathBuf);
+impl SeedProvider for FileSeedProvider {
+  fn seed(&self) -> Zeroizing<[u8; 32]> {
+    parse_seed(&Zeroizing::new(
+      fs::read_to_string(&self.0).expect("couldn't read the file containing the entropy"),
+    ))
+  }
+}
+
+/// The seed provider configured for this processor.
+///
+/// If ENTROPY_FILE is set, the seed is read from the specified file. Else, it's read from the
+/// ENTROPY environment variable.
+pub fn seed_provider() -> Box<dyn SeedProvider> {
+  match env::var("ENTROPY_FILE") {
+    Ok(path) => Box::new(FileSeedProvider(path.into())),
+    Err(_) => Box::new(EnvSeedProvider),
+  }
+}
@@ -13,7 +13,7 @@ use frost::{
   dkg::{Participant, ThresholdParams, ThresholdCore, ThresholdKeys, encryption::*, frost::*},
 };
 
-use log::info;
+use log::{info, warn};
 
 use serai_client::validator_sets::primitives::{ValidatorSet, KeyPair};
 use messages::key_gen::*;
@@ -36,14 +36,41 @@ impl<C: Coin, D: Db> KeyGenDb<C, D> {
   fn params_key(set: &ValidatorSet) -> Vec<u8> {
     Self::key_gen_key(b"params", bincode::serialize(set).unwrap())
   }
-  fn save_params(txn: &mut D::Transaction<'_>, set: &ValidatorSet, params: &ThresholdParams) {
-    txn.put(Self::params_key(set), bincode::serialize(params).unwrap());
+  fn save_params(
+    txn: &mut D::Transaction<'_>,
+    set: &ValidatorSet,
+    params: &ThresholdParams,
+    serai_genesis: &[u8; 32],
+  ) {
+    txn.put(Self::params_key(set), bincode::serialize(&(params, serai_genesis)).unwrap());
   }
-  fn params<G: Get>(getter: &G, set: &ValidatorSet) -> ThresholdParams {
+  fn params<G: Get>(getter: &G, set: &ValidatorSet) -> (ThresholdParams, [u8; 32]) {
     // Directly unwraps the .get() as this will only be called after being set
     bincode::deserialize(&getter.get(Self::params_key(set)).unwrap()).unwrap()
   }
 
+  // Keyed by the set, then the attempt in big endian, so iterating the set's prefix yields its
+  // attempts in order
+  fn attempt_key(id: &KeyGenId) -> Vec<u8> {
+    Self::key_gen_key(
+      b"attempt",
+      [bincode::serialize(&id.set).unwrap(), id.attempt.to_be_bytes().to_vec()].concat(),
+    )
+  }
+  fn save_attempt(txn: &mut D::Transaction<'_>, id: &KeyGenId) {
+    txn.put(Self::attempt_key(id), id.attempt.to_le_bytes());
+  }
+  fn attempts<G: Get>(getter: &G, set: &ValidatorSet) -> Vec<KeyGenId> {
+    getter
+      .get_prefix(Self::key_gen_key(b"attempt", bincode::serialize(set).unwrap()))
+      .into_iter()
+      .map(|(_, attempt)| KeyGenId {
+        set: *set,
+        attempt: u32::from_le_bytes(attempt.try_into().unwrap()),
+      })
+      .collect()
+  }
+
   // Not scoped to the set since that'd have latter attempts overwrite former
   // A former attempt may become the finalized attempt, even if it doesn't in a timely manner
   // Overwriting its commitments would be accordingly poor
@@ -64,6 +91,67 @@ impl<C: Coin, D: Db> KeyGenDb<C, D> {
     .unwrap()
   }
 
+  // Commitments and shares delivered individually, keyed by the KeyGenId and then the stage
+  // (commitments or shares)
+  fn partial_key(dst: &'static [u8], id: &KeyGenId, stage: &'static [u8]) -> Vec<u8> {
+    Self::key_gen_key(dst, [bincode::serialize(id).unwrap(), stage.to_vec()].concat())
+  }
+  // Accumulate a participant's data, or the coordinator marking the stage complete if None
+  //
+  // Returns every other participant's data once all of it has been received and the stage has been
+  // marked complete. This only happens once per stage, with any messages afterwards ignored
+  fn accumulate(
+    txn: &mut D::Transaction<'_>,
+    stage: &'static [u8],
+    id: &KeyGenId,
+    data: Option<(Participant, Vec<u8>)>,
+  ) -> Option<HashMap<Participant, Vec<u8>>> {
+    let stage_str = String::from_utf8_lossy(stage);
+    let dispatched_key = Self::partial_key(b"dispatched", id, stage);
+    if txn.get(&dispatched_key).is_some() {
+      info!("ignoring {} for {:?} as they were already handled", stage_str, id);
+      return None;
+    }
+
+    let params = Self::params(txn, &id.set).0;
+    let key = Self::partial_key(b"partial", id, stage);
+    let complete_key = Self::partial_key(b"complete", id, stage);
+    let mut accumulated: HashMap<Participant, Vec<u8>> =
+      txn.get(&key).map(|bytes| bincode::deserialize(&bytes).unwrap()).unwrap_or_default();
+    match data {
+      Some((participant, data)) => {
+        if (participant == params.i()) || (u16::from(participant) > params.n()) {
+          warn!("received {} for {:?} from invalid participant {:?}", stage_str, id, participant);
+          return None;
+        }
+        if let Some(existing) = accumulated.get(&participant) {
+          // A participant resending distinct data is faulty, so we ignore the resent data
+          if existing != &data {
+            warn!("participant {:?} sent distinct {} for {:?}", participant, stage_str, id);
+          }
+          return None;
+        }
+        accumulated.insert(participant, data);
+        txn.put(&key, bincode::serialize(&accumulated).unwrap());
+      }
+      None => txn.put(&complete_key, []),
+    }
+
+    // Wait for every participant other than us, and for the coordinator to mark the stage complete
+    let received = (1 ..= params.n())
+      .map(|i| Participant::new(i).unwrap())
+      .filter(|i| *i != params.i())
+      .all(|i| accumulated.contains_key(&i));
+    if !received || txn.get(&complete_key).is_none() {
+      return None;
+    }
+
+    txn.del(key);
+    txn.del(complete_key);
+    txn.put(dispatched_key, []);
+    Some(accumulated)
+  }
+
   fn generated_keys_key(set: ValidatorSet, key_pair: (&[u8], &[u8])) -> Vec<u8> {
     Self::key_gen_key(b"generated_keys", bincode::serialize(&(set, key_pair)).unwrap())
   }
@@ -117,8 +205,40 @@ impl<C: Coin, D: Db> KeyGenDb<C, D> {
       keys.1.group_key().to_bytes().as_ref(),
     );
     txn.put(Self::keys_key(&keys.1.group_key()), keys_vec);
+    txn.put(Self::confirmed_key(&set), [key_pair.0.as_ref(), key_pair.1.as_ref()].concat());
+    for key in [key_pair.0.as_ref(), key_pair.1.as_ref()] {
+      txn.put(Self::key_set_key(key), bincode::serialize(&set).unwrap());
+    }
+
+    // Now that the keys are confirmed, the commitments/shares from every attempt are unnecessary
+    // Since these are keyed by the KeyGenId, which starts with the set, prune them by prefix
+    // The markers for which stages were handled are kept so late messages continue to be ignored
+    let set = bincode::serialize(&set).unwrap();
+    for dst in [b"commitments".as_ref(), b"partial", b"complete"] {
+      txn.del_prefix(Self::key_gen_key(dst, &set));
+    }
+
     keys
   }
+  // The set each confirmed key, both Substrate and coin, is for
+  fn key_set_key(key: &[u8]) -> Vec<u8> {
+    Self::key_gen_key(b"key_set", key)
+  }
+  fn key_set<G: Get>(getter: &G, key: &[u8]) -> Option<ValidatorSet> {
+    getter.get(Self::key_set_key(key)).map(|set| bincode::deserialize(&set).unwrap())
+  }
+
+  fn confirmed_key(set: &ValidatorSet) -> Vec<u8> {
+    Self::key_gen_key(b"confirmed", bincode::serialize(set).unwrap())
+  }
+  fn confirmed<G: Get>(getter: &G, set: &ValidatorSet) -> Option<KeyPair> {
+    getter.get(Self::confirmed_key(set)).map(|key_pair| {
+      (
+        sp_application_crypto::sr25519::Public(key_pair[.. 32].try_into().unwrap()),
+        key_pair[32 ..].to_vec().try_into().unwrap(),
+      )
+    })
+  }
   fn keys<G: Get>(
     getter: &G,
     key: &<C::Curve as Ciphersuite>::G,
@@ -161,38 +281,88 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
     KeyGenDb::<C, D>::keys(&self.db, key)
   }
 
+  /// The validator set a confirmed key, either Substrate or coin, is for.
+  pub fn key_set(&self, key: &[u8]) -> Option<ValidatorSet> {
+    // This is safe, despite not having a txn, for the same reasons as keys
+    KeyGenDb::<C, D>::key_set(&self.db, key)
+  }
+
+  // The history of a set's key gen is saved for inspection, yet the processor itself never needs
+  // it. These expose it for inspection, though only the tests currently do so
+
+  /// Every attempt started for this set, in order.
+  #[allow(dead_code)]
+  pub fn attempts(&self, set: ValidatorSet) -> Vec<KeyGenId> {
+    KeyGenDb::<C, D>::attempts(&self.db, &set)
+  }
+
+  /// The key pair confirmed for this set, if one has been.
+  #[allow(dead_code)]
+  pub fn latest_confirmed_key(&self, set: ValidatorSet) -> Option<KeyPair> {
+    KeyGenDb::<C, D>::confirmed(&self.db, &set)
+  }
+
   pub async fn handle(
     &mut self,
     txn: &mut D::Transaction<'_>,
     msg: CoordinatorMessage,
-  ) -> ProcessorMessage {
-    let context = |id: &KeyGenId| {
-      // TODO2: Also embed the chain ID/genesis block
+  ) -> Option<ProcessorMessage> {
+    // Accumulate commitments/shares delivered individually, only continuing once we've received
+    // every other participant's and the coordinator has marked them complete
+    let msg = match msg {
+      CoordinatorMessage::Commitment { id, participant, commitments } => {
+        let data = Some((participant, commitments));
+        let commitments = KeyGenDb::<C, D>::accumulate(txn, b"commitments", &id, data)?;
+        CoordinatorMessage::Commitments { id, commitments }
+      }
+      CoordinatorMessage::CommitmentsComplete { id } => {
+        let commitments = KeyGenDb::<C, D>::accumulate(txn, b"commitments", &id, None)?;
+        CoordinatorMessage::Commitments { id, commitments }
+      }
+      CoordinatorMessage::Share { id, participant, share } => {
+        let shares = KeyGenDb::<C, D>::accumulate(txn, b"shares", &id, Some((participant, share)))?;
+        CoordinatorMessage::Shares { id, shares }
+      }
+      CoordinatorMessage::SharesComplete { id } => {
+        let shares = KeyGenDb::<C, D>::accumulate(txn, b"shares", &id, None)?;
+        CoordinatorMessage::Shares { id, shares }
+      }
+      msg => msg,
+    };
+
+    // Binding the Serai genesis prevents keys generated for one Serai network (such as a testnet)
+    // from being replayed on another
+    let context = |id: &KeyGenId, serai_genesis: &[u8; 32]| {
       format!(
-        "Serai Key Gen. Session: {:?}, Network: {:?}, Attempt: {}",
-        id.set.session, id.set.network, id.attempt
+        "Serai Key Gen. Serai Genesis: {}, Session: {:?}, Network: {:?}, Coin: {}, Attempt: {}",
+        hex::encode(serai_genesis),
+        id.set.session,
+        id.set.network,
+        C::ID,
+        id.attempt
       )
     };
 
-    let rng = |label, id: KeyGenId| {
+    let rng = |label, id: KeyGenId, serai_genesis: [u8; 32]| {
       let mut transcript = RecommendedTranscript::new(label);
       transcript.append_message(b"entropy", &self.entropy);
-      transcript.append_message(b"context", context(&id));
+      transcript.append_message(b"context", context(&id, &serai_genesis));
       ChaCha20Rng::from_seed(transcript.rng_seed(b"rng"))
     };
-    let coefficients_rng = |id| rng(b"Key Gen Coefficients", id);
-    let secret_shares_rng = |id| rng(b"Key Gen Secret Shares", id);
-    let share_rng = |id| rng(b"Key Gen Share", id);
-
-    let key_gen_machines = |id, params| {
-      let mut rng = coefficients_rng(id);
-      let substrate = KeyGenMachine::new(params, context(&id)).generate_coefficients(&mut rng);
-      let coin = KeyGenMachine::new(params, context(&id)).generate_coefficients(&mut rng);
+    let coefficients_rng = |id, serai_genesis| rng(b"Key Gen Coefficients", id, serai_genesis);
+    let secret_shares_rng = |id, serai_genesis| rng(b"Key Gen Secret Shares", id, serai_genesis);
+    let share_rng = |id, serai_genesis| rng(b"Key Gen Share", id, serai_genesis);
+
+    let key_gen_machines = |id, params, serai_genesis| {
+      let mut rng = coefficients_rng(id, serai_genesis);
+      let context = context(&id, &serai_genesis);
+      let substrate = KeyGenMachine::new(params, context.clone()).generate_coefficients(&mut rng);
+      let coin = KeyGenMachine::new(params, context).generate_coefficients(&mut rng);
       ((substrate.0, coin.0), (substrate.1, coin.1))
     };
 
     match msg {
-      CoordinatorMessage::GenerateKey { id, params } => {
+      CoordinatorMessage::GenerateKey { id, params, serai_genesis } => {
         info!("Generating new key. ID: {:?} Params: {:?}", id, params);
 
         // Remove old attempts
@@ -200,15 +370,16 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
           self.active_share.remove(&id.set).is_none()
         {
           // If we haven't handled this set before, save the params
-          KeyGenDb::<C, D>::save_params(txn, &id.set, &params);
+          KeyGenDb::<C, D>::save_params(txn, &id.set, &params, &serai_genesis);
         }
+        KeyGenDb::<C, D>::save_attempt(txn, &id);
 
-        let (machines, commitments) = key_gen_machines(id, params);
+        let (machines, commitments) = key_gen_machines(id, params, serai_genesis);
         let mut serialized = commitments.0.serialize();
         serialized.extend(commitments.1.serialize());
         self.active_commit.insert(id.set, machines);
 
-        ProcessorMessage::Commitments { id, commitments: serialized }
+        Some(ProcessorMessage::Commitments { id, commitments: serialized })
       }
 
       CoordinatorMessage::Commitments { id, commitments } => {
@@ -221,17 +392,19 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
           panic!("commitments when already handled commitments");
         }
 
-        let params = KeyGenDb::<C, D>::params(txn, &id.set);
+        let (params, serai_genesis) = KeyGenDb::<C, D>::params(txn, &id.set);
 
         // Unwrap the machines, rebuilding them if we didn't have them in our cache
         // We won't if the processor rebooted
         // This *may* be inconsistent if we receive a KeyGen for attempt x, then commitments for
         // attempt y
         // The coordinator is trusted to be proper in this regard
-        let machines =
-          self.active_commit.remove(&id.set).unwrap_or_else(|| key_gen_machines(id, params).0);
+        let machines = self
+          .active_commit
+          .remove(&id.set)
+          .unwrap_or_else(|| key_gen_machines(id, params, serai_genesis).0);
 
-        let mut rng = secret_shares_rng(id);
+        let mut rng = secret_shares_rng(id, serai_genesis);
 
         let mut commitments_ref: HashMap<Participant, &[u8]> =
           commitments.iter().map(|(i, commitments)| (*i, commitments.as_ref())).collect();
@@ -283,18 +456,18 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
 
         KeyGenDb::<C, D>::save_commitments(txn, &id, &commitments);
 
-        ProcessorMessage::Shares { id, shares }
+        Some(ProcessorMessage::Shares { id, shares })
       }
 
       CoordinatorMessage::Shares { id, shares } => {
         info!("Received shares for {:?}", id);
 
-        let params = KeyGenDb::<C, D>::params(txn, &id.set);
+        let (params, serai_genesis) = KeyGenDb::<C, D>::params(txn, &id.set);
 
         // Same commentary on inconsistency as above exists
         let machines = self.active_share.remove(&id.set).unwrap_or_else(|| {
-          let machines = key_gen_machines(id, params).0;
-          let mut rng = secret_shares_rng(id);
+          let machines = key_gen_machines(id, params, serai_genesis).0;
+          let mut rng = secret_shares_rng(id, serai_genesis);
           let commitments = KeyGenDb::<C, D>::commitments(txn, &id);
 
           let mut commitments_ref: HashMap<Participant, &[u8]> =
@@ -326,7 +499,7 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
           )
         });
 
-        let mut rng = share_rng(id);
+        let mut rng = share_rng(id, serai_genesis);
 
         let mut shares_ref: HashMap<Participant, &[u8]> =
           shares.iter().map(|(i, shares)| (*i, shares.as_ref())).collect();
@@ -371,11 +544,18 @@ impl<C: Coin, D: Db> KeyGen<C, D> {
 
         KeyGenDb::<C, D>::save_keys(txn, &id, &substrate_keys, &coin_keys);
 
-        ProcessorMessage::GeneratedKeyPair {
+        Some(ProcessorMessage::GeneratedKeyPair {
           id,
           substrate_key: substrate_keys.group_key().to_bytes(),
           coin_key: coin_keys.group_key().to_bytes().as_ref().to_vec(),
-        }
+        })
+      }
+
+      CoordinatorMessage::Commitment { .. } |
+      CoordinatorMessage::CommitmentsComplete { .. } |
+      CoordinatorMessage::Share { .. } |
+      CoordinatorMessage::SharesComplete { .. } => {
+        unreachable!("individual commitments/shares weren't accumulated")
       }
     }
   }
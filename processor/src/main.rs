@@ -4,23 +4,19 @@ use std::{
   collections::{VecDeque, HashMap},
 };
 
-use zeroize::{Zeroize, Zeroizing};
-
-use transcript::{Transcript, RecommendedTranscript};
 use group::GroupEncoding;
-use frost::{curve::Ciphersuite, ThresholdKeys};
+use frost::{
+  curve::{Ciphersuite, Ristretto},
+  ThresholdKeys,
+};
 
 use log::{info, warn, error};
-use tokio::time::sleep;
-
-use scale::Decode;
+use tokio::time::{sleep, interval};
 
 use serai_client::{
-  primitives::{MAX_DATA_LEN, BlockHash},
+  primitives::BlockHash,
   tokens::primitives::{OutInstruction, OutInstructionWithBalance},
-  in_instructions::primitives::{
-    Shorthand, RefundableInInstruction, InInstructionWithBalance, Batch,
-  },
+  in_instructions::primitives::Batch,
 };
 
 use messages::{SubstrateContext, CoordinatorMessage, ProcessorMessage};
@@ -35,12 +31,15 @@ mod coordinator;
 pub use coordinator::*;
 
 mod coins;
-use coins::{OutputType, Output, PostFeeBranch, Block, Coin};
+use coins::{PostFeeBranch, Block, Coin, drop_branches};
 #[cfg(feature = "bitcoin")]
 use coins::Bitcoin;
 #[cfg(feature = "monero")]
 use coins::Monero;
 
+mod entropy;
+use entropy::{SeedProvider, seed_provider};
+
 mod key_gen;
 use key_gen::{KeyConfirmed, KeyGen};
 
@@ -51,7 +50,7 @@ mod substrate_signer;
 use substrate_signer::{SubstrateSignerEvent, SubstrateSigner};
 
 mod scanner;
-use scanner::{ScannerEvent, Scanner, ScannerHandle};
+use scanner::{in_instructions, batch_instructions, ScannerEvent, Scanner, ScannerHandle};
 
 mod scheduler;
 use scheduler::Scheduler;
@@ -59,6 +58,9 @@ use scheduler::Scheduler;
 #[cfg(test)]
 mod tests;
 
+// How often to rebroadcast published transactions which have yet to be confirmed
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(60);
+
 // Generate a static additional key for a given chain in a globally consistent manner
 // Doesn't consider the current group key to increase the simplicity of verifying Serai's status
 // Takes an index, k, to support protocols which use multiple secondary keys
@@ -233,8 +235,31 @@ async fn sign_plans<C: Coin, D: Db>(
   }
 }
 
+// Convert the burns from a Substrate block into the payments they're owed
+fn burns_to_payments<C: Coin>(burns: Vec<OutInstructionWithBalance>) -> Vec<Payment<C>> {
+  let mut payments = vec![];
+  for out in burns {
+    let OutInstructionWithBalance { instruction: OutInstruction { address, data }, balance } = out;
+    assert_eq!(balance.coin.network(), C::NETWORK);
+
+    // Burns to addresses which aren't valid for this coin are dropped, as we can't pay them
+    if let Ok(address) = C::Address::try_from(address.consume()) {
+      // TODO: Add coin to payment
+      payments.push(Payment {
+        address,
+        data: data.map(|data| data.consume()),
+        amount: balance.amount.0,
+      });
+    } else {
+      warn!("burn to an address which isn't valid for this coin");
+    }
+  }
+  payments
+}
+
 async fn handle_coordinator_msg<D: Db, C: Coin, Co: Coordinator>(
   txn: &mut D::Transaction<'_>,
+  main_db: &MainDb<C, D>,
   coin: &C,
   coordinator: &mut Co,
   tributary_mutable: &mut TributaryMutable<C, D>,
@@ -302,18 +327,56 @@ async fn handle_coordinator_msg<D: Db, C: Coin, Co: Coordinator>(
   match msg.msg.clone() {
     CoordinatorMessage::KeyGen(msg) => {
       // TODO: This may be fired multiple times. What's our plan for that?
-      coordinator
-        .send(ProcessorMessage::KeyGen(tributary_mutable.key_gen.handle(txn, msg).await))
-        .await;
+      if let Some(msg) = tributary_mutable.key_gen.handle(txn, msg).await {
+        coordinator.send(ProcessorMessage::KeyGen(msg)).await;
+      }
+    }
+
+    CoordinatorMessage::Sign(messages::sign::CoordinatorMessage::BumpFee { id, bumps }) => {
+      let Some((block_number, plan)) = main_db.plan(&id.id) else {
+        warn!("told to bump the fee for {}, which we never planned", hex::encode(id.id));
+        return;
+      };
+      // Re-preparing the plan with a higher fee would change the amounts of the branch outputs it
+      // creates, which the scheduler was already told of
+      if (!C::FEE_BUMPS) || (!drop_branches(&plan).is_empty()) {
+        warn!("told to bump the fee for {}, which we can't bump the fee of", hex::encode(id.id));
+        return;
+      }
+
+      let key = id.key.clone();
+      let block_number = usize::try_from(block_number).unwrap();
+      let fee = C::bump_fee(get_fee(coin, block_number).await, bumps);
+      let signer = tributary_mutable.signers.get_mut(&key).unwrap();
+      let (Some((tx, _)), _) =
+        prepare_send(coin, signer.keys(), block_number, fee, plan.clone()).await
+      else {
+        warn!("{} can't afford its fee after {} bumps", hex::encode(id.id), bumps);
+        return;
+      };
+
+      if signer.bump_fee(txn, id, bumps, tx).await {
+        // Note we're signing this plan again, as its completion will be reported again
+        MainDb::<C, D>::save_signing(txn, &key, block_number.try_into().unwrap(), &plan);
+      }
     }
 
     CoordinatorMessage::Sign(msg) => {
       tributary_mutable.signers.get_mut(msg.key()).unwrap().handle(txn, msg).await;
     }
 
-    CoordinatorMessage::Coordinator(msg) => {
-      tributary_mutable.substrate_signers.get_mut(msg.key()).unwrap().handle(txn, msg).await;
-    }
+    CoordinatorMessage::Coordinator(msg) => match msg {
+      messages::coordinator::CoordinatorMessage::BatchSignedAck { .. } |
+      messages::coordinator::CoordinatorMessage::TransactionSignedAck { .. } |
+      messages::coordinator::CoordinatorMessage::ScannerCursorAck { .. } => {
+        if !MainDb::<C, D>::acknowledge(txn, &msg) {
+          warn!("coordinator acknowledged a message we weren't waiting on: {:?}", msg);
+        }
+      }
+      _ => {
+        tributary_mutable.substrate_signers.get_mut(msg.key()).unwrap().handle(txn, msg).await;
+      }
+    },
 
     CoordinatorMessage::Substrate(msg) => {
       match msg {
@@ -391,8 +454,20 @@ async fn handle_coordinator_msg<D: Db, C: Coin, Co: Coordinator>(
           let mut block_id = <C::Block as Block<C>>::Id::default();
           block_id.as_mut().copy_from_slice(&context.coin_latest_finalized_block.0);
 
+          assert_eq!(network, C::NETWORK);
           let key = <C::Curve as Ciphersuite>::read_G::<&[u8]>(&mut key_vec.as_ref()).unwrap();
 
+          // Substrate blocks are handled in order, so a block at or before the last one handled
+          // is a replay
+          // Scheduling its burns again would cause them to be paid out twice
+          if let Some(last) = main_db.last_substrate_block(&key_vec) {
+            if block <= last {
+              warn!("told to handle substrate block {block} yet already handled block {last}");
+              return;
+            }
+          }
+          MainDb::<C, D>::save_substrate_block(txn, &key_vec, block);
+
           // We now have to acknowledge every block for this key up to the acknowledged block
           let (blocks, outputs) =
             substrate_mutable.scanner.ack_up_to_block(txn, key, block_id).await;
@@ -403,25 +478,7 @@ async fn handle_coordinator_msg<D: Db, C: Coin, Co: Coordinator>(
             }
           }
 
-          let mut payments = vec![];
-          for out in burns {
-            let OutInstructionWithBalance {
-              instruction: OutInstruction { address, data },
-              balance,
-            } = out;
-            // TODO: Check network is this coin's network
-            assert_eq!(balance.coin.network(), network);
-
-            if let Ok(address) = C::Address::try_from(address.consume()) {
-              // TODO: Add coin to payment
-              payments.push(Payment {
-                address,
-                data: data.map(|data| data.consume()),
-                amount: balance.amount.0,
-              });
-            }
-          }
-
+          let payments = burns_to_payments::<C>(burns);
           let plans = substrate_mutable
             .schedulers
             .get_mut(&key_vec)
@@ -458,36 +515,11 @@ async fn boot<C: Coin, D: Db>(
   raw_db: &mut D,
   coin: &C,
 ) -> (MainDb<C, D>, TributaryMutable<C, D>, SubstrateMutable<C, D>) {
-  let mut entropy_transcript = {
-    let entropy =
-      Zeroizing::new(env::var("ENTROPY").expect("entropy wasn't provided as an env var"));
-    if entropy.len() != 64 {
-      panic!("entropy isn't the right length");
-    }
-    let bytes = Zeroizing::new(hex::decode(entropy).expect("entropy wasn't hex-formatted"));
-    let mut entropy = Zeroizing::new([0; 32]);
-    let entropy_mut: &mut [u8] = entropy.as_mut();
-    entropy_mut.copy_from_slice(bytes.as_ref());
-
-    let mut transcript = RecommendedTranscript::new(b"Serai Processor Entropy");
-    transcript.append_message(b"entropy", entropy);
-    transcript
-  };
-
   // TODO: Save a hash of the entropy to the DB and make sure the entropy didn't change
 
-  let mut entropy = |label| {
-    let mut challenge = entropy_transcript.challenge(label);
-    let mut res = Zeroizing::new([0; 32]);
-    let res_mut: &mut [u8] = res.as_mut();
-    res_mut.copy_from_slice(&challenge[.. 32]);
-    challenge.zeroize();
-    res
-  };
-
   // We don't need to re-issue GenerateKey orders because the coordinator is expected to
   // schedule/notify us of new attempts
-  let key_gen = KeyGen::<C, _>::new(raw_db.clone(), entropy(b"key-gen_entropy"));
+  let key_gen = KeyGen::<C, _>::new(raw_db.clone(), seed_provider().entropy(b"key-gen_entropy"));
   // The scanner has no long-standing orders to re-issue
   let (mut scanner, active_keys) = Scanner::new(coin.clone(), raw_db.clone());
 
@@ -544,7 +576,12 @@ async fn boot<C: Coin, D: Db>(
   )
 }
 
-async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordinator: Co) {
+async fn run<C: Coin, D: Db, Co: Coordinator>(
+  mut raw_db: D,
+  coin: C,
+  mut coordinator: Co,
+  coordinator_key: <Ristretto as Ciphersuite>::G,
+) {
   // We currently expect a contextless bidirectional mapping between these two values
   // (which is that any value of A can be interpreted as B and vice versa)
   // While we can write a contextual mapping, we have yet to do so
@@ -553,8 +590,15 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
 
   let (mut main_db, mut tributary_mutable, mut substrate_mutable) = boot(&mut raw_db, &coin).await;
 
+  // Resend any completions the coordinator didn't acknowledge before we rebooted
+  for msg in main_db.unacknowledged() {
+    coordinator.send(ProcessorMessage::Coordinator(msg)).await;
+  }
+
   // We can't load this from the DB as we can't guarantee atomic increments with the ack function
-  let mut last_coordinator_msg = None;
+  let mut coordinator_msgs = MessageIds::default();
+
+  let mut rebroadcast = interval(REBROADCAST_INTERVAL);
 
   loop {
     // Check if the signers have events
@@ -576,13 +620,18 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
               }))
               .await;
 
+            let signed =
+              messages::coordinator::ProcessorMessage::TransactionSigned { key: key.clone(), id };
+
             let mut txn = raw_db.txn();
-            // This does mutate the Scanner, yet the eventuality protocol is only run to mutate
-            // the signer, which is Tributary mutable (and what's currently being mutated)
-            substrate_mutable.scanner.drop_eventuality(id).await;
+            // The scanner keeps tracking this plan's eventuality, so we're informed once a
+            // transaction completing it is confirmed, and can stop rebroadcasting ours
             main_db.finish_signing(&mut txn, key, id);
+            MainDb::<C, D>::save_unacknowledged(&mut txn, &signed);
             txn.commit();
 
+            coordinator.send(ProcessorMessage::Coordinator(signed)).await;
+
             // TODO
             // 1) We need to stop signing whenever a peer informs us or the chain has an
             //    eventuality
@@ -602,12 +651,23 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
             coordinator.send(ProcessorMessage::Coordinator(msg)).await;
           }
           SubstrateSignerEvent::SignedBatch(batch) => {
+            let signed = messages::coordinator::ProcessorMessage::BatchSigned {
+              key: key.clone(),
+              id: batch.batch.id,
+            };
+
             coordinator
               .send(ProcessorMessage::Substrate(messages::substrate::ProcessorMessage::Update {
                 key: key.clone(),
                 batch,
               }))
               .await;
+
+            let mut txn = raw_db.txn();
+            MainDb::<C, D>::save_unacknowledged(&mut txn, &signed);
+            txn.commit();
+
+            coordinator.send(ProcessorMessage::Coordinator(signed)).await;
           }
         }
       }
@@ -620,8 +680,22 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
       // the other messages in the queue, it may be beneficial to parallelize these
       // They could likely be parallelized by type (KeyGen, Sign, Substrate) without issue
       msg = coordinator.recv() => {
-        assert_eq!(msg.id, (last_coordinator_msg.unwrap_or(msg.id - 1) + 1));
-        last_coordinator_msg = Some(msg.id);
+        // Reject any message the coordinator didn't send us, or which isn't for one of our sets
+        // These are acknowledged so they aren't delivered again
+        let key_set = |key: &[u8]| tributary_mutable.key_gen.key_set(key);
+        if let Err(e) = msg.authenticate(coordinator_key, C::NETWORK, key_set) {
+          error!("rejecting coordinator message {}: {e}", msg.id);
+          coordinator_msgs.reject(msg.id);
+          coordinator.ack(msg).await;
+          continue;
+        }
+
+        // Since the ID is authenticated, this can only be a replay of a message we already handled
+        if !coordinator_msgs.receive(msg.id) {
+          warn!("coordinator message {} was received again", msg.id);
+          coordinator.ack(msg).await;
+          continue;
+        }
 
         // Only handle this if we haven't already
         if !main_db.handled_message(msg.id) {
@@ -640,6 +714,7 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
           // references over the same data
           handle_coordinator_msg(
             &mut txn,
+            &main_db,
             &coin,
             &mut coordinator,
             &mut tributary_mutable,
@@ -663,51 +738,47 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
             let mut block_hash = [0; 32];
             block_hash.copy_from_slice(block.as_ref());
 
-            let batch = Batch {
-              network: C::NETWORK,
-              id: batch,
+            // Start signing each of this block's batches
+            let batches = batch_instructions(in_instructions::<C>(&outputs));
+            for (i, instructions) in batches.into_iter().enumerate() {
+              let batch = Batch {
+                network: C::NETWORK,
+                id: batch + u32::try_from(i).unwrap(),
+                block: BlockHash(block_hash),
+                instructions,
+              };
+              let signer = tributary_mutable.substrate_signers.get_mut(&key).unwrap();
+              signer.sign(&mut txn, batch).await;
+            }
+
+            // Inform the coordinator we've handled up to this block, so we can both resume from
+            // here on reboot
+            let cursor = messages::coordinator::ProcessorMessage::ScannerCursor {
+              key,
               block: BlockHash(block_hash),
-              instructions: outputs.iter().filter_map(|output| {
-                // If these aren't externally received funds, don't handle it as an instruction
-                if output.kind() != OutputType::External {
-                  return None;
-                }
-
-                let mut data = output.data();
-                let max_data_len = MAX_DATA_LEN.try_into().unwrap();
-                if data.len() > max_data_len {
-                  error!(
-                    "data in output {} exceeded MAX_DATA_LEN ({MAX_DATA_LEN}): {}",
-                    hex::encode(output.id()),
-                    data.len(),
-                  );
-                  data = &data[.. max_data_len];
-                }
-
-                let shorthand = Shorthand::decode(&mut data).ok()?;
-                let instruction = RefundableInInstruction::try_from(shorthand).ok()?;
-                // TODO2: Set instruction.origin if not set (and handle refunds in general)
-                Some(InInstructionWithBalance {
-                  instruction: instruction.instruction,
-                  balance: output.balance(),
-                })
-              }).collect()
             };
-
-            // Start signing this batch
-            tributary_mutable.substrate_signers.get_mut(&key).unwrap().sign(&mut txn, batch).await;
+            MainDb::<C, D>::save_unacknowledged(&mut txn, &cursor);
+            coordinator.send(ProcessorMessage::Coordinator(cursor)).await;
           },
 
           ScannerEvent::Completed(id, tx) => {
             // We don't know which signer had this plan, so inform all of them
             for (_, signer) in tributary_mutable.signers.iter_mut() {
               signer.eventuality_completion(&mut txn, id, &tx).await;
+              // Since this was found on-chain, we no longer have to rebroadcast it
+              signer.confirmed_completion(&mut txn, id);
             }
           },
         }
 
         txn.commit();
       },
+
+      _ = rebroadcast.tick() => {
+        for signer in tributary_mutable.signers.values_mut() {
+          signer.rebroadcast(&raw_db).await;
+        }
+      },
     }
   }
 }
@@ -716,12 +787,32 @@ async fn run<C: Coin, D: Db, Co: Coordinator>(mut raw_db: D, coin: C, mut coordi
 async fn main() {
   let db = MemDb::new(); // TODO
   let coordinator = MemCoordinator::new(); // TODO
-  let url = env::var("COIN_RPC").expect("coin rpc wasn't specified as an env var");
+  let coordinator_key = {
+    let key = hex::decode(
+      env::var("COORDINATOR_KEY").expect("coordinator key wasn't specified as an env var"),
+    )
+    .expect("coordinator key wasn't hex");
+    Ristretto::read_G::<&[u8]>(&mut key.as_ref()).expect("coordinator key wasn't a valid point")
+  };
+  // Multiple RPCs may be specified, comma-separated, which blocks are cross-checked against
+  let urls = env::var("COIN_RPC")
+    .expect("coin rpc wasn't specified as an env var")
+    .split(',')
+    .map(|url| url.trim().to_string())
+    .collect::<Vec<_>>();
+  // By default, require a majority of RPCs agree
+  let agreement = env::var("COIN_RPC_AGREEMENT")
+    .map(|agreement| agreement.parse().expect("coin rpc agreement wasn't a number"))
+    .unwrap_or((urls.len() / 2) + 1);
   match env::var("COIN").expect("coin wasn't specified as an env var").as_str() {
     #[cfg(feature = "bitcoin")]
-    "bitcoin" => run(db, Bitcoin::new(url).await, coordinator).await,
+    "bitcoin" => {
+      run(db, Bitcoin::with_endpoints(urls, agreement).await, coordinator, coordinator_key).await
+    }
     #[cfg(feature = "monero")]
-    "monero" => run(db, Monero::new(url), coordinator).await,
+    "monero" => {
+      run(db, Monero::with_endpoints(urls, agreement), coordinator, coordinator_key).await
+    }
     _ => panic!("unrecognized coin"),
   }
 }
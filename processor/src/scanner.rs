@@ -8,25 +8,103 @@ use std::{
 use group::GroupEncoding;
 use frost::curve::Ciphersuite;
 
-use log::{info, debug, warn};
+use log::{info, debug, warn, error};
 use tokio::{
   sync::{RwLock, mpsc},
   time::sleep,
 };
 
-use serai_client::primitives::BlockHash;
+use scale::{Encode, Decode};
+
+use serai_client::{
+  primitives::{MAX_DATA_LEN, NetworkId, BlockHash},
+  in_instructions::primitives::{
+    MAX_BATCH_SIZE, Shorthand, RefundableInInstruction, InInstructionWithBalance, Batch,
+  },
+};
 
 use crate::{
   Get, DbTxn, Db,
-  coins::{Output, Transaction, EventualitiesTracker, Block, Coin},
+  coins::{OutputType, Output, Transaction, EventualitiesTracker, Block, Coin},
 };
 
+// The maximum amount of instructions in a single Batch
+// The in-instructions pallet only bounds a Batch by its size, yet bounding the amount of
+// instructions also bounds how long any single Batch takes to execute
+pub const MAX_BATCH_INSTRUCTIONS: usize = 256;
+
+/// Parse the InInstructions from a block's outputs, in the order the outputs were scanned.
+pub fn in_instructions<C: Coin>(outputs: &[C::Output]) -> Vec<InInstructionWithBalance> {
+  outputs
+    .iter()
+    .filter_map(|output| {
+      // If these aren't externally received funds, don't handle it as an instruction
+      if output.kind() != OutputType::External {
+        return None;
+      }
+
+      let mut data = output.data();
+      let max_data_len = MAX_DATA_LEN.try_into().unwrap();
+      if data.len() > max_data_len {
+        error!(
+          "data in output {} exceeded MAX_DATA_LEN ({MAX_DATA_LEN}): {}",
+          hex::encode(output.id()),
+          data.len(),
+        );
+        data = &data[.. max_data_len];
+      }
+
+      let shorthand = Shorthand::decode(&mut data).ok()?;
+      let instruction = RefundableInInstruction::try_from(shorthand).ok()?;
+      // TODO2: Set instruction.origin if not set (and handle refunds in general)
+      Some(InInstructionWithBalance {
+        instruction: instruction.instruction,
+        balance: output.balance(),
+      })
+    })
+    .collect()
+}
+
+/// Split a block's InInstructions into the instructions for each of its Batches.
+///
+/// This is deterministic, so every processor derives the same Batches (and Batch IDs) for a block
+/// without coordinating. Instructions keep their order and each Batch is bounded by both
+/// MAX_BATCH_SIZE and MAX_BATCH_INSTRUCTIONS. A block always has at least one Batch, even if it
+/// has no instructions.
+pub fn batch_instructions(
+  instructions: Vec<InInstructionWithBalance>,
+) -> Vec<Vec<InInstructionWithBalance>> {
+  // The size of a Batch without instructions, plus the largest length prefix for its instructions
+  let overhead =
+    Batch { network: NetworkId::Serai, id: 0, block: BlockHash([0; 32]), instructions: vec![] }
+      .encode()
+      .len() +
+      4;
+
+  let mut batches = vec![vec![]];
+  let mut size = overhead;
+  for instruction in instructions {
+    let len = instruction.encode().len();
+    let batch: &mut Vec<_> = batches.last_mut().unwrap();
+    if (!batch.is_empty()) &&
+      ((batch.len() == MAX_BATCH_INSTRUCTIONS) || ((size + len) > MAX_BATCH_SIZE))
+    {
+      batches.push(vec![]);
+      size = overhead;
+    }
+    size += len;
+    batches.last_mut().unwrap().push(instruction);
+  }
+  batches
+}
+
 #[derive(Clone, Debug)]
 pub enum ScannerEvent<C: Coin> {
   // Block scanned
   Block {
     key: <C::Curve as Ciphersuite>::G,
     block: <C::Block as Block<C>>::Id,
+    // The ID of the first Batch for this block
     batch: u32,
     outputs: Vec<C::Output>,
   },
@@ -66,43 +144,35 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
       .map(|number| u64::from_le_bytes(number.try_into().unwrap()).try_into().unwrap())
   }
 
-  fn active_keys_key() -> Vec<u8> {
-    Self::scanner_key(b"active_keys", b"")
+  // Active keys are keyed by their activation block number, in big endian, then the key
+  // This lets them be iterated, in order of activation, by prefix
+  fn active_key_key(activation_number: usize, key: &<C::Curve as Ciphersuite>::G) -> Vec<u8> {
+    Self::scanner_key(
+      b"active_key",
+      [u64::try_from(activation_number).unwrap().to_be_bytes().as_ref(), key.to_bytes().as_ref()]
+        .concat(),
+    )
   }
-  fn add_active_key(txn: &mut D::Transaction<'_>, key: <C::Curve as Ciphersuite>::G) {
-    let mut keys = txn.get(Self::active_keys_key()).unwrap_or(vec![]);
-
-    let key_bytes = key.to_bytes();
-
-    let key_len = key_bytes.as_ref().len();
-    assert_eq!(keys.len() % key_len, 0);
-
+  fn add_active_key(
+    txn: &mut D::Transaction<'_>,
+    activation_number: usize,
+    key: <C::Curve as Ciphersuite>::G,
+  ) {
     // Don't add this key if it's already present
-    let mut i = 0;
-    while i < keys.len() {
-      if &keys[i .. (i + key_len)] == key_bytes.as_ref() {
-        debug!("adding {} as an active key yet it was already present", hex::encode(key_bytes));
-        return;
-      }
-      i += key_len;
+    if Self::active_keys(txn).contains(&key) {
+      debug!("adding {} as an active key yet it was already present", hex::encode(key.to_bytes()));
+      return;
     }
 
-    keys.extend(key_bytes.as_ref());
-    txn.put(Self::active_keys_key(), keys);
+    txn.put(Self::active_key_key(activation_number, &key), b"");
   }
   fn active_keys<G: Get>(getter: &G) -> Vec<<C::Curve as Ciphersuite>::G> {
-    let bytes_vec = getter.get(Self::active_keys_key()).unwrap_or(vec![]);
-    let mut bytes: &[u8] = bytes_vec.as_ref();
-
-    // Assumes keys will be 32 bytes when calculating the capacity
-    // If keys are larger, this may allocate more memory than needed
-    // If keys are smaller, this may require additional allocations
-    // Either are fine
-    let mut res = Vec::with_capacity(bytes.len() / 32);
-    while !bytes.is_empty() {
-      res.push(C::Curve::read_G(&mut bytes).unwrap());
-    }
-    res
+    let prefix = Self::scanner_key(b"active_key", []);
+    getter
+      .get_prefix(&prefix)
+      .into_iter()
+      .map(|(key, _)| C::Curve::read_G(&mut &key[(prefix.len() + 8) ..]).unwrap())
+      .collect()
   }
 
   fn seen_key(id: &<C::Output as Output>::Id) -> Vec<u8> {
@@ -118,12 +188,21 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
   fn batch_key(key: &<C::Curve as Ciphersuite>::G, block: &<C::Block as Block<C>>::Id) -> Vec<u8> {
     Self::scanner_key(b"batch", [key.to_bytes().as_ref(), block.as_ref()].concat())
   }
+  // Outputs are keyed by the key, the block, and then their index within the block, in big
+  // endian, so a block's outputs can be iterated, in order, by prefix
   fn outputs_key(
     key: &<C::Curve as Ciphersuite>::G,
     block: &<C::Block as Block<C>>::Id,
   ) -> Vec<u8> {
     Self::scanner_key(b"outputs", [key.to_bytes().as_ref(), block.as_ref()].concat())
   }
+  fn output_key(
+    key: &<C::Curve as Ciphersuite>::G,
+    block: &<C::Block as Block<C>>::Id,
+    index: u32,
+  ) -> Vec<u8> {
+    [Self::outputs_key(key, block), index.to_be_bytes().to_vec()].concat()
+  }
   fn save_outputs(
     txn: &mut D::Transaction<'_>,
     key: &<C::Curve as Ciphersuite>::G,
@@ -135,11 +214,11 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
       return u32::from_le_bytes(batch.try_into().unwrap());
     }
 
-    let mut bytes = Vec::with_capacity(outputs.len() * 64);
-    for output in outputs {
+    for (i, output) in outputs.iter().enumerate() {
+      let mut bytes = Vec::with_capacity(64);
       output.write(&mut bytes).unwrap();
+      txn.put(Self::output_key(key, block, u32::try_from(i).unwrap()), bytes);
     }
-    txn.put(Self::outputs_key(key, block), bytes);
 
     // This is a new set of outputs, which are expected to be handled in a perfectly ordered
     // fashion
@@ -151,10 +230,13 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
     // when it should be
     // 0a, 1a, 2a, 3a, 4a, 4b, 5a, 5b
 
-    // Because it's a new set of outputs, allocate a batch ID for it
+    // Because it's a new set of outputs, allocate batch IDs for it
+    // The first ID is saved, with the rest implied by how the outputs deterministically batch
+    let batches =
+      u32::try_from(batch_instructions(in_instructions::<C>(outputs)).len()).unwrap();
     let next_bytes = txn.get(Self::next_batch_key()).unwrap_or(vec![0; 4]).try_into().unwrap();
     let next = u32::from_le_bytes(next_bytes);
-    txn.put(Self::next_batch_key(), (next + 1).to_le_bytes());
+    txn.put(Self::next_batch_key(), (next + batches).to_le_bytes());
     txn.put(Self::batch_key(key, block), next_bytes);
     next
   }
@@ -162,15 +244,12 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
     txn: &D::Transaction<'_>,
     key: &<C::Curve as Ciphersuite>::G,
     block: &<C::Block as Block<C>>::Id,
-  ) -> Option<Vec<C::Output>> {
-    let bytes_vec = txn.get(Self::outputs_key(key, block))?;
-    let mut bytes: &[u8] = bytes_vec.as_ref();
-
-    let mut res = vec![];
-    while !bytes.is_empty() {
-      res.push(C::Output::read(&mut bytes).unwrap());
-    }
-    Some(res)
+  ) -> Vec<C::Output> {
+    txn
+      .get_prefix(Self::outputs_key(key, block))
+      .into_iter()
+      .map(|(_, output)| C::Output::read::<&[u8]>(&mut output.as_ref()).unwrap())
+      .collect()
   }
 
   fn scanned_block_key(key: &<C::Curve as Ciphersuite>::G) -> Vec<u8> {
@@ -185,7 +264,7 @@ impl<C: Coin, D: Db> ScannerDb<C, D> {
   ) -> (Option<<C::Block as Block<C>>::Id>, Vec<C::Output>) {
     let id = Self::block(txn, block); // It may be None for the first key rotated to
     let outputs = if let Some(id) = id.as_ref() {
-      Self::outputs(txn, key, id).unwrap_or(vec![])
+      Self::outputs(txn, key, id)
     } else {
       vec![]
     };
@@ -254,10 +333,6 @@ impl<C: Coin, D: Db> ScannerHandle<C, D> {
     self.scanner.write().await.eventualities.register(block_number, id, eventuality)
   }
 
-  pub async fn drop_eventuality(&mut self, id: [u8; 32]) {
-    self.scanner.write().await.eventualities.drop(id);
-  }
-
   /// Rotate the key being scanned for.
   ///
   /// If no key has been prior set, this will become the key with no further actions.
@@ -284,7 +359,7 @@ impl<C: Coin, D: Db> ScannerHandle<C, D> {
     scanner.ram_scanned.insert(key.to_bytes().as_ref().to_vec(), activation_number);
     assert!(outputs.is_empty());
 
-    ScannerDb::<C, D>::add_active_key(txn, key);
+    ScannerDb::<C, D>::add_active_key(txn, activation_number, key);
     scanner.keys.push(key);
   }
 
@@ -437,10 +512,11 @@ impl<C: Coin, D: Db> Scanner<C, D> {
             for (id, tx) in
               coin.get_eventuality_completions(&mut scanner.eventualities, &block).await
             {
-              // This should only happen if there's a P2P net desync or there's a malicious
-              // validator
-              warn!(
-                "eventuality {} resolved by {}, as found on chain. this should not happen",
+              // Eventualities remain registered after we sign for them, so this is how
+              // transactions we published are confirmed. Since only confirmed blocks are scanned,
+              // this is only emitted once the transaction has the coin's confirmation depth
+              info!(
+                "eventuality {} resolved by {}, as found on chain",
                 hex::encode(id),
                 hex::encode(&tx)
               );
@@ -17,6 +17,10 @@ use crate::{
   coins::{Transaction, Eventuality, Coin},
 };
 
+// How many times a transaction may be rebroadcast without confirming before we warn about it, and
+// request its fee be bumped
+pub(crate) const LINGERING_REBROADCASTS: usize = 10;
+
 #[derive(Debug)]
 pub enum SignerEvent<C: Coin> {
   SignedTransaction { id: [u8; 32], tx: <C::Transaction as Transaction<C>>::Id },
@@ -60,6 +64,15 @@ impl<C: Coin, D: Db> SignerDb<C, D> {
   fn completed<G: Get>(getter: &G, id: [u8; 32]) -> Option<Vec<u8>> {
     getter.get(Self::completed_key(id))
   }
+  // If this TX was already noted as completing this plan
+  fn completed_by<G: Get>(
+    getter: &G,
+    id: [u8; 32],
+    tx: &<C::Transaction as Transaction<C>>::Id,
+  ) -> bool {
+    let Some(existing) = Self::completed(getter, id) else { return false };
+    existing.chunks(tx.as_ref().len()).any(|existing| existing == tx.as_ref())
+  }
 
   fn eventuality_key(id: [u8; 32]) -> Vec<u8> {
     Self::sign_key(b"eventuality", id)
@@ -72,6 +85,26 @@ impl<C: Coin, D: Db> SignerDb<C, D> {
       C::Eventuality::read::<&[u8]>(&mut getter.get(Self::eventuality_key(id))?.as_ref()).unwrap(),
     )
   }
+  fn delete_eventuality(txn: &mut D::Transaction<'_>, id: [u8; 32]) {
+    txn.del(Self::eventuality_key(id));
+  }
+
+  // How many times the fee of the transaction for this plan has been bumped
+  fn bumps_key(id: [u8; 32]) -> Vec<u8> {
+    Self::sign_key(b"bumps", id)
+  }
+  fn save_bumps(txn: &mut D::Transaction<'_>, id: [u8; 32], bumps: u32) {
+    txn.put(Self::bumps_key(id), bumps.to_le_bytes());
+  }
+  fn bumps<G: Get>(getter: &G, id: [u8; 32]) -> u32 {
+    getter
+      .get(Self::bumps_key(id))
+      .map(|bumps| u32::from_le_bytes(bumps.try_into().unwrap()))
+      .unwrap_or(0)
+  }
+  fn delete_bumps(txn: &mut D::Transaction<'_>, id: [u8; 32]) {
+    txn.del(Self::bumps_key(id));
+  }
 
   fn attempt_key(id: &SignId) -> Vec<u8> {
     Self::sign_key(b"attempt", bincode::serialize(id).unwrap())
@@ -86,6 +119,28 @@ impl<C: Coin, D: Db> SignerDb<C, D> {
   fn save_transaction(txn: &mut D::Transaction<'_>, tx: &C::Transaction) {
     txn.put(Self::sign_key(b"tx", tx.id()), tx.serialize());
   }
+
+  // Transactions we've published yet have yet to see confirmed, keyed by the signer's key and the
+  // plan they're for
+  fn rebroadcast_key(key: &[u8], id: [u8; 32]) -> Vec<u8> {
+    Self::sign_key(b"rebroadcast", [key, id.as_ref()].concat())
+  }
+  fn save_rebroadcast(txn: &mut D::Transaction<'_>, key: &[u8], id: [u8; 32], tx: &C::Transaction) {
+    txn.put(Self::rebroadcast_key(key, id), tx.serialize());
+  }
+  fn stop_rebroadcast(txn: &mut D::Transaction<'_>, key: &[u8], id: [u8; 32]) {
+    txn.del(Self::rebroadcast_key(key, id));
+  }
+  fn rebroadcasts<G: Get>(getter: &G, key: &[u8]) -> Vec<([u8; 32], C::Transaction)> {
+    getter
+      .get_prefix(Self::sign_key(b"rebroadcast", key))
+      .into_iter()
+      .map(|(db_key, tx)| {
+        let id = db_key[(db_key.len() - 32) ..].try_into().unwrap();
+        (id, C::Transaction::read::<&[u8]>(&mut tx.as_ref()).unwrap())
+      })
+      .collect()
+  }
 }
 
 pub struct Signer<C: Coin, D: Db> {
@@ -97,6 +152,8 @@ pub struct Signer<C: Coin, D: Db> {
 
   signable: HashMap<[u8; 32], C::SignableTransaction>,
   attempt: HashMap<[u8; 32], u32>,
+  // How many times we've rebroadcast the transaction for each plan
+  rebroadcasted: HashMap<[u8; 32], usize>,
   preprocessing: HashMap<[u8; 32], <C::TransactionMachine as PreprocessMachine>::SignMachine>,
   #[allow(clippy::type_complexity)]
   signing: HashMap<
@@ -131,6 +188,7 @@ impl<C: Coin, D: Db> Signer<C, D> {
 
       signable: HashMap::new(),
       attempt: HashMap::new(),
+      rebroadcasted: HashMap::new(),
       preprocessing: HashMap::new(),
       signing: HashMap::new(),
 
@@ -179,6 +237,13 @@ impl<C: Coin, D: Db> Signer<C, D> {
     tx_id: &<C::Transaction as Transaction<C>>::Id,
   ) {
     if let Some(eventuality) = SignerDb::<C, D>::eventuality(txn, id) {
+      // We already handled this TX completing this plan, either as we signed it ourselves or as
+      // we were already informed of it
+      if SignerDb::<C, D>::completed_by(txn, id, tx_id) {
+        debug!("already noted {} completed {}", hex::encode(tx_id), hex::encode(id));
+        return;
+      }
+
       // Transaction hasn't hit our mempool/was dropped for a different signature
       // The latter can happen given certain latency conditions/a single malicious signer
       // In the case of a single malicious signer, they can drag multiple honest
@@ -195,16 +260,22 @@ impl<C: Coin, D: Db> Signer<C, D> {
       if self.coin.confirm_completion(&eventuality, &tx) {
         debug!("eventuality for {} resolved in TX {}", hex::encode(id), hex::encode(tx_id));
 
+        // If this plan was already completed, and we aren't re-signing it with a bumped fee, the
+        // plan's completion was already reported
+        let reported = SignerDb::<C, D>::completed(txn, id).is_some();
+
         // Stop trying to sign for this TX
         SignerDb::<C, D>::save_transaction(txn, &tx);
         SignerDb::<C, D>::complete(txn, id, tx_id);
 
-        self.signable.remove(&id);
+        let signing = self.signable.remove(&id).is_some();
         self.attempt.remove(&id);
         self.preprocessing.remove(&id);
         self.signing.remove(&id);
 
-        self.events.push_back(SignerEvent::SignedTransaction { id, tx: tx.id() });
+        if signing || (!reported) {
+          self.events.push_back(SignerEvent::SignedTransaction { id, tx: tx.id() });
+        }
       } else {
         warn!(
           "a validator claimed {} completed {} when it did not",
@@ -222,6 +293,50 @@ impl<C: Coin, D: Db> Signer<C, D> {
     }
   }
 
+  /// Note the plan was completed by a transaction confirmed on-chain, so whatever transaction we
+  /// published for it no longer needs to be rebroadcast, nor can its fee be bumped.
+  pub fn confirmed_completion(&mut self, txn: &mut D::Transaction<'_>, id: [u8; 32]) {
+    SignerDb::<C, D>::stop_rebroadcast(txn, self.keys.group_key().to_bytes().as_ref(), id);
+    SignerDb::<C, D>::delete_eventuality(txn, id);
+    SignerDb::<C, D>::delete_bumps(txn, id);
+    self.rebroadcasted.remove(&id);
+  }
+
+  /// Rebroadcast every transaction we've published which has yet to be confirmed.
+  ///
+  /// Warns whenever a transaction has been rebroadcast LINGERING_REBROADCASTS times without
+  /// confirming, as its fee is likely too low. If the coin supports replacing transactions, the
+  /// first such warning also requests the transaction's fee be bumped.
+  pub async fn rebroadcast<G: Get>(&mut self, getter: &G) {
+    let key = self.keys.group_key().to_bytes().as_ref().to_vec();
+    for (id, tx) in SignerDb::<C, D>::rebroadcasts(getter, &key) {
+      let rebroadcasted = self.rebroadcasted.entry(id).or_insert(0);
+      *rebroadcasted += 1;
+      if (*rebroadcasted % LINGERING_REBROADCASTS) == 0 {
+        warn!(
+          "{} for {} was rebroadcast {} times without confirming. its fee may be too low",
+          hex::encode(tx.id()),
+          hex::encode(id),
+          rebroadcasted,
+        );
+
+        if C::FEE_BUMPS && (*rebroadcasted == LINGERING_REBROADCASTS) {
+          self.events.push_back(SignerEvent::ProcessorMessage(ProcessorMessage::RequestFeeBump {
+            key: key.clone(),
+            id,
+            bumps: SignerDb::<C, D>::bumps(getter, id) + 1,
+          }));
+        }
+      }
+
+      if let Err(e) = self.coin.publish_transaction(&tx).await {
+        warn!("couldn't rebroadcast {}: {:?}", hex::encode(tx.id()), e);
+      } else {
+        debug!("rebroadcasted {}", hex::encode(tx.id()));
+      }
+    }
+  }
+
   async fn check_completion(&mut self, txn: &mut D::Transaction<'_>, id: [u8; 32]) -> bool {
     if let Some(txs) = SignerDb::<C, D>::completed(txn, id) {
       debug!(
@@ -257,7 +372,8 @@ impl<C: Coin, D: Db> Signer<C, D> {
   }
 
   async fn attempt(&mut self, txn: &mut D::Transaction<'_>, id: [u8; 32], attempt: u32) {
-    if self.check_completion(txn, id).await {
+    // Plans which were completed are only signed again when re-signing them with a bumped fee
+    if (!self.signable.contains_key(&id)) && self.check_completion(txn, id).await {
       return;
     }
 
@@ -352,6 +468,27 @@ impl<C: Coin, D: Db> Signer<C, D> {
     self.attempt(txn, id, 0).await;
   }
 
+  /// Re-sign the transaction for a plan, with its fee bumped, under the specified attempt.
+  ///
+  /// Returns false if the plan's completion was already confirmed on-chain.
+  pub async fn bump_fee(
+    &mut self,
+    txn: &mut D::Transaction<'_>,
+    id: SignId,
+    bumps: u32,
+    tx: C::SignableTransaction,
+  ) -> bool {
+    if SignerDb::<C, D>::eventuality(txn, id.id).is_none() {
+      warn!("told to bump the fee for {}, which was already confirmed", hex::encode(id.id));
+      return false;
+    }
+
+    SignerDb::<C, D>::save_bumps(txn, id.id, bumps);
+    self.signable.insert(id.id, tx);
+    self.attempt(txn, id.id, id.attempt).await;
+    true
+  }
+
   pub async fn handle(&mut self, txn: &mut D::Transaction<'_>, msg: CoordinatorMessage) {
     match msg {
       CoordinatorMessage::Preprocesses { id, mut preprocesses } => {
@@ -458,12 +595,21 @@ impl<C: Coin, D: Db> Signer<C, D> {
         } else {
           info!("published {}", hex::encode(&tx_id));
         }
+        // Keep rebroadcasting it until it's confirmed, in case it's dropped from mempools
+        SignerDb::<C, D>::save_rebroadcast(
+          txn,
+          self.keys.group_key().to_bytes().as_ref(),
+          id.id,
+          &tx,
+        );
 
         // Stop trying to sign for this TX
         assert!(self.signable.remove(&id.id).is_some());
         assert!(self.attempt.remove(&id.id).is_some());
         assert!(self.preprocessing.remove(&id.id).is_none());
         assert!(self.signing.remove(&id.id).is_none());
+        // If this replaced a lingering TX, it has yet to be rebroadcast
+        self.rebroadcasted.remove(&id.id);
 
         self.events.push_back(SignerEvent::SignedTransaction { id: id.id, tx: tx_id });
       }
@@ -487,6 +633,11 @@ impl<C: Coin, D: Db> Signer<C, D> {
 
         self.eventuality_completion(txn, id, &tx).await;
       }
+
+      // Re-signing with a bumped fee requires re-preparing the plan, which the signer can't do
+      CoordinatorMessage::BumpFee { .. } => {
+        unreachable!("BumpFee should be handled by calling Signer::bump_fee")
+      }
     }
   }
 }
@@ -60,7 +60,10 @@ impl<D: Db> SubstrateSignerDb<D> {
   }
 
   fn save_batch(txn: &mut D::Transaction<'_>, batch: &SignedBatch) {
-    txn.put(Self::sign_key(b"batch", batch.batch.block), batch.encode());
+    txn.put(
+      Self::sign_key(b"batch", batch_sign_id(batch.batch.network, batch.batch.id)),
+      batch.encode(),
+    );
   }
 }
 
@@ -161,6 +164,7 @@ impl<D: Db> SubstrateSigner<D> {
     // Update the attempt number
     self.attempt.insert(id, attempt);
 
+    let block = self.signable[&id].block;
     let id = SignId { key: self.keys.group_key().to_bytes().to_vec(), id, attempt };
     info!("signing batch {} #{}", hex::encode(id.id), id.attempt);
 
@@ -195,18 +199,19 @@ impl<D: Db> SubstrateSigner<D> {
 
     // Broadcast our preprocess
     self.events.push_back(SubstrateSignerEvent::ProcessorMessage(
-      ProcessorMessage::BatchPreprocess { id, preprocess: preprocess.serialize() },
+      ProcessorMessage::BatchPreprocess { id, block, preprocess: preprocess.serialize() },
     ));
   }
 
   pub async fn sign(&mut self, txn: &mut D::Transaction<'_>, batch: Batch) {
-    if SubstrateSignerDb::<D>::completed(txn, batch.block.0) {
+    // A block may have multiple batches, so batches are identified by their own ID
+    let id = batch_sign_id(batch.network, batch.id);
+    if SubstrateSignerDb::<D>::completed(txn, id) {
       debug!("Sign batch order for ID we've already completed signing");
       // See batch_signed for commentary on why this simply returns
       return;
     }
 
-    let id = batch.block.0;
     self.signable.insert(id, batch);
     self.attempt(txn, id, 0).await;
   }
@@ -324,21 +329,34 @@ impl<D: Db> SubstrateSigner<D> {
       CoordinatorMessage::BatchReattempt { id } => {
         self.attempt(txn, id.id, id.attempt).await;
       }
+
+      CoordinatorMessage::BatchSignedAck { .. } |
+      CoordinatorMessage::TransactionSignedAck { .. } |
+      CoordinatorMessage::ScannerCursorAck { .. } => {
+        panic!("substrate signer was passed an acknowledgement")
+      }
     }
   }
 
   pub fn batch_signed(&mut self, txn: &mut D::Transaction<'_>, block: BlockHash) {
-    // Stop trying to sign for this batch
-    SubstrateSignerDb::<D>::complete(txn, block.0);
-
-    self.signable.remove(&block.0);
-    self.attempt.remove(&block.0);
-    self.preprocessing.remove(&block.0);
-    self.signing.remove(&block.0);
+    // Stop trying to sign for every batch for this block
+    let ids = self
+      .signable
+      .iter()
+      .filter_map(|(id, batch)| (batch.block == block).then_some(*id))
+      .collect::<Vec<_>>();
+    for id in ids {
+      SubstrateSignerDb::<D>::complete(txn, id);
+
+      self.signable.remove(&id);
+      self.attempt.remove(&id);
+      self.preprocessing.remove(&id);
+      self.signing.remove(&id);
+    }
 
     // This doesn't emit SignedBatch because it doesn't have access to the SignedBatch
     // This function is expected to only be called once Substrate acknowledges this block,
-    // which means its batch must have been signed
+    // which means its batches must have been signed
     // While a successive batch's signing would also cause this block to be acknowledged, Substrate
     // guarantees a batch's ordered inclusion
 
@@ -0,0 +1,145 @@
+use core::ops::Deref;
+
+use zeroize::Zeroizing;
+
+use rand_core::{RngCore, CryptoRng, OsRng};
+
+use group::ff::Field;
+use frost::{
+  curve::{Ciphersuite, Ristretto},
+  Participant, ThresholdParams,
+};
+use schnorr::SchnorrSignature;
+
+use serai_client::{
+  primitives::NetworkId,
+  validator_sets::primitives::{Session, ValidatorSet},
+};
+
+use messages::{key_gen, sign, CoordinatorMessage};
+
+use crate::coordinator::{AuthenticationError, Message, MessageIds};
+
+// Sign a message as the coordinator would for the specified validator set
+fn sign_message<R: RngCore + CryptoRng>(
+  rng: &mut R,
+  key: &Zeroizing<<Ristretto as Ciphersuite>::F>,
+  set: ValidatorSet,
+  id: u64,
+  msg: CoordinatorMessage,
+) -> Message {
+  let nonce = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut *rng));
+  let challenge = msg.challenge(
+    Ristretto::generator() * key.deref(),
+    Ristretto::generator() * nonce.deref(),
+    set,
+    id,
+  );
+  Message { id, msg, signature: SchnorrSignature::sign(key, nonce, challenge) }
+}
+
+fn set(session: u32, network: NetworkId) -> ValidatorSet {
+  ValidatorSet { session: Session(session), network }
+}
+
+fn generate_key(set: ValidatorSet) -> CoordinatorMessage {
+  CoordinatorMessage::KeyGen(key_gen::CoordinatorMessage::GenerateKey {
+    id: key_gen::KeyGenId { set, attempt: 0 },
+    params: ThresholdParams::new(1, 1, Participant::new(1).unwrap()).unwrap(),
+    serai_genesis: [0xff; 32],
+  })
+}
+
+fn reattempt(key: &[u8]) -> CoordinatorMessage {
+  CoordinatorMessage::Sign(sign::CoordinatorMessage::Reattempt {
+    id: sign::SignId { key: key.to_vec(), id: [0xff; 32], attempt: 1 },
+  })
+}
+
+#[test]
+fn authenticate() {
+  let key = Zeroizing::new(<Ristretto as Ciphersuite>::F::random(&mut OsRng));
+  let coordinator = Ristretto::generator() * *key;
+  let sign = |set, msg| sign_message(&mut OsRng, &key, set, 1, msg);
+
+  // The key we have, which is for the second session
+  let our_key = [1; 32];
+  let key_set = |key: &[u8]| (key == our_key).then_some(set(1, NetworkId::Bitcoin));
+
+  let msg = sign(set(0, NetworkId::Bitcoin), generate_key(set(0, NetworkId::Bitcoin)));
+  assert_eq!(msg.authenticate(coordinator, NetworkId::Bitcoin, key_set), Ok(()));
+
+  // Messages shouldn't be accepted from anyone other than the coordinator
+  let other = Ristretto::generator() * <Ristretto as Ciphersuite>::F::random(&mut OsRng);
+  assert_eq!(
+    msg.authenticate(other, NetworkId::Bitcoin, key_set),
+    Err(AuthenticationError::InvalidSignature)
+  );
+
+  // Or be replayable under another ID
+  let mut replayed = msg.clone();
+  replayed.id = 2;
+  assert_eq!(
+    replayed.authenticate(coordinator, NetworkId::Bitcoin, key_set),
+    Err(AuthenticationError::InvalidSignature)
+  );
+
+  // Messages for another network's validator set should be rejected, even if signed
+  let msg = sign(set(0, NetworkId::Monero), generate_key(set(0, NetworkId::Monero)));
+  assert_eq!(
+    msg.authenticate(coordinator, NetworkId::Bitcoin, key_set),
+    Err(AuthenticationError::WrongNetwork(NetworkId::Monero))
+  );
+
+  // Messages for a key are bound to the validator set the key is for
+  assert_eq!(
+    sign(set(1, NetworkId::Bitcoin), reattempt(&our_key)).authenticate(
+      coordinator,
+      NetworkId::Bitcoin,
+      key_set
+    ),
+    Ok(())
+  );
+  // So they can't be replayed from another session
+  assert_eq!(
+    sign(set(0, NetworkId::Bitcoin), reattempt(&our_key)).authenticate(
+      coordinator,
+      NetworkId::Bitcoin,
+      key_set
+    ),
+    Err(AuthenticationError::InvalidSignature)
+  );
+  assert_eq!(
+    sign(set(1, NetworkId::Bitcoin), reattempt(&[2; 32])).authenticate(
+      coordinator,
+      NetworkId::Bitcoin,
+      key_set
+    ),
+    Err(AuthenticationError::UnrecognizedKey)
+  );
+}
+
+#[test]
+fn message_ids() {
+  let mut ids = MessageIds::default();
+  assert!(ids.receive(5));
+  assert!(ids.receive(6));
+
+  // Messages which were rejected shouldn't cause the following messages to be out of order
+  ids.reject(7);
+  ids.reject(8);
+  assert!(ids.receive(9));
+
+  // Messages already received should be detected
+  assert!(!ids.receive(9));
+  assert!(!ids.receive(6));
+  assert!(ids.receive(10));
+}
+
+#[test]
+#[should_panic]
+fn skipped_message_id() {
+  let mut ids = MessageIds::default();
+  assert!(ids.receive(0));
+  ids.receive(2);
+}
@@ -0,0 +1,27 @@
+use zeroize::Zeroizing;
+
+use transcript::{Transcript, RecommendedTranscript};
+
+use crate::entropy::SeedProvider;
+
+struct TestSeedProvider;
+impl SeedProvider for TestSeedProvider {
+  fn seed(&self) -> Zeroizing<[u8; 32]> {
+    Zeroizing::new([0xaa; 32])
+  }
+}
+
+#[test]
+fn entropy_vector() {
+  // The derivation used before seed providers, which existing keys were generated with
+  let mut transcript = RecommendedTranscript::new(b"Serai Processor Entropy");
+  transcript.append_message(b"entropy", [0xaa; 32]);
+  let expected = transcript.challenge(b"key-gen_entropy");
+
+  let entropy = TestSeedProvider.entropy(b"key-gen_entropy");
+  assert_eq!(entropy[..], expected[.. 32]);
+  assert_eq!(
+    hex::encode(*entropy),
+    "3dd8cbc1b650d6e363f7fdb8bc1ee37e04100f2c4beedea1fd00644f15269f5b"
+  );
+}
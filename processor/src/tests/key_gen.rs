@@ -7,7 +7,7 @@ use rand_core::{RngCore, OsRng};
 use group::GroupEncoding;
 use frost::{Participant, ThresholdParams, tests::clone_without};
 
-use serai_db::{DbTxn, Db, MemDb};
+use serai_db::{Get, DbTxn, Db, MemDb};
 
 use sp_application_crypto::sr25519;
 use serai_client::{
@@ -23,6 +23,7 @@ use crate::{
 
 const ID: KeyGenId =
   KeyGenId { set: ValidatorSet { session: Session(1), network: NetworkId::Monero }, attempt: 3 };
+const SERAI_GENESIS: [u8; 32] = [0xff; 32];
 
 pub async fn test_key_gen<C: Coin>() {
   let mut entropies = HashMap::new();
@@ -48,9 +49,11 @@ pub async fn test_key_gen<C: Coin>() {
           id: ID,
           params: ThresholdParams::new(3, 5, Participant::new(u16::try_from(i).unwrap()).unwrap())
             .unwrap(),
+          serai_genesis: SERAI_GENESIS,
         },
       )
       .await
+      .unwrap()
     {
       assert_eq!(id, ID);
       all_commitments.insert(Participant::new(u16::try_from(i).unwrap()).unwrap(), commitments);
@@ -58,6 +61,7 @@ pub async fn test_key_gen<C: Coin>() {
       panic!("didn't get commitments back");
     }
     txn.commit();
+    assert_eq!(key_gen.attempts(ID.set), vec![ID]);
   }
 
   // 1 is rebuilt on every step
@@ -84,6 +88,7 @@ pub async fn test_key_gen<C: Coin>() {
         },
       )
       .await
+      .unwrap()
     {
       assert_eq!(id, ID);
       all_shares.insert(i, shares);
@@ -102,19 +107,50 @@ pub async fn test_key_gen<C: Coin>() {
     let key_gen = key_gens.get_mut(&i).unwrap();
     let mut txn = dbs.get_mut(&i).unwrap().txn();
     let i = Participant::new(u16::try_from(i).unwrap()).unwrap();
-    if let ProcessorMessage::GeneratedKeyPair { id, substrate_key, coin_key } = key_gen
-      .handle(
-        &mut txn,
-        CoordinatorMessage::Shares {
-          id: ID,
-          shares: all_shares
-            .iter()
-            .filter_map(|(l, shares)| if i == *l { None } else { Some((*l, shares[&i].clone())) })
-            .collect(),
-        },
-      )
-      .await
-    {
+    let shares = all_shares
+      .iter()
+      .filter_map(|(l, shares)| if i == *l { None } else { Some((*l, shares[&i].clone())) })
+      .collect::<HashMap<_, _>>();
+
+    // Have odd participants receive their shares one at a time, instead of all at once
+    let msg = if (u16::from(i) % 2) == 1 {
+      let share_msg = |participant, share| CoordinatorMessage::Share { id: ID, participant, share };
+      let complete = CoordinatorMessage::SharesComplete { id: ID };
+
+      // 1 is told the shares are complete before receiving them, the rest after
+      let mut msg = None;
+      if i == Participant::new(1).unwrap() {
+        assert!(key_gen.handle(&mut txn, complete.clone()).await.is_none());
+      }
+      for (l, share) in shares.clone() {
+        assert!(msg.is_none(), "handled shares before receiving all of them");
+        msg = key_gen.handle(&mut txn, share_msg(l, share.clone())).await;
+
+        // The same share, or a distinct share, resent by the same participant should be ignored
+        let mut distinct = share.clone();
+        distinct.push(0);
+        for resent in [share, distinct] {
+          assert!(key_gen.handle(&mut txn, share_msg(l, resent)).await.is_none());
+        }
+      }
+      // A share claiming to be from us should be ignored
+      assert!(key_gen.handle(&mut txn, share_msg(i, vec![])).await.is_none());
+      if i != Participant::new(1).unwrap() {
+        assert!(msg.is_none(), "handled shares before they were marked complete");
+        msg = key_gen.handle(&mut txn, complete.clone()).await;
+      }
+      assert!(msg.is_some());
+
+      // Once handled, the shares shouldn't be handled again
+      let (l, share) = shares.into_iter().next().unwrap();
+      assert!(key_gen.handle(&mut txn, share_msg(l, share)).await.is_none());
+      assert!(key_gen.handle(&mut txn, complete).await.is_none());
+      msg
+    } else {
+      key_gen.handle(&mut txn, CoordinatorMessage::Shares { id: ID, shares }).await
+    };
+
+    if let ProcessorMessage::GeneratedKeyPair { id, substrate_key, coin_key } = msg.unwrap() {
       assert_eq!(id, ID);
       if res.is_none() {
         res = Some((substrate_key, coin_key.clone()));
@@ -133,12 +169,23 @@ pub async fn test_key_gen<C: Coin>() {
 
   for i in 1 ..= 5 {
     let key_gen = key_gens.get_mut(&i).unwrap();
+    assert!(key_gen.latest_confirmed_key(ID.set).is_none());
     let mut txn = dbs.get_mut(&i).unwrap().txn();
     let KeyConfirmed { substrate_keys, coin_keys } = key_gen
       .confirm(&mut txn, ID.set, (sr25519::Public(res.0), res.1.clone().try_into().unwrap()))
       .await;
     txn.commit();
 
+    let key_pair = key_gen.latest_confirmed_key(ID.set).unwrap();
+    assert_eq!((key_pair.0 .0, key_pair.1.into_inner()), res);
+    // The attempts should be kept, despite the data for them being pruned
+    assert_eq!(key_gen.attempts(ID.set), vec![ID]);
+
+    // The data for every attempt should've been pruned
+    for dst in [b"commitments".as_ref(), b"partial", b"complete"] {
+      assert!(dbs[&i].get_prefix(MemDb::key(b"KEY_GEN", dst, [])).is_empty());
+    }
+
     let params =
       ThresholdParams::new(3, 5, Participant::new(u16::try_from(i).unwrap()).unwrap()).unwrap();
     assert_eq!(substrate_keys.params(), params);
@@ -0,0 +1,248 @@
+use core::time::Duration;
+use std::collections::HashMap;
+
+use rand_core::OsRng;
+
+use group::GroupEncoding;
+use frost::{Participant, curve::Ristretto};
+
+use tokio::time::timeout;
+
+use serai_db::{Get, DbTxn, Db, MemDb};
+
+use messages::sign::{SignId, ProcessorMessage};
+
+use crate::{
+  Payment, Plan,
+  coins::{OutputType, Output, Block, Transaction, Coin, Mock},
+  scanner::{ScannerEvent, Scanner},
+  signer::{LINGERING_REBROADCASTS, SignerEvent},
+  tests::{sign_with_signers, sign_attempt},
+};
+
+async fn mock() -> Mock {
+  Mock::new()
+}
+
+test_coin!(
+  Mock,
+  mock,
+  mock_key_gen,
+  mock_scanner,
+  mock_signer,
+  mock_wallet,
+  mock_addresses,
+);
+
+async_sequential! {
+  async fn mock_reorg() {
+    let coin = mock().await;
+
+    let keys = frost::tests::key_gen::<_, Ristretto>(&mut OsRng)
+      .remove(&Participant::new(1).unwrap())
+      .unwrap();
+    let key = keys.group_key();
+
+    for _ in 0 .. Mock::CONFIRMATIONS {
+      coin.mine_block().await;
+    }
+
+    let mut db = MemDb::new();
+    let (mut scanner, _) = Scanner::new(coin.clone(), db.clone());
+    let mut txn = db.txn();
+    scanner.rotate_key(&mut txn, coin.get_latest_block_number().await.unwrap(), key).await;
+    txn.commit();
+
+    // Receive funds in a block which is then reorganized out, before it's confirmed
+    coin.deposit(Mock::address(key), 100_000_000, vec![]);
+    coin.mine_block().await;
+    let number = coin.get_latest_block_number().await.unwrap();
+    let orphaned = coin.get_block(number).await.unwrap().id();
+    coin.reorg(1);
+
+    // The deposit should be included in the block replacing it
+    for _ in 0 .. Mock::CONFIRMATIONS {
+      coin.mine_block().await;
+    }
+    let block = coin.get_block(number).await.unwrap().id();
+    assert!(block != orphaned);
+
+    match timeout(Duration::from_secs(30), scanner.events.recv()).await.unwrap().unwrap() {
+      ScannerEvent::Block { key: this_key, block: this_block, batch, outputs } => {
+        assert_eq!(this_key, key);
+        assert_eq!(this_block, block);
+        assert_eq!(batch, 0);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].kind(), OutputType::External);
+        assert_eq!(outputs[0].amount(), 100_000_000);
+      }
+      ScannerEvent::Completed(_, _) => {
+        panic!("unexpectedly got eventuality completion");
+      }
+    }
+  }
+
+  async fn mock_rebroadcast() {
+    let coin = mock().await;
+
+    let mut keys = frost::tests::key_gen::<_, Ristretto>(&mut OsRng);
+    for (_, keys) in keys.iter_mut() {
+      Mock::tweak_keys(keys);
+    }
+    let key = keys[&Participant::new(1).unwrap()].group_key();
+
+    for _ in 0 .. Mock::CONFIRMATIONS {
+      coin.mine_block().await;
+    }
+
+    let mut db = MemDb::new();
+    let (mut scanner, _) = Scanner::new(coin.clone(), db.clone());
+    let mut txn = db.txn();
+    scanner.rotate_key(&mut txn, coin.get_latest_block_number().await.unwrap(), key).await;
+    txn.commit();
+
+    let outputs = coin.get_outputs(&coin.test_send(Mock::address(key)).await, key).await.unwrap();
+    match timeout(Duration::from_secs(30), scanner.events.recv()).await.unwrap().unwrap() {
+      ScannerEvent::Block { .. } => {}
+      ScannerEvent::Completed(_, _) => {
+        panic!("unexpectedly got eventuality completion");
+      }
+    }
+
+    let block_number = coin.get_latest_block_number().await.unwrap();
+    let fee = coin.get_fee().await;
+    let plan = Plan {
+      key,
+      inputs: outputs.clone(),
+      payments: vec![Payment { address: Mock::address(key), data: None, amount: 2 * Mock::DUST }],
+      change: Some(key),
+    };
+    let mut keys_txs = HashMap::new();
+    for (i, keys) in keys.drain() {
+      let (signable, eventuality) =
+        coin.prepare_send(keys.clone(), block_number, plan.clone(), fee).await.unwrap().0.unwrap();
+      // Every signer has the same eventuality, so the scanner only has to track one of them
+      if keys_txs.is_empty() {
+        scanner.register_eventuality(block_number, [0xaa; 32], eventuality.clone()).await;
+      }
+      keys_txs.insert(i, (keys, (signable, eventuality)));
+    }
+
+    let (txid, mut signers, mut dbs) = sign_with_signers(coin.clone(), keys_txs).await;
+    // How many transactions each signer is rebroadcasting
+    let rebroadcasts = |dbs: &HashMap<Participant, MemDb>| {
+      let prefix = MemDb::key(b"SIGNER", b"rebroadcast", b"");
+      dbs.values().map(|db| db.get_prefix(&prefix).len()).collect::<Vec<_>>()
+    };
+    assert!(rebroadcasts(&dbs).iter().any(|rebroadcasts| *rebroadcasts != 0));
+
+    // Drop the transaction from the mempool, as if it was evicted
+    assert!(coin.drop_transaction(txid));
+    assert!(coin.get_transaction(&txid).await.is_err());
+
+    // Rebroadcasting should put it back
+    for (i, signer) in signers.iter_mut() {
+      signer.rebroadcast(&dbs[i]).await;
+    }
+    assert_eq!(coin.get_transaction(&txid).await.unwrap().id(), txid);
+
+    // If the fee rises from its initial rate of 1, it should linger in the mempool
+    coin.set_fee(2);
+    coin.mine_block().await;
+    let latest = coin.get_block(coin.get_latest_block_number().await.unwrap()).await.unwrap();
+    assert!(coin.get_outputs(&latest, key).await.unwrap().is_empty());
+
+    // Until it's rebroadcast enough times for the signers who published it to request a fee bump
+    for (i, signer) in signers.iter_mut() {
+      for _ in 1 .. LINGERING_REBROADCASTS {
+        signer.rebroadcast(&dbs[i]).await;
+      }
+      if dbs[i].get_prefix(MemDb::key(b"SIGNER", b"rebroadcast", b"")).is_empty() {
+        assert!(signer.events.pop_front().is_none());
+        continue;
+      }
+      match signer.events.pop_front().unwrap() {
+        SignerEvent::ProcessorMessage(ProcessorMessage::RequestFeeBump {
+          key: this_key,
+          id,
+          bumps,
+        }) => {
+          assert_eq!(this_key, key.to_bytes().to_vec());
+          assert_eq!(id, [0xaa; 32]);
+          assert_eq!(bumps, 1);
+        }
+        _ => panic!("signer didn't request a fee bump"),
+      }
+      assert!(signer.events.pop_front().is_none());
+    }
+
+    // Re-sign it with a bumped fee, as the coordinator would have every signer do
+    let bumped_id = SignId { key: key.to_bytes().to_vec(), id: [0xaa; 32], attempt: 1 };
+    let bumped_fee = Mock::bump_fee(fee, 1);
+    for (i, signer) in signers.iter_mut() {
+      let (signable, _) = coin
+        .prepare_send(signer.keys(), block_number, plan.clone(), bumped_fee)
+        .await
+        .unwrap()
+        .0
+        .unwrap();
+      let mut txn = dbs.get_mut(i).unwrap().txn();
+      assert!(signer.bump_fee(&mut txn, bumped_id.clone(), 1, signable).await);
+      txn.commit();
+    }
+    let bumped = sign_attempt(&mut signers, &mut dbs, bumped_id).await;
+    assert!(bumped != txid);
+
+    // The bumped transaction should replace the lingering one, and be confirmed
+    assert!(coin.get_transaction(&txid).await.is_err());
+    assert_eq!(coin.get_transaction(&bumped).await.unwrap().id(), bumped);
+    for _ in 0 .. Mock::CONFIRMATIONS {
+      coin.mine_block().await;
+    }
+
+    // Once it's confirmed, the scanner should inform us of its completion
+    let (id, tx) = timeout(Duration::from_secs(30), async {
+      loop {
+        match scanner.events.recv().await.unwrap() {
+          ScannerEvent::Block { .. } => {}
+          ScannerEvent::Completed(id, tx) => break (id, tx),
+        }
+      }
+    })
+    .await
+    .unwrap();
+    assert_eq!((id, tx), ([0xaa; 32], bumped));
+
+    // Which should only be reported by signers who didn't sign it themselves
+    let completed = MemDb::key(b"SIGNER", b"completed", id);
+    for (i, signer) in signers.iter_mut() {
+      let signed = dbs[i].get(&completed).map(|txs| txs.ends_with(&bumped)).unwrap_or(false);
+
+      let db = dbs.get_mut(i).unwrap();
+      let mut txn = db.txn();
+      signer.eventuality_completion(&mut txn, id, &tx).await;
+      // At which point we should stop rebroadcasting it
+      signer.confirmed_completion(&mut txn, id);
+      txn.commit();
+
+      if signed {
+        assert!(signer.events.pop_front().is_none());
+      } else {
+        match signer.events.pop_front().unwrap() {
+          SignerEvent::SignedTransaction { id: this_id, tx: this_tx } => {
+            assert_eq!((this_id, this_tx), (id, bumped));
+          }
+          _ => panic!("signer didn't report the plan's completion"),
+        }
+      }
+      assert!(dbs[i].get(&completed).unwrap().ends_with(&bumped));
+
+      // Further reports of its completion should be ignored
+      let mut txn = dbs.get_mut(i).unwrap().txn();
+      signer.eventuality_completion(&mut txn, id, &tx).await;
+      txn.commit();
+      assert!(signer.events.pop_front().is_none());
+    }
+    assert!(rebroadcasts(&dbs).iter().all(|rebroadcasts| *rebroadcasts == 0));
+  }
+}
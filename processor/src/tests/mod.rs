@@ -5,10 +5,16 @@ mod scanner;
 pub(crate) use scanner::test_scanner;
 
 mod signer;
-pub(crate) use signer::{sign, test_signer};
+pub(crate) use signer::{sign, sign_with_signers, sign_attempt, test_signer};
 
 mod substrate_signer;
 
+mod coordinator;
+
+mod entropy;
+
+mod pool;
+
 mod wallet;
 pub(crate) use wallet::test_wallet;
 
@@ -97,3 +103,4 @@ macro_rules! test_coin {
 }
 
 mod literal;
+mod mock;
@@ -0,0 +1,105 @@
+use std::sync::{
+  Arc,
+  atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use crate::coins::RpcPool;
+
+#[derive(Clone, Debug)]
+struct TestRpc {
+  value: u64,
+  failing: Arc<AtomicBool>,
+  requests: Arc<AtomicUsize>,
+}
+
+impl TestRpc {
+  fn new(value: u64) -> TestRpc {
+    TestRpc {
+      value,
+      failing: Arc::new(AtomicBool::new(false)),
+      requests: Arc::new(AtomicUsize::new(0)),
+    }
+  }
+
+  fn failing(value: u64) -> TestRpc {
+    let rpc = TestRpc::new(value);
+    rpc.failing.store(true, Ordering::Relaxed);
+    rpc
+  }
+
+  fn requests(&self) -> usize {
+    self.requests.load(Ordering::Relaxed)
+  }
+
+  async fn get(&self) -> Result<u64, ()> {
+    self.requests.fetch_add(1, Ordering::Relaxed);
+    if self.failing.load(Ordering::Relaxed) {
+      Err(())?;
+    }
+    Ok(self.value)
+  }
+}
+
+#[tokio::test]
+async fn failover() {
+  let failing = TestRpc::failing(1);
+  let working = TestRpc::new(1);
+  let pool = RpcPool::new(vec![failing.clone(), working.clone()], 1);
+
+  // The failing endpoint should be tried, and then the request should fail over
+  assert_eq!(pool.request(|rpc| async move { rpc.get().await }).await.unwrap(), 1);
+  assert_eq!(failing.requests(), 1);
+  assert_eq!(working.requests(), 1);
+  assert!(!pool.healthy(0));
+  assert!(pool.healthy(1));
+
+  // Further requests should go straight to the working endpoint
+  assert_eq!(pool.request(|rpc| async move { rpc.get().await }).await.unwrap(), 1);
+  assert_eq!(failing.requests(), 1);
+  assert_eq!(working.requests(), 2);
+
+  // If every endpoint fails, the request should error
+  working.failing.store(true, Ordering::Relaxed);
+  assert!(pool.request(|rpc| async move { rpc.get().await }).await.is_err());
+  assert_eq!(failing.requests(), 2);
+  assert_eq!(working.requests(), 3);
+}
+
+#[tokio::test]
+async fn health_check() {
+  let first = TestRpc::new(1);
+  let second = TestRpc::new(1);
+  let pool = RpcPool::new(vec![first.clone(), second.clone()], 1);
+  assert_eq!(pool.check_health(|rpc| async move { rpc.get().await }).await, 2);
+
+  // An endpoint failing its health check shouldn't be used while a healthy endpoint exists
+  first.failing.store(true, Ordering::Relaxed);
+  assert_eq!(pool.check_health(|rpc| async move { rpc.get().await }).await, 1);
+  assert!(!pool.healthy(0));
+  let before = first.requests();
+  assert_eq!(pool.request(|rpc| async move { rpc.get().await }).await.unwrap(), 1);
+  assert_eq!(first.requests(), before);
+
+  // Once it recovers, the health check should mark it as healthy again
+  first.failing.store(false, Ordering::Relaxed);
+  assert_eq!(pool.check_health(|rpc| async move { rpc.get().await }).await, 2);
+  assert!(pool.healthy(0));
+}
+
+#[tokio::test]
+async fn agreement() {
+  // A single disagreeing endpoint should be outvoted
+  let pool = RpcPool::new(vec![TestRpc::new(1), TestRpc::new(2), TestRpc::new(1)], 2);
+  assert_eq!(pool.agreed(|rpc| async move { rpc.get().await }).await.unwrap(), 1);
+
+  // Yet if it's joined by a failing endpoint, no value has sufficient agreement
+  let pool = RpcPool::new(vec![TestRpc::new(1), TestRpc::new(2), TestRpc::failing(1)], 2);
+  assert!(pool.agreed(|rpc| async move { rpc.get().await }).await.is_err());
+  assert!(!pool.healthy(2));
+
+  // Requiring every endpoint to agree should error on any disagreement
+  let pool = RpcPool::new(vec![TestRpc::new(1), TestRpc::new(2)], 2);
+  assert!(pool.agreed(|rpc| async move { rpc.get().await }).await.is_err());
+  let pool = RpcPool::new(vec![TestRpc::new(1), TestRpc::new(1)], 2);
+  assert_eq!(pool.agreed(|rpc| async move { rpc.get().await }).await.unwrap(), 1);
+}
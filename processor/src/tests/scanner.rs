@@ -7,13 +7,20 @@ use frost::Participant;
 
 use tokio::time::timeout;
 
-use serai_client::primitives::BlockHash;
+use scale::Encode;
+
+use serai_client::{
+  primitives::{MAX_DATA_LEN, BlockHash, Coin as SeraiCoin, Amount, Balance, Data, SeraiAddress},
+  in_instructions::primitives::{
+    MAX_BATCH_SIZE, Application, ApplicationCall, InInstruction, InInstructionWithBalance, Batch,
+  },
+};
 
 use serai_db::{DbTxn, Db, MemDb};
 
 use crate::{
   coins::{OutputType, Output, Block, Coin},
-  scanner::{ScannerEvent, Scanner, ScannerHandle},
+  scanner::{MAX_BATCH_INSTRUCTIONS, batch_instructions, ScannerEvent, Scanner, ScannerHandle},
 };
 
 pub async fn test_scanner<C: Coin>(coin: C) {
@@ -102,3 +109,48 @@ pub async fn test_scanner<C: Coin>(coin: C) {
   // Create a new scanner off the current DB and make sure it also does nothing
   assert!(timeout(Duration::from_secs(30), new_scanner().await.events.recv()).await.is_err());
 }
+
+#[test]
+fn test_batch_instructions() {
+  // A block without instructions still has a single, empty Batch
+  assert_eq!(batch_instructions(vec![]), vec![vec![]]);
+
+  let balance = Balance { coin: SeraiCoin::Bitcoin, amount: Amount(1) };
+  let mut instructions = vec![];
+  // Small instructions, which should be bound by the amount of instructions per Batch
+  for i in 0 .. (MAX_BATCH_INSTRUCTIONS + 1) {
+    instructions.push(InInstructionWithBalance {
+      instruction: InInstruction::Transfer(SeraiAddress([u8::try_from(i % 256).unwrap(); 32])),
+      balance,
+    });
+  }
+  // Large instructions, which should be bound by the size of a Batch
+  for _ in 0 .. ((2 * MAX_BATCH_SIZE) / usize::try_from(MAX_DATA_LEN).unwrap()) {
+    instructions.push(InInstructionWithBalance {
+      instruction: InInstruction::Call(ApplicationCall {
+        application: Application::DEX,
+        data: Data::new(vec![0xdd; usize::try_from(MAX_DATA_LEN).unwrap()]).unwrap(),
+      }),
+      balance,
+    });
+  }
+
+  let batches = batch_instructions(instructions.clone());
+  assert!(batches.len() > 3);
+  assert_eq!(batches[0].len(), MAX_BATCH_INSTRUCTIONS);
+  for (i, batch) in batches.iter().enumerate() {
+    assert!(!batch.is_empty());
+    assert!(batch.len() <= MAX_BATCH_INSTRUCTIONS);
+    let batch = Batch {
+      network: SeraiCoin::Bitcoin.network(),
+      id: u32::try_from(i).unwrap(),
+      block: BlockHash([0xff; 32]),
+      instructions: batch.clone(),
+    };
+    assert!(batch.encode().len() <= MAX_BATCH_SIZE);
+  }
+  // The instructions should be preserved, in order
+  assert_eq!(batches.concat(), instructions);
+  // And batching should be deterministic
+  assert_eq!(batch_instructions(instructions), batches);
+}
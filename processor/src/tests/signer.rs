@@ -20,11 +20,27 @@ use crate::{
 #[allow(clippy::type_complexity)]
 pub async fn sign<C: Coin>(
   coin: C,
-  mut keys_txs: HashMap<
+  keys_txs: HashMap<
     Participant,
     (ThresholdKeys<C::Curve>, (C::SignableTransaction, C::Eventuality)),
   >,
 ) -> <C::Transaction as Transaction<C>>::Id {
+  sign_with_signers(coin, keys_txs).await.0
+}
+
+// Sign a transaction, also returning the signers and their databases
+#[allow(clippy::type_complexity)]
+pub async fn sign_with_signers<C: Coin>(
+  coin: C,
+  mut keys_txs: HashMap<
+    Participant,
+    (ThresholdKeys<C::Curve>, (C::SignableTransaction, C::Eventuality)),
+  >,
+) -> (
+  <C::Transaction as Transaction<C>>::Id,
+  HashMap<Participant, Signer<C, MemDb>>,
+  HashMap<Participant, MemDb>,
+) {
   let actual_id = SignId {
     key: keys_txs[&Participant::new(1).unwrap()].0.group_key().to_bytes().as_ref().to_vec(),
     id: [0xaa; 32],
@@ -40,11 +56,9 @@ pub async fn sign<C: Coin>(
 
   let mut signers = HashMap::new();
   let mut dbs = HashMap::new();
-  let mut t = 0;
   for i in 1 ..= keys.len() {
     let i = Participant::new(u16::try_from(i).unwrap()).unwrap();
     let keys = keys.remove(&i).unwrap();
-    t = keys.params().t();
     signers.insert(i, Signer::<_, MemDb>::new(coin.clone(), keys));
     dbs.insert(i, MemDb::new());
   }
@@ -58,6 +72,16 @@ pub async fn sign<C: Coin>(
     txn.commit();
   }
 
+  (sign_attempt(&mut signers, &mut dbs, actual_id).await, signers, dbs)
+}
+
+// Complete an attempt every signer has already started, with a random signing set
+pub async fn sign_attempt<C: Coin>(
+  signers: &mut HashMap<Participant, Signer<C, MemDb>>,
+  dbs: &mut HashMap<Participant, MemDb>,
+  actual_id: SignId,
+) -> <C::Transaction as Transaction<C>>::Id {
+  let t = signers[&Participant::new(1).unwrap()].keys().params().t();
   let mut signing_set = vec![];
   while signing_set.len() < usize::from(t) {
     let candidate = Participant::new(
@@ -139,7 +163,7 @@ pub async fn sign<C: Coin>(
   }
 
   // Make sure there's no events left
-  for (_, mut signer) in signers.drain() {
+  for signer in signers.values_mut() {
     assert!(signer.events.pop_front().is_none());
   }
 
@@ -19,34 +19,12 @@ use serai_client::{primitives::*, in_instructions::primitives::*};
 use messages::{sign::SignId, coordinator::*};
 use crate::substrate_signer::{SubstrateSignerEvent, SubstrateSigner};
 
-#[tokio::test]
-async fn test_substrate_signer() {
+// Sign every batch, which may be for the same block, checking each is signed
+async fn sign_batches(batches: Vec<Batch>) {
   let mut keys = key_gen::<_, Ristretto>(&mut OsRng);
 
   let participant_one = Participant::new(1).unwrap();
-
-  let block = BlockHash([0xaa; 32]);
-  let actual_id =
-    SignId { key: keys[&participant_one].group_key().to_bytes().to_vec(), id: block.0, attempt: 0 };
-
-  let batch = Batch {
-    network: NetworkId::Monero,
-    id: 5,
-    block,
-    instructions: vec![
-      InInstructionWithBalance {
-        instruction: InInstruction::Transfer(SeraiAddress([0xbb; 32])),
-        balance: Balance { coin: Coin::Bitcoin, amount: Amount(1000) },
-      },
-      InInstructionWithBalance {
-        instruction: InInstruction::Call(ApplicationCall {
-          application: Application::DEX,
-          data: Data::new(vec![0xcc; 128]).unwrap(),
-        }),
-        balance: Balance { coin: Coin::Monero, amount: Amount(9999999999999999) },
-      },
-    ],
-  };
+  let key = keys[&participant_one].group_key().to_bytes().to_vec();
 
   let mut signers = HashMap::new();
   let mut dbs = HashMap::new();
@@ -59,7 +37,9 @@ async fn test_substrate_signer() {
     let mut signer = SubstrateSigner::<MemDb>::new(keys);
     let mut db = MemDb::new();
     let mut txn = db.txn();
-    signer.sign(&mut txn, batch.clone()).await;
+    for batch in &batches {
+      signer.sign(&mut txn, batch.clone()).await;
+    }
     txn.commit();
 
     signers.insert(i, signer);
@@ -67,85 +47,101 @@ async fn test_substrate_signer() {
   }
   drop(keys);
 
-  let mut signing_set = vec![];
-  while signing_set.len() < usize::from(t) {
-    let candidate = Participant::new(
-      u16::try_from((OsRng.next_u64() % u64::try_from(signers.len()).unwrap()) + 1).unwrap(),
-    )
-    .unwrap();
-    if signing_set.contains(&candidate) {
-      continue;
-    }
-    signing_set.push(candidate);
-  }
-
-  // All participants should emit a preprocess
-  let mut preprocesses = HashMap::new();
-  for i in 1 ..= signers.len() {
-    let i = Participant::new(u16::try_from(i).unwrap()).unwrap();
-    if let SubstrateSignerEvent::ProcessorMessage(ProcessorMessage::BatchPreprocess {
-      id,
-      preprocess,
-    }) = signers.get_mut(&i).unwrap().events.pop_front().unwrap()
-    {
-      assert_eq!(id, actual_id);
-      if signing_set.contains(&i) {
-        preprocesses.insert(i, preprocess);
+  // All participants should emit a preprocess for every batch
+  let mut all_preprocesses = HashMap::new();
+  for (i, signer) in signers.iter_mut() {
+    for batch in &batches {
+      if let SubstrateSignerEvent::ProcessorMessage(ProcessorMessage::BatchPreprocess {
+        id,
+        block,
+        preprocess,
+      }) = signer.events.pop_front().unwrap()
+      {
+        assert_eq!(
+          id,
+          SignId { key: key.clone(), id: batch_sign_id(batch.network, batch.id), attempt: 0 }
+        );
+        assert_eq!(block, batch.block);
+        all_preprocesses.entry(id.id).or_insert_with(HashMap::new).insert(*i, preprocess);
+      } else {
+        panic!("didn't get preprocess back");
       }
-    } else {
-      panic!("didn't get preprocess back");
     }
+    assert!(signer.events.is_empty());
   }
 
-  let mut shares = HashMap::new();
-  for i in &signing_set {
-    let mut txn = dbs.get_mut(i).unwrap().txn();
-    signers
-      .get_mut(i)
-      .unwrap()
-      .handle(
-        &mut txn,
-        CoordinatorMessage::BatchPreprocesses {
-          id: actual_id.clone(),
-          preprocesses: clone_without(&preprocesses, i),
-        },
-      )
-      .await;
-    txn.commit();
+  for batch in &batches {
+    let actual_id =
+      SignId { key: key.clone(), id: batch_sign_id(batch.network, batch.id), attempt: 0 };
 
-    if let SubstrateSignerEvent::ProcessorMessage(ProcessorMessage::BatchShare { id, share }) =
-      signers.get_mut(i).unwrap().events.pop_front().unwrap()
-    {
-      assert_eq!(id, actual_id);
-      shares.insert(*i, share);
-    } else {
-      panic!("didn't get share back");
+    let mut signing_set = vec![];
+    while signing_set.len() < usize::from(t) {
+      let candidate = Participant::new(
+        u16::try_from((OsRng.next_u64() % u64::try_from(signers.len()).unwrap()) + 1).unwrap(),
+      )
+      .unwrap();
+      if signing_set.contains(&candidate) {
+        continue;
+      }
+      signing_set.push(candidate);
     }
-  }
 
-  for i in &signing_set {
-    let mut txn = dbs.get_mut(i).unwrap().txn();
-    signers
-      .get_mut(i)
-      .unwrap()
-      .handle(
-        &mut txn,
-        CoordinatorMessage::BatchShares {
-          id: actual_id.clone(),
-          shares: clone_without(&shares, i),
-        },
-      )
-      .await;
-    txn.commit();
+    let preprocesses = all_preprocesses[&actual_id.id]
+      .iter()
+      .filter(|(i, _)| signing_set.contains(i))
+      .map(|(i, preprocess)| (*i, preprocess.clone()))
+      .collect::<HashMap<_, _>>();
+
+    let mut shares = HashMap::new();
+    for i in &signing_set {
+      let mut txn = dbs.get_mut(i).unwrap().txn();
+      signers
+        .get_mut(i)
+        .unwrap()
+        .handle(
+          &mut txn,
+          CoordinatorMessage::BatchPreprocesses {
+            id: actual_id.clone(),
+            preprocesses: clone_without(&preprocesses, i),
+          },
+        )
+        .await;
+      txn.commit();
+
+      if let SubstrateSignerEvent::ProcessorMessage(ProcessorMessage::BatchShare { id, share }) =
+        signers.get_mut(i).unwrap().events.pop_front().unwrap()
+      {
+        assert_eq!(id, actual_id);
+        shares.insert(*i, share);
+      } else {
+        panic!("didn't get share back");
+      }
+    }
 
-    if let SubstrateSignerEvent::SignedBatch(signed_batch) =
-      signers.get_mut(i).unwrap().events.pop_front().unwrap()
-    {
-      assert_eq!(signed_batch.batch, batch);
-      assert!(Public::from_raw(actual_id.key.clone().try_into().unwrap())
-        .verify(&batch.encode(), &signed_batch.signature));
-    } else {
-      panic!("didn't get signed batch back");
+    for i in &signing_set {
+      let mut txn = dbs.get_mut(i).unwrap().txn();
+      signers
+        .get_mut(i)
+        .unwrap()
+        .handle(
+          &mut txn,
+          CoordinatorMessage::BatchShares {
+            id: actual_id.clone(),
+            shares: clone_without(&shares, i),
+          },
+        )
+        .await;
+      txn.commit();
+
+      if let SubstrateSignerEvent::SignedBatch(signed_batch) =
+        signers.get_mut(i).unwrap().events.pop_front().unwrap()
+      {
+        assert_eq!(&signed_batch.batch, batch);
+        assert!(Public::from_raw(actual_id.key.clone().try_into().unwrap())
+          .verify(&batch.encode(), &signed_batch.signature));
+      } else {
+        panic!("didn't get signed batch back");
+      }
     }
   }
 
@@ -154,3 +150,36 @@ async fn test_substrate_signer() {
     assert!(signer.events.pop_front().is_none());
   }
 }
+
+fn batch(id: u32, block: BlockHash) -> Batch {
+  Batch {
+    network: NetworkId::Monero,
+    id,
+    block,
+    instructions: vec![
+      InInstructionWithBalance {
+        instruction: InInstruction::Transfer(SeraiAddress([0xbb; 32])),
+        balance: Balance { coin: Coin::Bitcoin, amount: Amount(1000) },
+      },
+      InInstructionWithBalance {
+        instruction: InInstruction::Call(ApplicationCall {
+          application: Application::DEX,
+          data: Data::new(vec![0xcc; 128]).unwrap(),
+        }),
+        balance: Balance { coin: Coin::Monero, amount: Amount(9999999999999999) },
+      },
+    ],
+  }
+}
+
+#[tokio::test]
+async fn test_substrate_signer() {
+  sign_batches(vec![batch(5, BlockHash([0xaa; 32]))]).await;
+}
+
+#[tokio::test]
+async fn test_substrate_signer_multiple_batches() {
+  // A block whose instructions were split across several batches should have every batch signed
+  let block = BlockHash([0xaa; 32]);
+  sign_batches(vec![batch(5, block), batch(6, block), batch(7, block)]).await;
+}
@@ -20,7 +20,8 @@ impl Address {
 impl FromStr for Address {
   type Err = AddressError;
   fn from_str(str: &str) -> Result<Address, AddressError> {
-    MoneroAddress::from_str(Network::Mainnet, str).map(Address)
+    // Serai doesn't support payment IDs, so reject the address bytes for integrated addresses
+    Address::new(MoneroAddress::from_str(Network::Mainnet, str)?).ok_or(AddressError::InvalidByte)
   }
 }
 
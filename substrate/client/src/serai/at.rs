@@ -0,0 +1,105 @@
+use sp_core::sr25519::Public;
+
+use crate::{
+  primitives::{NetworkId, Coin, Amount, SeraiAddress, BlockHash},
+  validator_sets::primitives::{Session, ValidatorSet, ValidatorSetData, KeyPair},
+  tokens::TokensEvent,
+  in_instructions::InInstructionsEvent,
+  validator_sets::ValidatorSetsEvent,
+  Serai, SeraiError,
+};
+
+/// A view of Serai's state as of a specific block.
+///
+/// Every read made through this view is pinned to the same block, so multiple reads are
+/// guaranteed to be consistent with each other.
+#[derive(Clone, Copy)]
+pub struct SeraiAt<'a>(&'a Serai, [u8; 32]);
+
+impl Serai {
+  /// Read Serai's state as of the specified block.
+  pub fn at(&self, block: [u8; 32]) -> SeraiAt<'_> {
+    SeraiAt(self, block)
+  }
+}
+
+impl SeraiAt<'_> {
+  /// The hash of the block this view is pinned to.
+  pub fn block(&self) -> [u8; 32] {
+    self.1
+  }
+
+  pub async fn get_mint_events(&self) -> Result<Vec<TokensEvent>, SeraiError> {
+    self.0.get_mint_events(self.1).await
+  }
+
+  pub async fn get_burn_events(&self) -> Result<Vec<TokensEvent>, SeraiError> {
+    self.0.get_burn_events(self.1).await
+  }
+
+  pub async fn get_token_supply(&self, coin: Coin) -> Result<Amount, SeraiError> {
+    self.0.get_token_supply(self.1, coin).await
+  }
+
+  pub async fn get_token_balance(
+    &self,
+    coin: Coin,
+    address: SeraiAddress,
+  ) -> Result<Amount, SeraiError> {
+    self.0.get_token_balance(self.1, coin, address).await
+  }
+
+  pub async fn get_latest_block_for_network(
+    &self,
+    network: NetworkId,
+  ) -> Result<Option<BlockHash>, SeraiError> {
+    self.0.get_latest_block_for_network(self.1, network).await
+  }
+
+  pub async fn get_batch_events(&self) -> Result<Vec<InInstructionsEvent>, SeraiError> {
+    self.0.get_batch_events(self.1).await
+  }
+
+  pub async fn get_new_set_events(&self) -> Result<Vec<ValidatorSetsEvent>, SeraiError> {
+    self.0.get_new_set_events(self.1).await
+  }
+
+  pub async fn get_vote_events(&self) -> Result<Vec<ValidatorSetsEvent>, SeraiError> {
+    self.0.get_vote_events(self.1).await
+  }
+
+  pub async fn get_key_gen_events(&self) -> Result<Vec<ValidatorSetsEvent>, SeraiError> {
+    self.0.get_key_gen_events(self.1).await
+  }
+
+  pub async fn get_session(&self, network: NetworkId) -> Result<Option<Session>, SeraiError> {
+    self.0.get_session(self.1, network).await
+  }
+
+  pub async fn get_validator_set(
+    &self,
+    set: ValidatorSet,
+  ) -> Result<Option<ValidatorSetData>, SeraiError> {
+    self.0.get_validator_set(self.1, set).await
+  }
+
+  /// Get the current validator set for a network, along with its data.
+  pub async fn get_current_validator_set(
+    &self,
+    network: NetworkId,
+  ) -> Result<Option<(ValidatorSet, ValidatorSetData)>, SeraiError> {
+    self.0.get_current_validator_set(self.1, network).await
+  }
+
+  /// Get the participants of a validator set, along with the amount they each have bonded.
+  pub async fn get_validator_set_participants(
+    &self,
+    set: ValidatorSet,
+  ) -> Result<Option<Vec<(Public, Amount)>>, SeraiError> {
+    self.0.get_validator_set_participants(self.1, set).await
+  }
+
+  pub async fn get_keys(&self, set: ValidatorSet) -> Result<Option<KeyPair>, SeraiError> {
+    self.0.get_keys(self.1, set).await
+  }
+}
@@ -0,0 +1,122 @@
+use core::time::Duration;
+use std::collections::VecDeque;
+
+use scale::Decode;
+
+use futures::{
+  stream::{self, Stream},
+  future::try_join_all,
+};
+use tokio::time::sleep;
+
+pub use serai_runtime::RuntimeEvent as SeraiEvent;
+
+use crate::{SeraiError, Serai};
+
+// How long to wait before polling for a new finalized block
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// How long to wait before reconnecting after an RPC error
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// How many blocks to request at once when fetching a range of blocks
+const RANGE_BATCH_SIZE: u64 = 64;
+
+impl Serai {
+  /// Get every event emitted by a finalized block, by the block's number.
+  ///
+  /// Returns None if the block either doesn't exist or isn't finalized yet.
+  pub async fn get_finalized_events(
+    &self,
+    number: u64,
+  ) -> Result<Option<([u8; 32], Vec<SeraiEvent>)>, SeraiError> {
+    let Some(block) = self.get_block_by_number(number).await? else { return Ok(None) };
+    let hash = block.hash();
+    Ok(Some((hash, self.all_events(hash).await?)))
+  }
+
+  async fn all_events(&self, hash: [u8; 32]) -> Result<Vec<SeraiEvent>, SeraiError> {
+    let mut res = vec![];
+    for event in self.rpc(|| self.client.events().at(hash.into())).await?.iter() {
+      let event = event.map_err(|_| SeraiError::InvalidRuntime)?;
+      let mut encoded: &[u8] =
+        &[[event.pallet_index(), event.variant_index()].as_ref(), event.field_bytes()].concat();
+      res.push(SeraiEvent::decode(&mut encoded).map_err(|_| SeraiError::InvalidRuntime)?);
+    }
+    Ok(res)
+  }
+
+  /// Get the events emitted by every block numbered within `from ..= to`, alongside the hash of
+  /// the block which emitted them.
+  ///
+  /// Requests for multiple blocks are made concurrently, instead of serially. Returns None if any
+  /// block in the range isn't finalized yet.
+  pub async fn get_events_range(
+    &self,
+    from: u64,
+    to: u64,
+    filter: impl Fn(&SeraiEvent) -> bool,
+  ) -> Result<Option<Vec<([u8; 32], Vec<SeraiEvent>)>>, SeraiError> {
+    // Since every block in the range is at most the latest finalized block, the hashes for their
+    // numbers will be of finalized blocks, removing the need to check each individually
+    if to > self.get_latest_block().await?.number() {
+      return Ok(None);
+    }
+
+    let mut res = vec![];
+    let mut start = from;
+    while start <= to {
+      let end = to.min(start + RANGE_BATCH_SIZE - 1);
+      let blocks = try_join_all((start ..= end).map(|number| async move {
+        let hash: [u8; 32] = self
+          .rpc(|| self.client.rpc().block_hash(Some(number.into())))
+          .await?
+          // This is an error since there is a finalized block at this index
+          .ok_or(SeraiError::InvalidNode)?
+          .into();
+        Ok::<_, SeraiError>((hash, self.all_events(hash).await?))
+      }))
+      .await?;
+
+      res.extend(blocks.into_iter().map(|(hash, events)| {
+        (hash, events.into_iter().filter(|event| filter(event)).collect::<Vec<_>>())
+      }));
+      start = end + 1;
+    }
+    Ok(Some(res))
+  }
+
+  /// Subscribe to the events emitted by finalized blocks, starting with the block numbered
+  /// `start`.
+  ///
+  /// Every event is yielded alongside the hash of the block which emitted it, in order. RPC errors
+  /// are handled by reconnecting and resuming from the block which failed to be fetched. Any other
+  /// error is yielded and ends the stream.
+  pub fn subscribe_events(
+    &self,
+    start: u64,
+    filter: impl 'static + Send + Fn(&SeraiEvent) -> bool,
+  ) -> impl Stream<Item = Result<([u8; 32], SeraiEvent), SeraiError>> {
+    stream::unfold(Some((self.clone(), start, VecDeque::new(), filter)), |state| async move {
+      let (mut serai, mut next, mut pending, filter) = state?;
+      loop {
+        if let Some(event) = pending.pop_front() {
+          return Some((Ok(event), Some((serai, next, pending, filter))));
+        }
+
+        match serai.get_finalized_events(next).await {
+          Ok(Some((hash, events))) => {
+            pending.extend(events.into_iter().filter(|event| filter(event)).map(|e| (hash, e)));
+            next += 1;
+          }
+          Ok(None) => sleep(POLL_INTERVAL).await,
+          Err(SeraiError::RpcError(_)) => {
+            sleep(RECONNECT_DELAY).await;
+            if let Ok(reconnected) = serai.reconnect().await {
+              serai = reconnected;
+            }
+          }
+          Err(e) => return Some((Err(e), None)),
+        }
+      }
+    })
+  }
+}
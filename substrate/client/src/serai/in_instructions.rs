@@ -36,4 +36,33 @@ impl Serai {
   pub fn execute_batch(&self, batch: SignedBatch) -> Result<Encoded, SeraiError> {
     self.unsigned::<InInstructions, _>(&in_instructions::Call::<Runtime>::execute_batch { batch })
   }
+
+  /// Publish a signed batch and wait for its execution.
+  ///
+  /// Batches are unsigned extrinsics, authenticated by the validator set's signature, and
+  /// accordingly don't require a nonce or pay a fee.
+  ///
+  /// Returns the hash of the block the batch was executed in and the event it emitted.
+  pub async fn publish_batch(
+    &self,
+    batch: SignedBatch,
+  ) -> Result<([u8; 32], InInstructionsEvent), SeraiError> {
+    let (network, id) = (batch.batch.network, batch.batch.id);
+    let block = self.publish_and_await_inclusion(&self.execute_batch(batch)?).await?;
+
+    let mut events = self
+      .events::<InInstructions, _>(block, |event| {
+        matches!(
+          event,
+          InInstructionsEvent::Batch { network: event_network, id: event_id, .. }
+            if (*event_network == network) && (*event_id == id)
+        )
+      })
+      .await?;
+    // If the batch was included, it must have been executed, as it'd be invalid otherwise
+    if events.len() != 1 {
+      Err(SeraiError::InvalidNode)?;
+    }
+    Ok((block, events.remove(0)))
+  }
 }
@@ -1,10 +1,21 @@
+use core::{future::Future, time::Duration};
+use std::{
+  sync::{Arc, Mutex},
+  collections::BTreeMap,
+};
+
 use thiserror::Error;
 
+use rand_core::{RngCore, OsRng};
+
+use tokio::time::sleep;
+
 use scale::{Encode, Decode, Compact};
 mod scale_value;
 pub(crate) use scale_value::{Value, Composite, scale_value, scale_composite};
 
-use sp_core::{Pair as PairTrait, sr25519::Pair};
+use sp_core::{Pair as PairTrait, sr25519::Pair, Blake2Hasher};
+use sp_state_machine::{StorageProof, read_proof_check};
 
 pub use subxt;
 use subxt::{
@@ -13,7 +24,7 @@ use subxt::{
   config::{
     Header as HeaderTrait,
     substrate::{BlakeTwo256, SubstrateHeader},
-    extrinsic_params::{BaseExtrinsicParams, BaseExtrinsicParamsBuilder},
+    extrinsic_params::{Era, BaseExtrinsicParams, BaseExtrinsicParamsBuilder},
   },
   tx::{Signer, Payload, TxClient},
   rpc::types::{ChainBlock, ChainBlockExtrinsic},
@@ -31,6 +42,12 @@ pub mod tokens;
 pub mod in_instructions;
 pub mod validator_sets;
 
+mod events;
+pub use events::SeraiEvent;
+
+mod at;
+pub use at::SeraiAt;
+
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Encode, Decode)]
 pub struct Tip {
   #[codec(compact)]
@@ -100,6 +117,20 @@ impl Block {
     Err(SeraiError::InvalidNode)
   }
 
+  pub fn parent(&self) -> [u8; 32] {
+    self.0.header.parent_hash.into()
+  }
+
+  fn metadata(&self, finalized: bool) -> Result<BlockMetadata, SeraiError> {
+    Ok(BlockMetadata {
+      hash: self.hash(),
+      number: self.number(),
+      parent: self.parent(),
+      time: self.time()?,
+      finalized,
+    })
+  }
+
   pub fn header(&self) -> &Header {
     &self.0.header
   }
@@ -117,22 +148,194 @@ impl Clone for Block {
   }
 }
 
+/// Metadata on a block, sufficient to make decisions based on its depth and age.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BlockMetadata {
+  pub hash: [u8; 32],
+  pub number: u64,
+  pub parent: [u8; 32],
+  /// The time of this block, set by its producer, as a unix timestamp.
+  pub time: u64,
+  /// If this block was finalized when its metadata was fetched.
+  pub finalized: bool,
+}
+
 #[derive(Error, Debug)]
 pub enum SeraiError {
+  /// The request failed, either in transport or due to the node erroring.
   #[error("failed to communicate with serai: {0}")]
   RpcError(SubxtError),
+  /// A value returned by the node couldn't be decoded.
   #[error("serai-client library was intended for a different runtime version")]
   InvalidRuntime,
+  /// The node returned a response which was well-formed yet invalid.
   #[error("node is faulty")]
   InvalidNode,
+  /// The node's transaction pool rejected a transaction.
+  #[error("transaction was rejected: {0}")]
+  TransactionRejected(String),
+}
+
+impl SeraiError {
+  /// If this error may not occur if the request is retried.
+  pub fn is_transient(&self) -> bool {
+    matches!(self, SeraiError::RpcError(SubxtError::Rpc(_) | SubxtError::Io(_)))
+  }
+}
+
+// The messages Substrate's author RPC uses for transactions rejected by the transaction pool
+const POOL_REJECTIONS: &[&str] = &[
+  "Invalid Transaction",
+  "Unknown Transaction Validity",
+  "Transaction is temporarily banned",
+  "Transaction Already Imported",
+  "Priority is too low",
+  "Cycle Detected",
+  "Immediately Dropped",
+  "Unactionable",
+];
+
+/// How to retry idempotent reads which failed due to transient errors.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RetryPolicy {
+  /// The maximum amount of attempts, including the first.
+  pub attempts: u32,
+  /// The delay before the first retry, doubled for every retry after it.
+  pub base_delay: Duration,
+  /// The maximum delay between attempts.
+  pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> RetryPolicy {
+    RetryPolicy {
+      attempts: 4,
+      base_delay: Duration::from_millis(250),
+      max_delay: Duration::from_secs(5),
+    }
+  }
+}
+
+impl RetryPolicy {
+  /// A policy which never retries.
+  pub fn none() -> RetryPolicy {
+    RetryPolicy { attempts: 1, base_delay: Duration::ZERO, max_delay: Duration::ZERO }
+  }
+
+  // The delay before the specified retry, with up to half of it randomly removed so clients which
+  // failed together don't retry together
+  fn delay(&self, retry: u32) -> Duration {
+    let delay = self.base_delay.saturating_mul(2u32.saturating_pow(retry)).min(self.max_delay);
+    let jitter = u32::try_from(OsRng.next_u64() % 1024).unwrap();
+    delay - ((delay / 2) * jitter / 1024)
+  }
 }
 
+// The next nonce this client will use for each account, allowing multiple transactions from the
+// same account to be in-flight at once
+type Nonces = Arc<Mutex<BTreeMap<SeraiAddress, u32>>>;
+
+// The tip a transaction is first bumped to if it expires without being included
+const DEFAULT_TIP_BUMP: u64 = 1_000_000;
+
 #[derive(Clone)]
-pub struct Serai(OnlineClient<SeraiConfig>);
+pub struct Serai {
+  client: OnlineClient<SeraiConfig>,
+  // The URL is kept in order to reconnect
+  url: String,
+  verify_storage: bool,
+  // The nonces are shared across clones, so every clone reserves nonces from the same pool
+  nonces: Nonces,
+  retry_policy: RetryPolicy,
+  tip_bump: u64,
+}
 
 impl Serai {
   pub async fn new(url: &str) -> Result<Self, SeraiError> {
-    Ok(Serai(OnlineClient::<SeraiConfig>::from_url(url).await.map_err(SeraiError::RpcError)?))
+    Ok(Serai {
+      client: OnlineClient::<SeraiConfig>::from_url(url).await.map_err(SeraiError::RpcError)?,
+      url: url.to_string(),
+      verify_storage: false,
+      nonces: Arc::new(Mutex::new(BTreeMap::new())),
+      retry_policy: RetryPolicy::default(),
+      tip_bump: DEFAULT_TIP_BUMP,
+    })
+  }
+
+  /// Reconnect to the node this client was created for, preserving its configuration and nonces.
+  pub(crate) async fn reconnect(&self) -> Result<Self, SeraiError> {
+    let client =
+      OnlineClient::<SeraiConfig>::from_url(&self.url).await.map_err(SeraiError::RpcError)?;
+    Ok(Serai { client, ..self.clone() })
+  }
+
+  /// Use the specified policy when retrying idempotent reads.
+  ///
+  /// By default, RetryPolicy::default() is used.
+  pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+    self.retry_policy = policy;
+    self
+  }
+
+  /// Use the specified tip for transactions re-signed by `sign_and_publish` after first expiring.
+  ///
+  /// The tip is doubled on every further expiry. By default, a tip of 1_000_000 is used.
+  pub fn with_tip_bump(mut self, tip_bump: u64) -> Self {
+    self.tip_bump = tip_bump;
+    self
+  }
+
+  // Perform an idempotent request, retrying it per the retry policy
+  async fn rpc<T, F: Future<Output = Result<T, SubxtError>>>(
+    &self,
+    request: impl Fn() -> F,
+  ) -> Result<T, SeraiError> {
+    let mut retry = 0;
+    loop {
+      match request().await.map_err(SeraiError::RpcError) {
+        Err(e) if e.is_transient() && ((retry + 1) < self.retry_policy.attempts) => {
+          sleep(self.retry_policy.delay(retry)).await;
+          retry += 1;
+        }
+        res => return res,
+      }
+    }
+  }
+
+  /// Verify all storage reads against the state root of the block they're read from, using
+  /// storage proofs provided by the node.
+  ///
+  /// This removes the need to trust the node for the values of storage, yet not for which blocks
+  /// are finalized. Callers who independently know a block's hash will only receive storage which
+  /// is valid for that block.
+  pub fn with_verified_storage(mut self) -> Self {
+    self.verify_storage = true;
+    self
+  }
+
+  async fn verified_storage(
+    &self,
+    key: Vec<u8>,
+    block: [u8; 32],
+  ) -> Result<Option<Vec<u8>>, SeraiError> {
+    let rpc = self.client.rpc();
+
+    let header =
+      self.rpc(|| rpc.header(Some(block.into()))).await?.ok_or(SeraiError::InvalidNode)?;
+    if header.hash() != block.into() {
+      Err(SeraiError::InvalidNode)?;
+    }
+
+    let proof = self
+      .rpc(|| rpc.read_proof([key.as_slice()], Some(block.into())))
+      .await?
+      .proof
+      .into_iter()
+      .map(|node| node.0);
+    let mut values =
+      read_proof_check::<Blake2Hasher, _>(header.state_root, StorageProof::new(proof), [&key])
+        .map_err(|_| SeraiError::InvalidNode)?;
+    values.remove(&key).ok_or(SeraiError::InvalidNode)
   }
 
   async fn storage<R: Decode>(
@@ -142,15 +345,22 @@ impl Serai {
     keys: Option<Vec<Value>>,
     block: [u8; 32],
   ) -> Result<Option<R>, SeraiError> {
-    let storage = self.0.storage();
+    let storage = self.client.storage();
     let address = subxt::dynamic::storage(pallet, name, keys.unwrap_or(vec![]));
     debug_assert!(storage.validate(&address).is_ok(), "invalid storage address");
 
-    storage
-      .at(block.into())
-      .fetch(&address)
-      .await
-      .map_err(SeraiError::RpcError)?
+    if self.verify_storage {
+      let key = storage.address_bytes(&address).map_err(|_| SeraiError::InvalidRuntime)?;
+      return self
+        .verified_storage(key, block)
+        .await?
+        .map(|res| R::decode(&mut res.as_ref()).map_err(|_| SeraiError::InvalidRuntime))
+        .transpose();
+    }
+
+    self
+      .rpc(|| storage.at(block.into()).fetch(&address))
+      .await?
       .map(|res| R::decode(&mut res.encoded()).map_err(|_| SeraiError::InvalidRuntime))
       .transpose()
   }
@@ -161,7 +371,7 @@ impl Serai {
     filter: impl Fn(&E) -> bool,
   ) -> Result<Vec<E>, SeraiError> {
     let mut res = vec![];
-    for event in self.0.events().at(block.into()).await.map_err(SeraiError::RpcError)?.iter() {
+    for event in self.rpc(|| self.client.events().at(block.into())).await?.iter() {
       let event = event.map_err(|_| SeraiError::InvalidRuntime)?;
       if PalletInfo::index::<P>().unwrap() == usize::from(event.pallet_index()) {
         let mut with_variant: &[u8] =
@@ -176,22 +386,38 @@ impl Serai {
   }
 
   pub async fn get_latest_block_hash(&self) -> Result<[u8; 32], SeraiError> {
-    Ok(self.0.rpc().finalized_head().await.map_err(SeraiError::RpcError)?.into())
+    Ok(self.rpc(|| self.client.rpc().finalized_head()).await?.into())
   }
 
   pub async fn get_latest_block(&self) -> Result<Block, SeraiError> {
+    let finalized = self.rpc(|| self.client.rpc().finalized_head()).await?;
     Block::new(
       self
-        .0
-        .rpc()
-        .block(Some(self.0.rpc().finalized_head().await.map_err(SeraiError::RpcError)?))
-        .await
-        .map_err(SeraiError::RpcError)?
+        .rpc(|| self.client.rpc().block(Some(finalized)))
+        .await?
         .ok_or(SeraiError::InvalidNode)?
         .block,
     )
   }
 
+  /// Get the metadata of the latest finalized block.
+  pub async fn latest_finalized(&self) -> Result<BlockMetadata, SeraiError> {
+    self.get_latest_block().await?.metadata(true)
+  }
+
+  /// Get the metadata of a block, whether or not it's finalized.
+  pub async fn block(&self, hash: [u8; 32]) -> Result<Option<BlockMetadata>, SeraiError> {
+    let Some(res) = self.rpc(|| self.client.rpc().block(Some(hash.into()))).await? else {
+      return Ok(None);
+    };
+    let block = Block::new(res.block)?;
+    if block.hash() != hash {
+      Err(SeraiError::InvalidNode)?;
+    }
+    let finalized = self.is_finalized(block.header()).await? == Some(true);
+    Ok(Some(block.metadata(finalized)?))
+  }
+
   // There is no provided method for this
   // TODO: Add one to Serai
   pub async fn is_finalized(&self, header: &Header) -> Result<Option<bool>, SeraiError> {
@@ -202,10 +428,9 @@ impl Serai {
       return Ok(Some(true));
     }
 
-    let Some(finalized) =
-      self.0.rpc().header(Some(finalized)).await.map_err(SeraiError::RpcError)? else {
-        return Ok(None);
-      };
+    let Some(finalized) = self.rpc(|| self.client.rpc().header(Some(finalized))).await? else {
+      return Ok(None);
+    };
 
     // If the finalized block has a lower number, this block can't be finalized
     if finalized.number() < header.number() {
@@ -217,24 +442,19 @@ impl Serai {
     // chain
     // If that hash is this hash, this block is finalized
     let Some(hash) =
-      self
-        .0
-        .rpc()
-        .block_hash(Some(header.number().into()))
-        .await
-        .map_err(SeraiError::RpcError)? else {
-          // This is an error since there is a block at this index
-          Err(SeraiError::InvalidNode)?
-        };
+      self.rpc(|| self.client.rpc().block_hash(Some(header.number().into()))).await?
+    else {
+      // This is an error since there is a block at this index
+      Err(SeraiError::InvalidNode)?
+    };
 
     Ok(Some(header.hash() == hash))
   }
 
   pub async fn get_block(&self, hash: [u8; 32]) -> Result<Option<Block>, SeraiError> {
-    let Some(res) =
-      self.0.rpc().block(Some(hash.into())).await.map_err(SeraiError::RpcError)? else {
-        return Ok(None);
-      };
+    let Some(res) = self.rpc(|| self.client.rpc().block(Some(hash.into()))).await? else {
+      return Ok(None);
+    };
 
     // Only return finalized blocks
     if self.is_finalized(&res.block.header).await? != Some(true) {
@@ -250,20 +470,15 @@ impl Serai {
   // is_finalized method, which at least requires the header
   // In practice, the block is likely more useful than the header
   pub async fn get_block_by_number(&self, number: u64) -> Result<Option<Block>, SeraiError> {
-    let Some(hash) =
-      self.0.rpc().block_hash(Some(number.into())).await.map_err(SeraiError::RpcError)? else {
-        return Ok(None);
-      };
+    let Some(hash) = self.rpc(|| self.client.rpc().block_hash(Some(number.into()))).await? else {
+      return Ok(None);
+    };
     self.get_block(hash.into()).await
   }
 
   pub async fn get_nonce(&self, address: &SeraiAddress) -> Result<u32, SeraiError> {
-    self
-      .0
-      .rpc()
-      .system_account_next_index(&sp_core::sr25519::Public(address.0).to_string())
-      .await
-      .map_err(SeraiError::RpcError)
+    let address = sp_core::sr25519::Public(address.0).to_string();
+    self.rpc(|| self.client.rpc().system_account_next_index(&address)).await
   }
 
   fn unsigned<P: 'static, C: Encode>(&self, call: &C) -> Result<Encoded, SeraiError> {
@@ -291,14 +506,160 @@ impl Serai {
     nonce: u32,
     params: BaseExtrinsicParamsBuilder<SeraiConfig, Tip>,
   ) -> Result<Encoded, SeraiError> {
-    TxClient::new(self.0.offline())
+    TxClient::new(self.client.offline())
       .create_signed_with_nonce(payload, signer, nonce, params)
       .map(|tx| Encoded(tx.into_encoded()))
       .map_err(|_| SeraiError::InvalidRuntime)
   }
 
+  /// Build extrinsic params for a transaction only valid for `period` blocks after `checkpoint`.
+  pub fn mortal_params(
+    checkpoint: &Block,
+    period: u64,
+  ) -> BaseExtrinsicParamsBuilder<SeraiConfig, Tip> {
+    BaseExtrinsicParamsBuilder::new()
+      .era(Era::mortal(period, checkpoint.number()), checkpoint.header().hash())
+  }
+
+  /// Reserve the next nonce for an account.
+  ///
+  /// Nonces are tracked locally, so repeated calls will return sequential nonces, even if the
+  /// transactions using prior nonces have yet to be published.
+  pub async fn reserve_nonce(&self, address: &SeraiAddress) -> Result<u32, SeraiError> {
+    let on_chain = self.get_nonce(address).await?;
+    let mut nonces = self.nonces.lock().unwrap();
+    let nonce = nonces.get(address).copied().unwrap_or(0).max(on_chain);
+    nonces.insert(*address, nonce + 1);
+    Ok(nonce)
+  }
+
+  /// Forget every locally reserved nonce for an account, resyncing with the chain on the next
+  /// reservation.
+  ///
+  /// This should be called if a transaction with a reserved nonce won't be published, as
+  /// otherwise every later transaction will be stuck behind the missing nonce.
+  pub fn reset_nonce(&self, address: &SeraiAddress) {
+    self.nonces.lock().unwrap().remove(address);
+  }
+
+  /// Sign a payload with the signer's next nonce.
+  pub async fn sign_with_next_nonce<S: Send + Sync + Signer<SeraiConfig>>(
+    &self,
+    signer: &S,
+    payload: &Payload<Composite<()>>,
+    params: BaseExtrinsicParamsBuilder<SeraiConfig, Tip>,
+  ) -> Result<Encoded, SeraiError> {
+    let nonce = self.reserve_nonce(&signer.address()).await?;
+    self.sign(signer, payload, nonce, params)
+  }
+
+  /// Publish a transaction.
+  ///
+  /// This isn't retried, and returns SeraiError::TransactionRejected if the node's transaction pool
+  /// rejected the transaction.
   pub async fn publish(&self, tx: &Encoded) -> Result<[u8; 32], SeraiError> {
-    self.0.rpc().submit_extrinsic(tx).await.map(Into::into).map_err(SeraiError::RpcError)
+    self.client.rpc().submit_extrinsic(tx).await.map(Into::into).map_err(|e| {
+      // The node reports rejections as RPC errors, which we distinguish as retrying won't help
+      let msg = e.to_string();
+      if POOL_REJECTIONS.iter().any(|rejection| msg.contains(rejection)) {
+        SeraiError::TransactionRejected(msg)
+      } else {
+        SeraiError::RpcError(e)
+      }
+    })
+  }
+
+  /// Publish a transaction and wait for it to be included in a finalized block.
+  ///
+  /// Returns the hash of the block it was included in. This will not return if the transaction is
+  /// never included, so callers should apply their own timeout.
+  pub async fn publish_and_await_inclusion(&self, tx: &Encoded) -> Result<[u8; 32], SeraiError> {
+    self.publish_and_await_inclusion_until(tx, None).await?.ok_or(SeraiError::InvalidNode)
+  }
+
+  // Publish a transaction and wait for it to be included in a finalized block, returning None if
+  // a block numbered `deadline` is finalized without having included it
+  async fn publish_and_await_inclusion_until(
+    &self,
+    tx: &Encoded,
+    deadline: Option<u64>,
+  ) -> Result<Option<[u8; 32]>, SeraiError> {
+    // Blocks contain the extrinsic without its length prefix
+    let mut extrinsic = tx.0.as_slice();
+    Compact::<u32>::decode(&mut extrinsic).map_err(|_| SeraiError::InvalidRuntime)?;
+
+    // Subscribe before publishing so we can't miss the block including it
+    let mut finalized =
+      self.client.rpc().subscribe_finalized_block_headers().await.map_err(SeraiError::RpcError)?;
+    let mut next = self.get_latest_block().await?.number() + 1;
+
+    self.publish(tx).await?;
+
+    while let Some(header) = finalized.next().await {
+      let number: u64 = header.map_err(SeraiError::RpcError)?.number().into();
+      // Check every block up to this one, as the subscription may skip blocks
+      while next <= number {
+        let block = self.get_block_by_number(next).await?.ok_or(SeraiError::InvalidNode)?;
+        if block.transactions().iter().any(|transaction| transaction.0 == extrinsic) {
+          return Ok(Some(block.hash()));
+        }
+        if Some(next) == deadline {
+          return Ok(None);
+        }
+        next += 1;
+      }
+    }
+
+    // The subscription ended, which only happens if the node dropped us
+    Err(SeraiError::InvalidNode)
+  }
+
+  /// Sign and publish a payload with the signer's next nonce, waiting for it to be included in a
+  /// finalized block.
+  ///
+  /// The transaction is mortal, only valid for `period` blocks. If it expires without being
+  /// included, it's re-signed with the same nonce, a fresh mortality, and an increased tip (see
+  /// `with_tip_bump`), then republished. The increased tip lets it replace any stuck transaction
+  /// with the same nonce.
+  ///
+  /// Multiple calls may be in-flight at once, even for the same signer. Returns the hash of the
+  /// block the transaction was included in.
+  pub async fn sign_and_publish<S: Send + Sync + Signer<SeraiConfig>>(
+    &self,
+    signer: &S,
+    payload: &Payload<Composite<()>>,
+    period: u64,
+  ) -> Result<[u8; 32], SeraiError> {
+    let address = signer.address();
+    let nonce = self.reserve_nonce(&address).await?;
+
+    let mut tip = 0;
+    loop {
+      let res = async {
+        let checkpoint = self.get_latest_block().await?;
+        // Substrate rounds the period up to a power of two, which we need to account for when
+        // deciding the transaction has expired
+        let deadline = checkpoint.number() +
+          period.checked_next_power_of_two().unwrap_or(1 << 16).clamp(4, 1 << 16);
+        let params = Self::mortal_params(&checkpoint, period).tip(Tip { tip });
+        let tx = self.sign(signer, payload, nonce, params)?;
+        self.publish_and_await_inclusion_until(&tx, Some(deadline)).await
+      }
+      .await;
+
+      match res {
+        Ok(Some(block)) => return Ok(block),
+        // Expired, so bump the tip and try again
+        // The tip is doubled on every expiry so it quickly outbids whatever it's stuck behind
+        Ok(None) => tip = tip.saturating_mul(2).max(self.tip_bump),
+        Err(e) => {
+          // This nonce may now go unused, so resync our nonces with the chain to prevent later
+          // transactions from being stuck behind it
+          self.reset_nonce(&address);
+          Err(e)?
+        }
+      }
+    }
   }
 }
 
@@ -1,10 +1,15 @@
 use serai_runtime::{validator_sets, ValidatorSets, Runtime};
 pub use validator_sets::primitives;
-use primitives::{ValidatorSet, ValidatorSetData, KeyPair};
+use primitives::{Session, ValidatorSet, ValidatorSetData, KeyPair};
+
+use sp_core::sr25519::Public;
 
 use subxt::tx::Payload;
 
-use crate::{primitives::NetworkId, Serai, SeraiError, Composite, scale_value, scale_composite};
+use crate::{
+  primitives::{NetworkId, Amount},
+  Serai, SeraiError, Composite, scale_value, scale_composite,
+};
 
 const PALLET: &str = "ValidatorSets";
 
@@ -38,24 +43,50 @@ impl Serai {
       .await
   }
 
+  pub async fn get_session(
+    &self,
+    block: [u8; 32],
+    network: NetworkId,
+  ) -> Result<Option<Session>, SeraiError> {
+    self.storage(PALLET, "CurrentSession", Some(vec![scale_value(network)]), block).await
+  }
+
   pub async fn get_validator_set(
     &self,
+    block: [u8; 32],
     set: ValidatorSet,
   ) -> Result<Option<ValidatorSetData>, SeraiError> {
-    self
-      .storage(
-        PALLET,
-        "ValidatorSets",
-        Some(vec![scale_value(set)]),
-        self.get_latest_block_hash().await?,
-      )
-      .await
+    self.storage(PALLET, "ValidatorSets", Some(vec![scale_value(set)]), block).await
   }
 
-  pub async fn get_keys(&self, set: ValidatorSet) -> Result<Option<KeyPair>, SeraiError> {
-    self
-      .storage(PALLET, "Keys", Some(vec![scale_value(set)]), self.get_latest_block_hash().await?)
-      .await
+  /// Get the current validator set for a network, along with its data.
+  pub async fn get_current_validator_set(
+    &self,
+    block: [u8; 32],
+    network: NetworkId,
+  ) -> Result<Option<(ValidatorSet, ValidatorSetData)>, SeraiError> {
+    let Some(session) = self.get_session(block, network).await? else { return Ok(None) };
+    let set = ValidatorSet { session, network };
+    // If the network has a session, it must have a validator set for it
+    let data = self.get_validator_set(block, set).await?.ok_or(SeraiError::InvalidNode)?;
+    Ok(Some((set, data)))
+  }
+
+  /// Get the participants of a validator set, along with the amount they each have bonded.
+  pub async fn get_validator_set_participants(
+    &self,
+    block: [u8; 32],
+    set: ValidatorSet,
+  ) -> Result<Option<Vec<(Public, Amount)>>, SeraiError> {
+    Ok(self.get_validator_set(block, set).await?.map(|data| data.participants.into_inner()))
+  }
+
+  pub async fn get_keys(
+    &self,
+    block: [u8; 32],
+    set: ValidatorSet,
+  ) -> Result<Option<KeyPair>, SeraiError> {
+    self.storage(PALLET, "Keys", Some(vec![scale_value(set)]), block).await
   }
 
   pub fn vote(network: NetworkId, key_pair: KeyPair) -> Payload<Composite<()>> {
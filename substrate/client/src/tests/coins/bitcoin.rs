@@ -1 +1,34 @@
-// TODO: Test the address back and forth
+use core::str::FromStr;
+
+use crate::coins::bitcoin::Address;
+
+#[test]
+fn address() {
+  // One address of each supported type, in the order of their encoded variant
+  let addresses = [
+    // P2PKH
+    "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+    // P2SH
+    "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+    // P2WPKH
+    "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+    // P2WSH
+    "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+    // P2TR
+    "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+  ];
+
+  for (i, str_address) in addresses.iter().enumerate() {
+    let address = Address::from_str(str_address).unwrap();
+    assert_eq!(&address.to_string(), str_address);
+
+    let encoded: Vec<u8> = address.clone().try_into().unwrap();
+    assert_eq!(encoded[0], u8::try_from(i).unwrap());
+    assert_eq!(Address::try_from(encoded).unwrap(), address);
+  }
+
+  // Invalid encodings should be rejected
+  assert!(Address::try_from(vec![]).is_err());
+  assert!(Address::try_from(vec![0; 20]).is_err());
+  assert!(Address::try_from(vec![u8::try_from(addresses.len()).unwrap(); 33]).is_err());
+}
@@ -1 +1,32 @@
-// TODO: Test the address back and forth
+use core::str::FromStr;
+
+use crate::coins::monero::Address;
+
+const STANDARD: &str =
+  "4B33mFPMq6mKi7Eiyd5XuyKRVMGVZz1Rqb9ZTyGApXW5d1aT7UBDZ89ewmnWFkzJ5wPd2SFbn313vCT8a4E2Qf4KQH4pNey";
+const SUBADDRESS: &str =
+  "8C5zHM5ud8nGC4hC2ULiBLSWx9infi8JUUmWEat4fcTf8J4H38iWYVdFmPCA9UmfLTZxD43RsyKnGEdZkoGij6csDeUnbEB";
+const FEATURED: &str =
+  "CjWdTpuDaZ69nTGxzm9YarR82YDYFECi1WaaREZTMy5yDsjaRX5bC3cbC3JpcrBPd7YYpjoWKuBMidgGaKBK5Jye2v3p\
+  YyUDn";
+const INTEGRATED: &str =
+  "4Ljin4CrSNHKi7Eiyd5XuyKRVMGVZz1Rqb9ZTyGApXW5d1aT7UBDZ89ewmnWFkzJ5wPd2SFbn313vCT8a4E2Qf4KbaTH6Mn\
+  pXSn88oBX35";
+
+#[test]
+fn address() {
+  for str_address in [STANDARD, SUBADDRESS, FEATURED] {
+    let address = Address::from_str(str_address).unwrap();
+    assert_eq!(address.to_string(), str_address);
+
+    let encoded: Vec<u8> = address.clone().into();
+    assert_eq!(Address::try_from(encoded).unwrap(), address);
+  }
+
+  // Payment IDs aren't supported
+  assert!(Address::from_str(INTEGRATED).is_err());
+
+  // Invalid encodings should be rejected
+  assert!(Address::try_from(vec![]).is_err());
+  assert!(Address::try_from(vec![0; 64]).is_err());
+}
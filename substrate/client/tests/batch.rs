@@ -1,5 +1,7 @@
 use rand_core::{RngCore, OsRng};
 
+use futures::StreamExt;
+
 use serai_client::{
   primitives::{Amount, NetworkId, Coin, Balance, BlockHash, SeraiAddress},
   in_instructions::{
@@ -7,7 +9,7 @@ use serai_client::{
     InInstructionsEvent,
   },
   tokens::TokensEvent,
-  Serai,
+  Serai, SeraiEvent,
 };
 
 mod common;
@@ -51,5 +53,53 @@ serai_test!(
     );
     assert_eq!(serai.get_token_supply(block, coin).await.unwrap(), amount);
     assert_eq!(serai.get_token_balance(block, coin, address).await.unwrap(), amount);
+
+    // Reading storage with verification should return the same values
+    let verified = serai.clone().with_verified_storage();
+    assert_eq!(
+      verified.get_latest_block_for_network(block, network).await.unwrap(),
+      Some(block_hash)
+    );
+    assert_eq!(verified.get_token_supply(block, coin).await.unwrap(), amount);
+    assert_eq!(verified.get_token_balance(block, coin, address).await.unwrap(), amount);
+
+    // The event subscription should yield the same events
+    let number = serai.get_block(block).await.unwrap().unwrap().number();
+    let mut events = Box::pin(serai.subscribe_events(number, |event| {
+      matches!(event, SeraiEvent::InInstructions(_) | SeraiEvent::Tokens(_))
+    }));
+    assert_eq!(
+      events.next().await.unwrap().unwrap(),
+      (
+        block,
+        SeraiEvent::InInstructions(InInstructionsEvent::Batch { network, id, block: block_hash })
+      ),
+    );
+    assert_eq!(
+      events.next().await.unwrap().unwrap(),
+      (block, SeraiEvent::Tokens(TokensEvent::Mint { address, balance })),
+    );
+
+    // As should fetching the events for a range of blocks
+    let range = serai
+      .get_events_range(number.saturating_sub(1), number, |event| {
+        matches!(event, SeraiEvent::InInstructions(_))
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(range.len(), 2);
+    assert!(range[0].1.is_empty());
+    assert_eq!(
+      range[1],
+      (
+        block,
+        vec![SeraiEvent::InInstructions(InInstructionsEvent::Batch {
+          network,
+          id,
+          block: block_hash
+        })]
+      )
+    );
   }
 );
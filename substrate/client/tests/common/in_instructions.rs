@@ -1,3 +1,7 @@
+use core::time::Duration;
+
+use tokio::time::timeout;
+
 use scale::Encode;
 
 use sp_core::Pair;
@@ -11,7 +15,7 @@ use serai_client::{
   },
 };
 
-use crate::common::{serai, tx::publish_tx, validator_sets::vote_in_keys};
+use crate::common::{serai, validator_sets::vote_in_keys};
 
 #[allow(dead_code)]
 pub async fn provide_batch(batch: Batch) -> [u8; 32] {
@@ -20,7 +24,8 @@ pub async fn provide_batch(batch: Batch) -> [u8; 32] {
   // TODO: Get the latest session
   let set = ValidatorSet { session: Session(0), network: batch.network };
   let pair = insecure_pair_from_name(&format!("ValidatorSet {:?}", set));
-  let keys = if let Some(keys) = serai.get_keys(set).await.unwrap() {
+  let latest = serai.get_latest_block_hash().await.unwrap();
+  let keys = if let Some(keys) = serai.get_keys(latest, set).await.unwrap() {
     keys
   } else {
     let keys = (pair.public(), vec![].try_into().unwrap());
@@ -29,19 +34,21 @@ pub async fn provide_batch(batch: Batch) -> [u8; 32] {
   };
   assert_eq!(keys.0, pair.public());
 
-  let block = publish_tx(
-    &serai
-      .execute_batch(SignedBatch { batch: batch.clone(), signature: pair.sign(&batch.encode()) })
-      .unwrap(),
+  let (block, event) = timeout(
+    Duration::from_secs(60),
+    serai
+      .publish_batch(SignedBatch { batch: batch.clone(), signature: pair.sign(&batch.encode()) }),
   )
-  .await;
+  .await
+  .expect("60 seconds without inclusion in a finalized block")
+  .unwrap();
 
-  let batches = serai.get_batch_events(block).await.unwrap();
   // TODO: impl From<Batch> for BatchEvent?
   assert_eq!(
-    batches,
-    vec![InInstructionsEvent::Batch { network: batch.network, id: batch.id, block: batch.block }],
+    event,
+    InInstructionsEvent::Batch { network: batch.network, id: batch.id, block: batch.block },
   );
+  assert_eq!(serai.get_batch_events(block).await.unwrap(), vec![event]);
 
   // TODO: Check the tokens events
 
@@ -1,6 +1,11 @@
-use lazy_static::lazy_static;
+use core::time::Duration;
+use std::{
+  path::{Path, PathBuf},
+  process::{Command, Child},
+  net::TcpListener,
+};
 
-use tokio::sync::Mutex;
+use tokio::time::sleep;
 
 use serai_client::Serai;
 
@@ -8,14 +13,101 @@ pub mod tx;
 pub mod validator_sets;
 pub mod in_instructions;
 
-pub const URL: &str = "ws://127.0.0.1:9944";
+tokio::task_local! {
+  // The URL of the node spawned for the currently running test
+  pub static URL: String;
+}
 
 pub async fn serai() -> Serai {
-  Serai::new(URL).await.unwrap()
+  Serai::new(&URL.with(Clone::clone)).await.unwrap()
+}
+
+// Find a port which is currently available
+fn free_port() -> u16 {
+  TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Builder for an isolated development node, allowing tests to run in parallel.
+///
+/// Each node has its own chain, base path, and ports.
+#[derive(Default)]
+pub struct NodeBuilder {
+  base_path: Option<PathBuf>,
 }
 
-lazy_static! {
-  pub static ref SEQUENTIAL: Mutex<()> = Mutex::new(());
+impl NodeBuilder {
+  /// Use the specified base path, instead of a fresh temporary directory.
+  #[allow(dead_code)]
+  pub fn base_path(mut self, base_path: PathBuf) -> Self {
+    self.base_path = Some(base_path);
+    self
+  }
+
+  /// Spawn the node, returning once it's producing blocks.
+  pub async fn spawn(self) -> Node {
+    let binary = {
+      let this_crate = Path::new(env!("CARGO_MANIFEST_DIR"));
+      let top_level = this_crate.join("../../");
+      top_level.join("target/debug/serai-node")
+    };
+
+    let ws_port = free_port();
+    let base_path = self
+      .base_path
+      .unwrap_or_else(|| std::env::temp_dir().join(format!("serai-client-test-{ws_port}")));
+    let process = Command::new(binary)
+      .arg("--dev")
+      .arg("--base-path")
+      .arg(&base_path)
+      .args(["--ws-port", &ws_port.to_string()])
+      .args(["--rpc-port", &free_port().to_string()])
+      .args(["--port", &free_port().to_string()])
+      .arg("--no-prometheus")
+      .spawn()
+      .unwrap();
+    let node = Node { process, url: format!("ws://127.0.0.1:{ws_port}"), base_path };
+
+    let serai = loop {
+      if let Ok(serai) = Serai::new(&node.url).await {
+        break serai;
+      }
+      sleep(Duration::from_secs(1)).await;
+    };
+    while serai.get_latest_block_hash().await.is_err() {
+      sleep(Duration::from_secs(1)).await;
+    }
+    // TODO: https://github.com/serai-dex/serai/247
+    if std::env::var("GITHUB_CI") == Ok("true".to_string()) {
+      sleep(Duration::from_secs(60)).await;
+    }
+
+    node
+  }
+}
+
+/// A running development node, which is killed and has its data removed when dropped.
+pub struct Node {
+  process: Child,
+  url: String,
+  base_path: PathBuf,
+}
+
+impl Node {
+  pub fn builder() -> NodeBuilder {
+    NodeBuilder::default()
+  }
+
+  pub fn url(&self) -> &str {
+    &self.url
+  }
+}
+
+impl Drop for Node {
+  fn drop(&mut self) {
+    let _ = self.process.kill();
+    let _ = self.process.wait();
+    let _ = std::fs::remove_dir_all(&self.base_path);
+  }
 }
 
 #[macro_export]
@@ -24,58 +116,18 @@ macro_rules! serai_test {
     $(
       #[tokio::test]
       async fn $name() {
-        use std::process::Command;
-
-        let guard = common::SEQUENTIAL.lock().await;
-
-        let is_running = || {
-          !Command::new("pidof").arg("serai-node").output().unwrap().stdout.is_empty()
-        };
-
-        // Spawn a fresh Serai node
-        let mut command = {
-          use core::time::Duration;
-          use std::path::Path;
-
-          // Make sure a node isn't already running
-          assert!(!is_running());
-
-          let node = {
-            let this_crate = Path::new(env!("CARGO_MANIFEST_DIR"));
-            let top_level = this_crate.join("../../");
-            top_level.join("target/debug/serai-node")
-          };
-
-          let command = Command::new(node).arg("--dev").spawn().unwrap();
-          while Serai::new(common::URL).await.is_err() {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-          }
-          let serai = serai().await;
-          while serai.get_latest_block_hash().await.is_err() {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-          }
-          // TODO: https://github.com/serai-dex/serai/247
-          if std::env::var("GITHUB_CI") == Ok("true".to_string()) {
-            tokio::time::sleep(Duration::from_secs(60)).await;
-          }
-
-          // Sanity check the pidof command is well-formed
-          assert!(is_running());
-
-          command
-        };
+        // Spawn a fresh Serai node, isolated from any other test's
+        let node = common::Node::builder().spawn().await;
 
         let local = tokio::task::LocalSet::new();
-        local.run_until(async move {
-          if let Err(err) = tokio::task::spawn_local(async move { $body }).await {
-            drop(guard);
-            let _ = command.kill();
-            Err(err).unwrap()
-          } else {
-            command.kill().unwrap();
-          }
-          assert!(!is_running());
-        }).await;
+        let res = local
+          .run_until(tokio::task::spawn_local(
+            common::URL.scope(node.url().to_string(), async move { $body }),
+          ))
+          .await;
+        // Explicitly kill the node before propagating any panic
+        drop(node);
+        res.unwrap();
       }
     )*
   }
@@ -2,7 +2,7 @@ use sp_core::Pair;
 
 use serai_client::{
   subxt::config::extrinsic_params::BaseExtrinsicParamsBuilder,
-  primitives::{SeraiAddress, insecure_pair_from_name},
+  primitives::insecure_pair_from_name,
   validator_sets::{
     primitives::{ValidatorSet, KeyPair},
     ValidatorSetsEvent,
@@ -20,15 +20,14 @@ pub async fn vote_in_keys(set: ValidatorSet, key_pair: KeyPair) -> [u8; 32] {
   let serai = serai().await;
 
   // Vote in a key pair
-  let address = SeraiAddress::from(pair.public());
   let block = publish_tx(
     &serai
-      .sign(
+      .sign_with_next_nonce(
         &PairSigner::new(pair),
         &Serai::vote(set.network, key_pair.clone()),
-        serai.get_nonce(&address).await.unwrap(),
         BaseExtrinsicParamsBuilder::new(),
       )
+      .await
       .unwrap(),
   )
   .await;
@@ -41,7 +40,7 @@ pub async fn vote_in_keys(set: ValidatorSet, key_pair: KeyPair) -> [u8; 32] {
     serai.get_key_gen_events(block).await.unwrap(),
     vec![ValidatorSetsEvent::KeyGen { set, key_pair: key_pair.clone() }]
   );
-  assert_eq!(serai.get_keys(set).await.unwrap(), Some(key_pair));
+  assert_eq!(serai.get_keys(block, set).await.unwrap(), Some(key_pair));
 
   block
 }
@@ -0,0 +1,25 @@
+use sp_core::Pair;
+
+use serai_client::primitives::{SeraiAddress, insecure_pair_from_name};
+
+mod common;
+use common::serai;
+
+serai_test!(
+  async fn nonces() {
+    let serai = serai().await;
+    let address = SeraiAddress::from(insecure_pair_from_name("Alice").public());
+
+    // Reserving nonces should return sequential nonces, even without publishing anything
+    let on_chain = serai.get_nonce(&address).await.unwrap();
+    assert_eq!(serai.reserve_nonce(&address).await.unwrap(), on_chain);
+    assert_eq!(serai.reserve_nonce(&address).await.unwrap(), on_chain + 1);
+
+    // Clones should share reservations
+    assert_eq!(serai.clone().reserve_nonce(&address).await.unwrap(), on_chain + 2);
+
+    // Resetting should resync with the chain
+    serai.reset_nonce(&address);
+    assert_eq!(serai.reserve_nonce(&address).await.unwrap(), on_chain);
+  }
+);
@@ -25,6 +25,15 @@ serai_test!(
       // Make sure the time we extract from the block is within 5 seconds of now
       let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
       assert!(now.saturating_sub(block.time().unwrap()) < 5);
+
+      // Make sure the block's metadata matches the block
+      let metadata = serai.block(block.hash()).await.unwrap().unwrap();
+      assert_eq!(metadata.hash, block.hash());
+      assert_eq!(metadata.number, number);
+      assert_eq!(metadata.parent, block.parent());
+      assert_eq!(metadata.time, block.time().unwrap());
+      assert!(metadata.finalized);
+      assert!(serai.latest_finalized().await.unwrap().number >= number);
       done += 1;
     }
   }
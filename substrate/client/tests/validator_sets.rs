@@ -47,10 +47,21 @@ serai_test!(
         .collect::<Vec<_>>(),
     );
 
-    let set_data = serai.get_validator_set(set).await.unwrap().unwrap();
+    let latest = serai.get_latest_block_hash().await.unwrap();
+    assert_eq!(serai.get_session(latest, network).await.unwrap(), Some(Session(0)));
+
+    let set_data = serai.get_validator_set(latest, set).await.unwrap().unwrap();
     assert_eq!(set_data.network, NETWORKS[&NetworkId::Bitcoin]);
     let participants_ref: &[_] = set_data.participants.as_ref();
     assert_eq!(participants_ref, [(public, set_data.bond)].as_ref());
+    assert_eq!(
+      serai.get_validator_set_participants(latest, set).await.unwrap(),
+      Some(vec![(public, set_data.bond)])
+    );
+    assert_eq!(
+      serai.get_current_validator_set(latest, network).await.unwrap(),
+      Some((set, set_data))
+    );
 
     let block = vote_in_keys(set, key_pair.clone()).await;
 
@@ -63,6 +74,6 @@ serai_test!(
       serai.get_key_gen_events(block).await.unwrap(),
       vec![ValidatorSetsEvent::KeyGen { set, key_pair: key_pair.clone() }]
     );
-    assert_eq!(serai.get_keys(set).await.unwrap(), Some(key_pair));
+    assert_eq!(serai.get_keys(block, set).await.unwrap(), Some(key_pair));
   }
 );
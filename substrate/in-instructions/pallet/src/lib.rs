@@ -9,7 +9,7 @@ use sp_runtime::RuntimeDebug;
 use serai_primitives::{BlockHash, NetworkId};
 
 pub use in_instructions_primitives as primitives;
-use primitives::{InInstruction, InInstructionWithBalance, SignedBatch};
+use primitives::{MAX_BATCH_SIZE, InInstruction, InInstructionWithBalance, SignedBatch};
 
 #[derive(Clone, Copy, Encode, RuntimeDebug)]
 #[cfg_attr(feature = "std", derive(scale::Decode, thiserror::Error))]
@@ -67,7 +67,10 @@ pub mod pallet {
     fn execute(instruction: InInstructionWithBalance) -> Result<(), ()> {
       match instruction.instruction {
         InInstruction::Transfer(address) => Tokens::<T>::mint(address, instruction.balance),
-        _ => panic!("unsupported instruction"),
+        // TODO: Support these once the DEX exists
+        InInstruction::Call(_) |
+        InInstruction::Swap { .. } |
+        InInstruction::AddLiquidity { .. } => Err(())?,
       }
       Ok(())
     }
@@ -117,6 +120,10 @@ pub mod pallet {
         _ => Err(InvalidTransaction::Call)?,
       };
 
+      if batch.batch.encode().len() > MAX_BATCH_SIZE {
+        Err(InvalidTransaction::ExhaustsResources)?;
+      }
+
       let network = batch.batch.network;
 
       // TODO: Get the latest session
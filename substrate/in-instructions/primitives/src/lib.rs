@@ -17,11 +17,18 @@ use sp_application_crypto::sr25519::Signature;
 use sp_std::vec::Vec;
 use sp_runtime::RuntimeDebug;
 
-use serai_primitives::{BlockHash, Balance, NetworkId, SeraiAddress, ExternalAddress, Data};
+use serai_primitives::{
+  BlockHash, Coin, Amount, Balance, NetworkId, SeraiAddress, ExternalAddress, Data,
+};
+
+use tokens_primitives::OutInstruction;
 
 mod shorthand;
 pub use shorthand::*;
 
+/// The maximum size of an encoded batch.
+pub const MAX_BATCH_SIZE: usize = 25_000; // ~25kb
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Encode, Decode, MaxEncodedLen, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Zeroize, Serialize, Deserialize))]
 pub enum Application {
@@ -40,6 +47,10 @@ pub struct ApplicationCall {
 pub enum InInstruction {
   Transfer(SeraiAddress),
   Call(ApplicationCall),
+  /// Swap the received coin for another, sending the result out with the specified instruction.
+  Swap { coin: Coin, minimum: Amount, out: OutInstruction },
+  /// Add the received coin as liquidity, with gas being the amount of SRI to swap for.
+  AddLiquidity { minimum: Amount, gas: Amount, address: SeraiAddress },
 }
 
 #[derive(Clone, PartialEq, Eq, Encode, Decode, TypeInfo, RuntimeDebug)]
@@ -79,3 +90,46 @@ impl Zeroize for SignedBatch {
     self.signature.as_mut().zeroize();
   }
 }
+
+#[test]
+fn instruction_codec() {
+  let address = SeraiAddress([0xaa; 32]);
+  let out = OutInstruction {
+    address: ExternalAddress::new(vec![0xbb; 20]).unwrap(),
+    data: Some(Data::new(vec![0xcc; 32]).unwrap()),
+  };
+
+  for instruction in [
+    InInstruction::Transfer(address),
+    InInstruction::Call(ApplicationCall {
+      application: Application::DEX,
+      data: Data::new(vec![0xdd; 64]).unwrap(),
+    }),
+    InInstruction::Swap { coin: Coin::Monero, minimum: Amount(1), out },
+    InInstruction::AddLiquidity { minimum: Amount(2), gas: Amount(3), address },
+  ] {
+    let encoded = instruction.encode();
+    assert!(encoded.len() <= InInstruction::max_encoded_len());
+    assert_eq!(InInstruction::decode(&mut encoded.as_ref()).unwrap(), instruction);
+
+    let instruction = RefundableInInstruction {
+      origin: Some(ExternalAddress::new(vec![0xee; 32]).unwrap()),
+      instruction,
+    };
+    assert_eq!(
+      RefundableInInstruction::decode(&mut instruction.encode().as_ref()).unwrap(),
+      instruction
+    );
+  }
+}
+
+#[test]
+fn max_batch_size() {
+  // A batch of the largest possible instructions must be able to include at least one
+  let instruction = InInstructionWithBalance::max_encoded_len();
+  let batch_overhead =
+    Batch { network: NetworkId::Serai, id: 0, block: BlockHash([0; 32]), instructions: vec![] }
+      .encode()
+      .len();
+  assert!((batch_overhead + instruction) <= MAX_BATCH_SIZE);
+}
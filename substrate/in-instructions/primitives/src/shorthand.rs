@@ -11,9 +11,7 @@ use serai_primitives::{Coin, Amount, SeraiAddress, ExternalAddress, Data};
 
 use tokens_primitives::OutInstruction;
 
-use crate::RefundableInInstruction;
-#[cfg(feature = "std")]
-use crate::InInstruction;
+use crate::{InInstruction, RefundableInInstruction};
 
 #[derive(Clone, PartialEq, Eq, Debug, Encode, Decode, MaxEncodedLen, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Zeroize, Serialize, Deserialize))]
@@ -53,8 +51,14 @@ impl TryFrom<Shorthand> for RefundableInInstruction {
       Shorthand::Raw(raw) => {
         RefundableInInstruction::decode(&mut raw.data()).map_err(|_| "invalid raw instruction")?
       }
-      Shorthand::Swap { .. } => todo!(),
-      Shorthand::AddLiquidity { .. } => todo!(),
+      Shorthand::Swap { origin, coin, minimum, out } => RefundableInInstruction {
+        origin,
+        instruction: InInstruction::Swap { coin, minimum, out },
+      },
+      Shorthand::AddLiquidity { origin, minimum, gas, address } => RefundableInInstruction {
+        origin,
+        instruction: InInstruction::AddLiquidity { minimum, gas, address },
+      },
     })
   }
 }
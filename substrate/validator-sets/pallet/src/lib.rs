@@ -39,6 +39,11 @@ pub mod pallet {
   #[pallet::pallet]
   pub struct Pallet<T>(PhantomData<T>);
 
+  /// The current session for a network.
+  #[pallet::storage]
+  #[pallet::getter(fn session)]
+  pub type CurrentSession<T: Config> = StorageMap<_, Twox64Concat, NetworkId, Session, OptionQuery>;
+
   /// The details of a validator set instance.
   #[pallet::storage]
   #[pallet::getter(fn validator_set)]
@@ -94,6 +99,7 @@ pub mod pallet {
 
       for (id, network) in self.networks.clone() {
         let set = ValidatorSet { session: Session(0), network: id };
+        CurrentSession::<T>::set(id, Some(set.session));
         ValidatorSets::<T>::set(
           set,
           Some(ValidatorSetData { bond: self.bond, network, participants: participants.clone() }),
@@ -125,8 +131,7 @@ pub mod pallet {
       // The docs suggest the BoundedVec will create/write, yet not read, which could be an issue
       // if it can be passed in
 
-      // TODO: Get session
-      let session: Session = Session(0);
+      let session = Self::session(network).ok_or(Error::<T>::NonExistentValidatorSet)?;
 
       // Confirm a key hasn't been set for this set instance
       let set = ValidatorSet { session, network };